@@ -51,20 +51,12 @@ impl PartialEq<AbeIdentifier> for AbeAttribute {
     fn eq(&self, other: &AbeIdentifier) -> bool {
         self.name == other.name && self.index == other.index
     }
-
-    fn ne(&self, other: &AbeIdentifier) -> bool {
-        !self.eq(other)
-    }
 }
 
 impl PartialEq<AbeAttribute> for AbeIdentifier {
     fn eq(&self, other: &AbeAttribute) -> bool {
         self.name == other.name && self.index == other.index
     }
-
-    fn ne(&self, other: &AbeAttribute) -> bool {
-        !self.eq(other)
-    }
 }
 
 #[cfg(test)]
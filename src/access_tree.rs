@@ -51,6 +51,63 @@ impl AccessTree {
             }
         }
     }
+
+    /// Removes the values assigned by `AssignValues`, keeping the structure and indices
+    pub fn clear_values(&mut self) {
+        match self {
+            AccessTree::Operator(Operator {
+                left, right, value, ..
+            }) => {
+                *value = None;
+                left.clear_values();
+                right.clear_values();
+            }
+            AccessTree::Leaf(Leaf { attribute, value }) => {
+                *value = None;
+                attribute.value = None;
+            }
+        }
+    }
+
+    /// Replaces the attribute name of every leaf, keeping the structure, indices and values
+    pub fn rename_leaves<F: FnMut(&AbeAttribute) -> String>(&mut self, rename: &mut F) {
+        match self {
+            AccessTree::Operator(Operator { left, right, .. }) => {
+                left.rename_leaves(rename);
+                right.rename_leaves(rename);
+            }
+            AccessTree::Leaf(Leaf { attribute, .. }) => {
+                attribute.name = rename(attribute);
+            }
+        }
+    }
+
+    /// Returns leaves that satisfy the tree with the given attributes, taking the first
+    /// satisfiable branch of every OR, or `None` if the attributes do not satisfy the tree.
+    /// Unlike `find_minimal_set`, leaves sharing an attribute name are told apart.
+    pub fn find_satisfying_leaves(&self, attributes: &[AbeAttribute]) -> Option<Vec<AbeAttribute>> {
+        match self {
+            AccessTree::Operator(Operator {
+                operator,
+                left,
+                right,
+                ..
+            }) => match operator {
+                And => {
+                    let mut leaves = left.find_satisfying_leaves(attributes)?;
+                    leaves.append(&mut right.find_satisfying_leaves(attributes)?);
+                    Some(leaves)
+                }
+                Or => left
+                    .find_satisfying_leaves(attributes)
+                    .or_else(|| right.find_satisfying_leaves(attributes)),
+            },
+            AccessTree::Leaf(Leaf { attribute, .. }) => attributes
+                .iter()
+                .any(|a| a.name == attribute.name)
+                .then(|| vec![attribute.clone()]),
+        }
+    }
 }
 
 pub trait GetAttributes {
@@ -126,17 +183,14 @@ impl AssignValues for AccessTree {
 
 pub trait MinimalSetFinder {
     /// Checks if the given set of attributes satisfies the tree
-    fn is_satisfiable(&self, attributes: &Vec<AbeAttribute>) -> bool;
+    fn is_satisfiable(&self, attributes: &[AbeAttribute]) -> bool;
 
     /// Finds the minimal set of attributes that satisfies the tree, starting from the given set
-    fn find_minimal_set(
-        &self,
-        attributes: &Vec<AbeAttribute>,
-    ) -> Result<Vec<AbeAttribute>, AbeError>;
+    fn find_minimal_set(&self, attributes: &[AbeAttribute]) -> Result<Vec<AbeAttribute>, AbeError>;
 }
 
 impl MinimalSetFinder for AccessTree {
-    fn is_satisfiable(&self, attributes: &Vec<AbeAttribute>) -> bool {
+    fn is_satisfiable(&self, attributes: &[AbeAttribute]) -> bool {
         match self {
             AccessTree::Operator(Operator {
                 operator,
@@ -153,10 +207,7 @@ impl MinimalSetFinder for AccessTree {
         }
     }
 
-    fn find_minimal_set(
-        &self,
-        attributes: &Vec<AbeAttribute>,
-    ) -> Result<Vec<AbeAttribute>, AbeError> {
+    fn find_minimal_set(&self, attributes: &[AbeAttribute]) -> Result<Vec<AbeAttribute>, AbeError> {
         // If initial set does not satisfy we immediately return
         if !self.is_satisfiable(attributes) {
             println!("{:#?} {:#?}", self, attributes);
@@ -175,7 +226,7 @@ impl MinimalSetFinder for AccessTree {
             }
         }
 
-        return Ok(attributes.clone());
+        Ok(attributes.to_vec())
     }
 }
 
@@ -221,9 +272,9 @@ mod tests {
             operator: Or,
         });
 
-        assert_eq!(tree.is_satisfiable(&vec![AbeAttribute::new("A")]), true);
-        assert_eq!(tree.is_satisfiable(&vec![AbeAttribute::new("B")]), true);
-        assert_eq!(tree.is_satisfiable(&vec![AbeAttribute::new("C")]), false);
+        assert!(tree.is_satisfiable(&[AbeAttribute::new("A")]));
+        assert!(tree.is_satisfiable(&[AbeAttribute::new("B")]));
+        assert!(!tree.is_satisfiable(&[AbeAttribute::new("C")]));
     }
 
     #[test]
@@ -241,13 +292,10 @@ mod tests {
             operator: And,
         });
 
-        assert_eq!(tree.is_satisfiable(&vec![AbeAttribute::new("A")]), false);
-        assert_eq!(tree.is_satisfiable(&vec![AbeAttribute::new("B")]), false);
-        assert_eq!(tree.is_satisfiable(&vec![AbeAttribute::new("C")]), false);
-        assert_eq!(
-            tree.is_satisfiable(&vec![AbeAttribute::new("A"), AbeAttribute::new("B")]),
-            true
-        );
+        assert!(!tree.is_satisfiable(&[AbeAttribute::new("A")]));
+        assert!(!tree.is_satisfiable(&[AbeAttribute::new("B")]));
+        assert!(!tree.is_satisfiable(&[AbeAttribute::new("C")]));
+        assert!(tree.is_satisfiable(&[AbeAttribute::new("A"), AbeAttribute::new("B")]));
     }
 
     #[test]
@@ -266,17 +314,15 @@ mod tests {
         });
 
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("A")])
-                .unwrap(),
+            tree.find_minimal_set(&[AbeAttribute::new("A")]).unwrap(),
             vec![AbeAttribute::new("A")]
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("B")])
-                .unwrap(),
+            tree.find_minimal_set(&[AbeAttribute::new("B")]).unwrap(),
             vec![AbeAttribute::new("B")]
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("C")])
+            tree.find_minimal_set(&[AbeAttribute::new("C")])
                 .unwrap_err(),
             AbeError::new("Initial attribute set does not satisfy the tree")
         );
@@ -298,22 +344,22 @@ mod tests {
         });
 
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("A")])
+            tree.find_minimal_set(&[AbeAttribute::new("A")])
                 .unwrap_err(),
             AbeError::new("Initial attribute set does not satisfy the tree")
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("B")])
+            tree.find_minimal_set(&[AbeAttribute::new("B")])
                 .unwrap_err(),
             AbeError::new("Initial attribute set does not satisfy the tree")
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("C")])
+            tree.find_minimal_set(&[AbeAttribute::new("C")])
                 .unwrap_err(),
             AbeError::new("Initial attribute set does not satisfy the tree")
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("A"), AbeAttribute::new("B")])
+            tree.find_minimal_set(&[AbeAttribute::new("A"), AbeAttribute::new("B")])
                 .unwrap(),
             vec![AbeAttribute::new("A"), AbeAttribute::new("B")]
         );
@@ -353,97 +399,97 @@ mod tests {
         let abe_error = AbeError::new("Initial attribute set does not satisfy the tree");
 
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("A")])
+            tree.find_minimal_set(&[AbeAttribute::new("A")])
                 .unwrap_err(),
             abe_error
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("B")])
+            tree.find_minimal_set(&[AbeAttribute::new("B")])
                 .unwrap_err(),
             abe_error
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("C")])
+            tree.find_minimal_set(&[AbeAttribute::new("C")])
                 .unwrap_err(),
             abe_error
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("D")])
+            tree.find_minimal_set(&[AbeAttribute::new("D")])
                 .unwrap_err(),
             abe_error
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("A"), AbeAttribute::new("B")])
+            tree.find_minimal_set(&[AbeAttribute::new("A"), AbeAttribute::new("B")])
                 .unwrap(),
             vec![AbeAttribute::new("A"), AbeAttribute::new("B")]
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("A"), AbeAttribute::new("C")])
+            tree.find_minimal_set(&[AbeAttribute::new("A"), AbeAttribute::new("C")])
                 .unwrap_err(),
             abe_error
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("A"), AbeAttribute::new("D")])
+            tree.find_minimal_set(&[AbeAttribute::new("A"), AbeAttribute::new("D")])
                 .unwrap_err(),
             abe_error
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("B"), AbeAttribute::new("C")])
+            tree.find_minimal_set(&[AbeAttribute::new("B"), AbeAttribute::new("C")])
                 .unwrap_err(),
             abe_error
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("B"), AbeAttribute::new("D")])
+            tree.find_minimal_set(&[AbeAttribute::new("B"), AbeAttribute::new("D")])
                 .unwrap_err(),
             abe_error
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("C"), AbeAttribute::new("D")])
+            tree.find_minimal_set(&[AbeAttribute::new("C"), AbeAttribute::new("D")])
                 .unwrap(),
             vec![AbeAttribute::new("C"), AbeAttribute::new("D")]
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![
+            tree.find_minimal_set(&[
                 AbeAttribute::new("A"),
                 AbeAttribute::new("B"),
-                AbeAttribute::new("C"),
+                AbeAttribute::new("C")
             ])
             .unwrap(),
             vec![AbeAttribute::new("A"), AbeAttribute::new("B")]
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![
+            tree.find_minimal_set(&[
                 AbeAttribute::new("A"),
                 AbeAttribute::new("B"),
-                AbeAttribute::new("D"),
+                AbeAttribute::new("D")
             ])
             .unwrap(),
             vec![AbeAttribute::new("A"), AbeAttribute::new("B")]
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![
+            tree.find_minimal_set(&[
                 AbeAttribute::new("A"),
                 AbeAttribute::new("C"),
-                AbeAttribute::new("D"),
+                AbeAttribute::new("D")
             ])
             .unwrap(),
             vec![AbeAttribute::new("C"), AbeAttribute::new("D")]
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![
+            tree.find_minimal_set(&[
                 AbeAttribute::new("B"),
                 AbeAttribute::new("C"),
-                AbeAttribute::new("D"),
+                AbeAttribute::new("D")
             ])
             .unwrap(),
             vec![AbeAttribute::new("C"), AbeAttribute::new("D")]
         );
         assert_eq!(
-            tree.find_minimal_set(&vec![
+            tree.find_minimal_set(&[
                 AbeAttribute::new("A"),
                 AbeAttribute::new("B"),
                 AbeAttribute::new("C"),
-                AbeAttribute::new("D"),
+                AbeAttribute::new("D")
             ])
             .unwrap(),
             vec![AbeAttribute::new("A"), AbeAttribute::new("B")]
@@ -457,11 +503,11 @@ mod tests {
         let tree = parser.parse().unwrap();
 
         assert_eq!(
-            tree.find_minimal_set(&vec![
+            tree.find_minimal_set(&[
                 AbeAttribute::new("A"),
                 AbeAttribute::new("B"),
                 AbeAttribute::new("C"),
-                AbeAttribute::new("D"),
+                AbeAttribute::new("D")
             ])
             .unwrap(),
             vec![
@@ -479,7 +525,7 @@ mod tests {
         let tree = parser.parse().unwrap();
 
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("A"), AbeAttribute::new("B"),])
+            tree.find_minimal_set(&[AbeAttribute::new("A"), AbeAttribute::new("B")])
                 .unwrap(),
             vec![AbeAttribute::new("A")]
         );
@@ -520,9 +566,43 @@ mod tests {
         let tree = parser.parse().unwrap();
 
         assert_eq!(
-            tree.find_minimal_set(&vec![AbeAttribute::new("A"),])
-                .unwrap(),
+            tree.find_minimal_set(&[AbeAttribute::new("A")]).unwrap(),
             vec![AbeAttribute::new("A")]
         );
     }
+
+    #[test]
+    fn test_find_satisfying_leaves() {
+        let mut tree = AccessTreeParser::new("(A&B)|(A&C)").parse().unwrap();
+        tree.assign_indices();
+
+        let leaves = tree
+            .find_satisfying_leaves(&[AbeAttribute::new("A"), AbeAttribute::new("C")])
+            .unwrap();
+        assert_eq!(
+            leaves
+                .iter()
+                .map(|leaf| (leaf.name.as_str(), leaf.index))
+                .collect::<Vec<_>>(),
+            vec![("A", Some(2)), ("C", Some(3))]
+        );
+        assert!(tree
+            .find_satisfying_leaves(&[AbeAttribute::new("B"), AbeAttribute::new("C")])
+            .is_none());
+    }
+
+    #[test]
+    fn test_rename_leaves() {
+        let mut tree = AccessTreeParser::new("a:1&(b:2|a:3)").parse().unwrap();
+        tree.assign_indices();
+        tree.rename_leaves(&mut |leaf| format!("{}{}", leaf.name, leaf.index.unwrap()));
+
+        assert_eq!(
+            tree.get_attributes()
+                .iter()
+                .map(|leaf| (leaf.name.as_str(), leaf.index))
+                .collect::<Vec<_>>(),
+            vec![("a:10", Some(0)), ("b:21", Some(1)), ("a:32", Some(2))]
+        );
+    }
 }
@@ -0,0 +1,306 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::errors::armor_error::ArmorError;
+use crate::models::{
+    AbeCipherText, AbeDetachedEnvelope, AbeDetachedHeader, AbeEnvelope, AbeHiddenCipherText,
+    AbeKpCipherText, AbeKpSecretKey, AbeLuCipherText, AbeLuMasterKey, AbeLuPublicKey,
+    AbeLuSecretKey, AbeMaCipherText, AbeMaGlobalParameters, AbeMaMasterKey, AbeMaPublicKey,
+    AbeMaSecretKey, AbeMasterKey, AbeOnlineCipherText, AbeOutsourcedKey, AbePublicKey,
+    AbeRetrievalKey, AbeSecretKey, AbeTransformationKey, AbeTransformed, AbeWatersCipherText,
+    AbeWatersMasterKey, AbeWatersPublicKey, AbeWatersSecretKey,
+};
+
+const LINE_LENGTH: usize = 64;
+
+/// Text representation of a model, e.g.
+///
+/// ```text
+/// -----BEGIN ABE CIPHERTEXT-----
+/// eyJzeXN0ZW1faWQiOiIyNWUzYmQxMjk4OTFkZGQ5ODA4ZTZlN2JmOTljNmNjODli
+/// ...
+/// =nCZ5
+/// -----END ABE CIPHERTEXT-----
+/// ```
+///
+/// The body is the base64 encoded JSON serialization, followed by a CRC-24 checksum as in RFC 4880.
+pub trait Armored: Serialize + DeserializeOwned {
+    /// Label used in the BEGIN and END lines
+    const LABEL: &'static str;
+
+    fn to_armored(&self) -> Result<String, ArmorError> {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| ArmorError::new(format!("Could not serialize: {}", e).as_str()))?;
+        Ok(armor(Self::LABEL, &json))
+    }
+
+    fn from_armored(text: &str) -> Result<Self, ArmorError> {
+        let json = dearmor(Self::LABEL, text)?;
+        serde_json::from_slice(&json)
+            .map_err(|e| ArmorError::new(format!("Could not deserialize: {}", e).as_str()))
+    }
+}
+
+impl Armored for AbePublicKey {
+    const LABEL: &'static str = "ABE PUBLIC KEY";
+}
+
+impl Armored for AbeMasterKey {
+    const LABEL: &'static str = "ABE MASTER KEY";
+}
+
+impl Armored for AbeSecretKey {
+    const LABEL: &'static str = "ABE SECRET KEY";
+}
+
+impl Armored for AbeCipherText {
+    const LABEL: &'static str = "ABE CIPHERTEXT";
+}
+
+impl Armored for AbeDetachedHeader {
+    const LABEL: &'static str = "ABE HEADER";
+}
+
+impl Armored for AbeEnvelope {
+    const LABEL: &'static str = "ABE ENVELOPE";
+}
+
+impl Armored for AbeDetachedEnvelope {
+    const LABEL: &'static str = "ABE ENVELOPE HEADER";
+}
+
+impl Armored for AbeTransformationKey {
+    const LABEL: &'static str = "ABE TRANSFORMATION KEY";
+}
+
+impl Armored for AbeRetrievalKey {
+    const LABEL: &'static str = "ABE RETRIEVAL KEY";
+}
+
+impl Armored for AbeOutsourcedKey {
+    const LABEL: &'static str = "ABE OUTSOURCED KEY";
+}
+
+impl Armored for AbeTransformed {
+    const LABEL: &'static str = "ABE TRANSFORMED";
+}
+
+impl Armored for AbeHiddenCipherText {
+    const LABEL: &'static str = "ABE HIDDEN CIPHERTEXT";
+}
+
+impl Armored for AbeMaGlobalParameters {
+    const LABEL: &'static str = "ABE MA PARAMETERS";
+}
+
+impl Armored for AbeMaPublicKey {
+    const LABEL: &'static str = "ABE MA PUBLIC KEY";
+}
+
+impl Armored for AbeMaMasterKey {
+    const LABEL: &'static str = "ABE MA MASTER KEY";
+}
+
+impl Armored for AbeMaSecretKey {
+    const LABEL: &'static str = "ABE MA SECRET KEY";
+}
+
+impl Armored for AbeMaCipherText {
+    const LABEL: &'static str = "ABE MA CIPHERTEXT";
+}
+
+impl Armored for AbeOnlineCipherText {
+    const LABEL: &'static str = "ABE ONLINE CIPHERTEXT";
+}
+
+impl Armored for AbeKpSecretKey {
+    const LABEL: &'static str = "ABE KP SECRET KEY";
+}
+
+impl Armored for AbeKpCipherText {
+    const LABEL: &'static str = "ABE KP CIPHERTEXT";
+}
+
+impl Armored for AbeLuPublicKey {
+    const LABEL: &'static str = "ABE LU PUBLIC KEY";
+}
+
+impl Armored for AbeLuMasterKey {
+    const LABEL: &'static str = "ABE LU MASTER KEY";
+}
+
+impl Armored for AbeLuSecretKey {
+    const LABEL: &'static str = "ABE LU SECRET KEY";
+}
+
+impl Armored for AbeLuCipherText {
+    const LABEL: &'static str = "ABE LU CIPHERTEXT";
+}
+
+impl Armored for AbeWatersPublicKey {
+    const LABEL: &'static str = "ABE WATERS PUBLIC KEY";
+}
+
+impl Armored for AbeWatersMasterKey {
+    const LABEL: &'static str = "ABE WATERS MASTER KEY";
+}
+
+impl Armored for AbeWatersSecretKey {
+    const LABEL: &'static str = "ABE WATERS SECRET KEY";
+}
+
+impl Armored for AbeWatersCipherText {
+    const LABEL: &'static str = "ABE WATERS CIPHERTEXT";
+}
+
+/// Checks whether the text looks like armored data, ignoring surrounding whitespace
+pub fn is_armored(text: &str) -> bool {
+    text.trim_start().starts_with("-----BEGIN ")
+}
+
+/// Returns the label of the BEGIN line of armored data
+pub fn label(text: &str) -> Option<&str> {
+    text.trim_start()
+        .lines()
+        .next()?
+        .trim()
+        .strip_prefix("-----BEGIN ")?
+        .strip_suffix("-----")
+}
+
+/// Wraps the data in BEGIN and END lines with the given label
+pub fn armor(label: &str, data: &[u8]) -> String {
+    let body = STANDARD.encode(data);
+    let checksum = STANDARD.encode(&crc24(data).to_be_bytes()[1..]);
+
+    let mut armored = format!("-----BEGIN {}-----\n", label);
+    for line in body.as_bytes().chunks(LINE_LENGTH) {
+        // base64 output is ASCII, so every chunk is valid UTF-8
+        armored.push_str(std::str::from_utf8(line).unwrap());
+        armored.push('\n');
+    }
+    armored.push_str(&format!("={}\n", checksum));
+    armored.push_str(&format!("-----END {}-----\n", label));
+    armored
+}
+
+/// Extracts and verifies the data wrapped in BEGIN and END lines with the given label
+pub fn dearmor(label: &str, text: &str) -> Result<Vec<u8>, ArmorError> {
+    let begin = format!("-----BEGIN {}-----", label);
+    let end = format!("-----END {}-----", label);
+
+    let mut lines = text
+        .lines()
+        .map(|line| line.trim())
+        .skip_while(|line| line.is_empty());
+    match lines.next() {
+        Some(line) if line == begin => {}
+        Some(line) if line.starts_with("-----BEGIN ") => {
+            return Err(ArmorError::new(
+                format!("Expected {} but found {}", begin, line).as_str(),
+            ))
+        }
+        _ => return Err(ArmorError::new("Missing BEGIN line")),
+    }
+
+    let mut body = String::new();
+    let mut checksum = None;
+    let mut ended = false;
+    for line in lines.by_ref() {
+        if line == end {
+            ended = true;
+            break;
+        }
+        if let Some(crc) = line.strip_prefix('=') {
+            checksum = Some(crc.to_string());
+        } else if checksum.is_some() {
+            return Err(ArmorError::new("Unexpected data after checksum"));
+        } else {
+            body.push_str(line);
+        }
+    }
+
+    if !ended {
+        return Err(ArmorError::new("Missing END line"));
+    }
+    if lines.any(|line| !line.is_empty()) {
+        return Err(ArmorError::new("Unexpected data after END line"));
+    }
+
+    let data = STANDARD
+        .decode(body)
+        .map_err(|e| ArmorError::new(format!("Invalid base64 body: {}", e).as_str()))?;
+
+    let checksum = checksum.ok_or(ArmorError::new("Missing checksum"))?;
+    let checksum = STANDARD
+        .decode(checksum)
+        .map_err(|e| ArmorError::new(format!("Invalid base64 checksum: {}", e).as_str()))?;
+    if checksum[..] != crc24(&data).to_be_bytes()[1..] {
+        return Err(ArmorError::new("Checksum mismatch"));
+    }
+
+    Ok(data)
+}
+
+/// CRC-24 as defined in RFC 4880, section 6.1
+fn crc24(data: &[u8]) -> u32 {
+    const CRC24_INIT: u32 = 0xB704CE;
+    const CRC24_POLY: u32 = 0x1864CFB;
+
+    let mut crc = CRC24_INIT;
+    for byte in data {
+        crc ^= (*byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc & 0xFFFFFF
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::armor::{armor, crc24, dearmor, label};
+
+    #[test]
+    fn test_crc24() {
+        assert_eq!(crc24(b""), 0xB704CE);
+        assert_eq!(crc24(b"123456789"), 0x21CF02);
+    }
+
+    #[test]
+    fn test_armor_roundtrip() {
+        let data = (0..200).map(|i| i as u8).collect::<Vec<u8>>();
+        let armored = armor("ABE TEST", &data);
+
+        assert!(armored.starts_with("-----BEGIN ABE TEST-----\n"));
+        assert!(armored.ends_with("-----END ABE TEST-----\n"));
+        assert!(armored.lines().all(|line| line.len() <= 64));
+        assert_eq!(dearmor("ABE TEST", &armored).unwrap(), data);
+        assert_eq!(label(&armored), Some("ABE TEST"));
+        assert_eq!(label("{}"), None);
+    }
+
+    #[test]
+    fn test_dearmor_tolerates_whitespace() {
+        let armored = format!(
+            "\n  {}  \n",
+            armor("ABE TEST", b"data").replace('\n', "\r\n")
+        );
+
+        assert_eq!(dearmor("ABE TEST", &armored).unwrap(), b"data");
+    }
+
+    #[test]
+    fn test_dearmor_rejects_corruption() {
+        let armored = armor("ABE TEST", b"some data to protect");
+        let corrupted = armored.replacen("c29", "c39", 1);
+
+        assert!(dearmor("ABE TEST", &corrupted).is_err());
+        assert!(dearmor("ABE OTHER", &armored).is_err());
+    }
+}
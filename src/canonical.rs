@@ -0,0 +1,348 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::Read;
+
+use rabe_bn::{Fr, Group, Gt, G1, G2};
+use serde::de::Error;
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha3::{Digest, Sha3_256};
+
+use crate::abe_attribute::{AbeAttribute, AbeIdentifier};
+use crate::access_tree::{AccessTree, Leaf, Operator, TreeOperator};
+
+/// Deterministic byte encoding of a value, independent of platform, map ordering and the
+/// projective representation of group elements.
+///
+/// Variable length data is prefixed with its length as a big endian `u64`, optional values and
+/// enum variants with a single tag byte, group elements are normalized to affine coordinates.
+pub trait CanonicalEncode {
+    /// Appends the canonical encoding of `self` to `out`
+    fn encode_canonical(&self, out: &mut Vec<u8>);
+
+    /// Returns the canonical encoding of `self`
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_canonical(&mut out);
+        out
+    }
+}
+
+/// Returns the element in affine form, or the canonical identity if the element is zero.
+pub fn normalized<G: Group>(element: &G) -> G {
+    if element.is_zero() {
+        return G::zero();
+    }
+
+    let mut element = *element;
+    element.normalize();
+    element
+}
+
+impl CanonicalEncode for Fr {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        out.extend(self.into_bytes());
+    }
+}
+
+impl CanonicalEncode for G1 {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        out.extend(normalized(self).into_bytes());
+    }
+}
+
+impl CanonicalEncode for G2 {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        out.extend(normalized(self).into_bytes());
+    }
+}
+
+impl CanonicalEncode for Gt {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        out.extend(self.into_bytes());
+    }
+}
+
+impl CanonicalEncode for u32 {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        out.extend(self.to_be_bytes());
+    }
+}
+
+impl CanonicalEncode for u64 {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        out.extend(self.to_be_bytes());
+    }
+}
+
+impl CanonicalEncode for usize {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for [u8] {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.len().encode_canonical(out);
+        out.extend_from_slice(self);
+    }
+}
+
+impl CanonicalEncode for str {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.as_bytes().encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for String {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.as_str().encode_canonical(out);
+    }
+}
+
+impl<T: CanonicalEncode> CanonicalEncode for Option<T> {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(value) => {
+                out.push(1);
+                value.encode_canonical(out);
+            }
+        }
+    }
+}
+
+impl<T: CanonicalEncode + ?Sized> CanonicalEncode for Box<T> {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.as_ref().encode_canonical(out);
+    }
+}
+
+impl<A: CanonicalEncode, B: CanonicalEncode> CanonicalEncode for (A, B) {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.0.encode_canonical(out);
+        self.1.encode_canonical(out);
+    }
+}
+
+impl<T: CanonicalEncode> CanonicalEncode for Vec<T> {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.len().encode_canonical(out);
+        for item in self {
+            item.encode_canonical(out);
+        }
+    }
+}
+
+impl<K: CanonicalEncode, V: CanonicalEncode> CanonicalEncode for BTreeMap<K, V> {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.len().encode_canonical(out);
+        for (key, value) in self {
+            key.encode_canonical(out);
+            value.encode_canonical(out);
+        }
+    }
+}
+
+impl CanonicalEncode for AbeIdentifier {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.index.encode_canonical(out);
+        self.name.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeAttribute {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.index.encode_canonical(out);
+        self.name.encode_canonical(out);
+        self.value.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AccessTree {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        match self {
+            AccessTree::Operator(Operator {
+                operator,
+                left,
+                right,
+                value,
+            }) => {
+                out.push(match operator {
+                    TreeOperator::Or => 0,
+                    TreeOperator::And => 1,
+                });
+                value.encode_canonical(out);
+                left.encode_canonical(out);
+                right.encode_canonical(out);
+            }
+            AccessTree::Leaf(Leaf { attribute, value }) => {
+                out.push(2);
+                value.encode_canonical(out);
+                attribute.encode_canonical(out);
+            }
+        }
+    }
+}
+
+/// A model with a stable identifier, see `Fingerprinted::fingerprint`
+pub trait Fingerprinted: CanonicalEncode {
+    /// Domain label of the fingerprint, unique per model
+    const FINGERPRINT_LABEL: &'static str;
+
+    /// Stable identifier derived from the canonical encoding, unaffected by how the model was
+    /// serialized
+    fn fingerprint(&self) -> Fingerprint {
+        Fingerprint::of(Self::FINGERPRINT_LABEL, self)
+    }
+}
+
+/// SHA3-256 digest over a domain label and the canonical encoding of a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint(pub [u8; 32]);
+
+impl Fingerprint {
+    pub fn of<T: CanonicalEncode + ?Sized>(label: &str, value: &T) -> Fingerprint {
+        let mut hasher = Sha3_256::new();
+        hasher.update(label.to_canonical_bytes());
+        hasher.update(value.to_canonical_bytes());
+        Fingerprint(hasher.finalize().into())
+    }
+
+    /// Same as `of` for a byte string of `length` bytes read from `reader`, which is hashed
+    /// without being held in memory
+    pub fn of_reader<R: Read>(label: &str, length: u64, reader: R) -> io::Result<Fingerprint> {
+        let mut hasher = Sha3_256::new();
+        hasher.update(label.to_canonical_bytes());
+        hasher.update(length.to_be_bytes());
+        if io::copy(&mut reader.take(length), &mut hasher)? != length {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Input is shorter than its length",
+            ));
+        }
+        Ok(Fingerprint(hasher.finalize().into()))
+    }
+
+    /// Parses a fingerprint from its 64 character hexadecimal representation
+    pub fn from_hex(hex: &str) -> Option<Fingerprint> {
+        if hex.len() != 64 || !hex.is_ascii() {
+            return None;
+        }
+
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+        }
+        Some(Fingerprint(bytes))
+    }
+}
+
+impl CanonicalEncode for Fingerprint {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.0);
+    }
+}
+
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Fingerprint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Fingerprint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        Fingerprint::from_hex(&hex).ok_or(D::Error::custom("invalid fingerprint"))
+    }
+}
+
+/// Serializes a group element in normalized form, for use with `#[serde(serialize_with)]`
+pub fn serialize_group<G: Group, S: Serializer>(
+    element: &G,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    normalized(element).serialize(serializer)
+}
+
+/// Serializes a map of group elements in key order with normalized values
+pub fn serialize_group_map<G: Group, S: Serializer>(
+    map: &BTreeMap<String, G>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut state = serializer.serialize_map(Some(map.len()))?;
+    for (key, value) in map {
+        state.serialize_entry(key, &normalized(value))?;
+    }
+    state.end()
+}
+
+/// Serializes a list of identified group elements with normalized values
+pub fn serialize_group_pairs<G: Group, S: Serializer>(
+    pairs: &[(AbeIdentifier, G)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut state = serializer.serialize_seq(Some(pairs.len()))?;
+    for (identifier, value) in pairs {
+        state.serialize_element(&(identifier, normalized(value)))?;
+    }
+    state.end()
+}
+
+#[cfg(test)]
+mod tests {
+    use rabe_bn::{Fr, Group, G1};
+
+    use crate::canonical::{CanonicalEncode, Fingerprint};
+
+    #[test]
+    fn test_projective_representations_encode_equal() {
+        let a = G1::one() * Fr::from_str("5").unwrap();
+        let b = (G1::one() * Fr::from_str("2").unwrap()) + (G1::one() * Fr::from_str("3").unwrap());
+
+        assert_eq!(a, b);
+        assert_eq!(a.to_canonical_bytes(), b.to_canonical_bytes());
+    }
+
+    #[test]
+    fn test_zero_encodes_as_identity() {
+        let zero = G1::one() - G1::one();
+
+        assert_eq!(zero.to_canonical_bytes(), G1::zero().to_canonical_bytes());
+    }
+
+    #[test]
+    fn test_fingerprint_hex_roundtrip() {
+        let fingerprint = Fingerprint::of("test", "value");
+        let hex = fingerprint.to_string();
+
+        assert_eq!(hex.len(), 64);
+        assert_eq!(Fingerprint::from_hex(&hex), Some(fingerprint));
+        assert_eq!(Fingerprint::from_hex("zz"), None);
+    }
+
+    #[test]
+    fn test_fingerprint_of_reader() {
+        let value = b"streamed value".to_vec();
+        let fingerprint =
+            Fingerprint::of_reader("test", value.len() as u64, value.as_slice()).unwrap();
+
+        assert_eq!(fingerprint, Fingerprint::of("test", value.as_slice()));
+        assert!(Fingerprint::of_reader("test", 100, value.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_fingerprint_domain_separation() {
+        assert_ne!(Fingerprint::of("a", "value"), Fingerprint::of("b", "value"));
+    }
+}
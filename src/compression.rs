@@ -0,0 +1,165 @@
+use std::io;
+use std::io::{Read, Write};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+
+/// Raw DEFLATE (RFC 1951)
+pub const DEFLATE: &str = "DEFLATE";
+
+/// Largest decompressed payload accepted unless a different limit is given. Compressed data
+/// comes from the cipher text and is not trusted, a few kilobytes can expand to gigabytes.
+pub const DEFAULT_SIZE_LIMIT: u64 = 1 << 30;
+
+/// Whether the compression algorithm with the given identifier is available
+pub fn is_supported(algorithm: &str) -> bool {
+    algorithm == DEFLATE
+}
+
+fn check_algorithm(algorithm: &str) -> io::Result<()> {
+    if !is_supported(algorithm) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unknown compression algorithm {}", algorithm),
+        ));
+    }
+    Ok(())
+}
+
+/// Compresses everything written to it and writes the result to the inner writer.
+///
+/// `finish` has to be called to write the end of the compressed data.
+pub struct Compressor<W: Write> {
+    encoder: DeflateEncoder<W>,
+}
+
+impl<W: Write> Compressor<W> {
+    pub fn new(algorithm: &str, inner: W) -> io::Result<Compressor<W>> {
+        check_algorithm(algorithm)?;
+        Ok(Compressor {
+            encoder: DeflateEncoder::new(inner, Compression::default()),
+        })
+    }
+
+    /// Writes the remaining compressed data and returns the inner writer
+    pub fn finish(self) -> io::Result<W> {
+        self.encoder.finish()
+    }
+}
+
+impl<W: Write> Write for Compressor<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.flush()
+    }
+}
+
+/// Decompresses the data read from the inner reader, failing as soon as more than `limit`
+/// bytes were decompressed
+pub struct Decompressor<R: Read> {
+    decoder: DeflateDecoder<R>,
+    remaining: u64,
+    exceeded: bool,
+}
+
+impl<R: Read> Decompressor<R> {
+    pub fn new(algorithm: &str, inner: R, limit: u64) -> io::Result<Decompressor<R>> {
+        check_algorithm(algorithm)?;
+        Ok(Decompressor {
+            decoder: DeflateDecoder::new(inner),
+            remaining: limit,
+            exceeded: false,
+        })
+    }
+
+    /// Whether reading failed because the size limit was exceeded
+    pub fn limit_exceeded(&self) -> bool {
+        self.exceeded
+    }
+}
+
+impl<R: Read> Read for Decompressor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // read at most one byte past the limit, which is enough to tell it was exceeded
+        let length = buf.len().min(self.remaining.saturating_add(1) as usize);
+        let read = self.decoder.read(&mut buf[..length])?;
+        if read as u64 > self.remaining {
+            self.exceeded = true;
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Decompressed payload exceeds the size limit",
+            ));
+        }
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Compresses data held in memory
+pub fn compress(algorithm: &str, data: &[u8]) -> Result<Vec<u8>, AbeError> {
+    let to_error = |e: io::Error| AbeError::new(format!("Could not compress: {}", e).as_str());
+    let mut compressor = Compressor::new(algorithm, Vec::new()).map_err(to_error)?;
+    compressor.write_all(data).map_err(to_error)?;
+    compressor.finish().map_err(to_error)
+}
+
+/// Decompresses data held in memory, failing if it expands to more than `limit` bytes
+pub fn decompress(algorithm: &str, data: &[u8], limit: u64) -> Result<Vec<u8>, AbeError> {
+    let mut decompressor = Decompressor::new(algorithm, data, limit)
+        .map_err(|e| AbeError::new(format!("Could not decompress: {}", e).as_str()))?;
+    let mut decompressed = Vec::new();
+    decompressor.read_to_end(&mut decompressed).map_err(|e| {
+        if decompressor.limit_exceeded() {
+            size_limit_error(limit)
+        } else {
+            AbeError::new(format!("Could not decompress: {}", e).as_str())
+        }
+    })?;
+    Ok(decompressed)
+}
+
+pub(crate) fn size_limit_error(limit: u64) -> AbeError {
+    AbeError::with_kind(
+        AbeErrorKind::SizeLimitExceeded,
+        format!("Decompressed payload exceeds {} bytes", limit).as_str(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compression::{compress, decompress, DEFLATE};
+    use crate::errors::abe_error::AbeErrorKind;
+
+    #[test]
+    fn test_roundtrip() {
+        let data = "{\"level\": \"info\", \"message\": \"hello\"}\n".repeat(100);
+        let compressed = compress(DEFLATE, data.as_bytes()).unwrap();
+
+        assert!(compressed.len() < data.len());
+        assert_eq!(
+            decompress(DEFLATE, &compressed, data.len() as u64).unwrap(),
+            data.as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let compressed = compress(DEFLATE, &vec![0u8; 1 << 20]).unwrap();
+
+        assert_eq!(
+            decompress(DEFLATE, &compressed, 1000).err().unwrap().kind,
+            AbeErrorKind::SizeLimitExceeded
+        );
+    }
+
+    #[test]
+    fn test_unknown_algorithm() {
+        assert!(compress("ZIP", b"data").is_err());
+    }
+}
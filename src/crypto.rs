@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{Read, Seek, Write};
 
 use rabe_bn::{pairing, Fr, Gt, G1, G2};
 use rand::Rng;
@@ -6,20 +8,126 @@ use rand::Rng;
 use crate::abe_attribute::{AbeAttribute, AbeIdentifier};
 use crate::access_tree::{AccessTree, AssignValues, GetAttributes, MinimalSetFinder};
 use crate::aes;
-use crate::errors::abe_error::AbeError;
-use crate::models::{AbeCipherText, AbeDecrypted, AbeMasterKey, AbePublicKey, AbeSecretKey};
+use crate::canonical::{CanonicalEncode, Fingerprint, Fingerprinted};
+use crate::compression;
+use crate::compression::{Compressor, Decompressor};
+use crate::dem::{Aes256Gcm, BuiltinDems, DataEncapsulation, DemResolver};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+use crate::kdf;
+use crate::kdf::KeySchedule;
+use crate::models::{
+    AbeCipherText, AbeDecrypted, AbeEnvelope, AbeEnvelopeHeader, AbeHeader, AbeMasterKey,
+    AbePublicKey, AbeRecipient, AbeRetrievalKey, AbeSecretKey, AbeTransformationKey,
+    AbeTransformed, CipherTextHeader,
+};
+use crate::padding;
+use crate::padding::{Padder, Padding, Unpadder};
+use crate::stream;
+use crate::stream::{SeekableDecryptor, StreamDecryptor, StreamEncryptor};
+
+/// Algorithm recorded in headers made by `encapsulate`, whose key is used outside of this crate
+pub const EXTERNAL_ALGORITHM: &str = "EXTERNAL";
+
+/// How the message is turned into the symmetric payload, recorded in the header
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PayloadOptions {
+    /// Plaintext bytes per segment, or `None` for a payload sealed at once. Streams default to
+    /// `stream::DEFAULT_SEGMENT_SIZE`.
+    pub segment_size: Option<usize>,
+    /// Algorithm to compress the message with before encryption, see `compression`. Compression
+    /// reveals how well the message compresses through the payload length, which can leak
+    /// secrets that are mixed with attacker controlled data.
+    pub compression: Option<String>,
+    /// Padding that hides the exact length of the (compressed) message, see `padding`
+    pub padding: Option<Padding>,
+}
+
+impl PayloadOptions {
+    /// Checks that the segment size, compression algorithm and padding can be used
+    pub fn validate(&self) -> Result<(), AbeError> {
+        if self.segment_size == Some(0) {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::InvalidSegmentSize,
+                "Segment size must be positive",
+            ));
+        }
+        if let Some(algorithm) = &self.compression {
+            if !compression::is_supported(algorithm) {
+                return Err(AbeError::with_kind(
+                    AbeErrorKind::UnknownAlgorithm,
+                    format!("Unknown compression algorithm {}", algorithm).as_str(),
+                ));
+            }
+        }
+        if let Some(padding) = &self.padding {
+            padding.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// Writer returned by `encrypt_stream`, which compresses and pads the message first if requested
+pub struct PayloadWriter<'a, W: Write> {
+    writer: Box<dyn FinishWrite<W> + 'a>,
+}
+
+impl<W: Write> PayloadWriter<'_, W> {
+    /// Encrypts the remaining data and returns the inner writer
+    pub fn finish(self) -> io::Result<W> {
+        self.writer.finish_boxed()
+    }
+}
+
+impl<W: Write> Write for PayloadWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// A layer of a `PayloadWriter` that has to write its remaining data once the message is complete
+trait FinishWrite<W>: Write {
+    fn finish_boxed(self: Box<Self>) -> io::Result<W>;
+}
+
+impl<W> FinishWrite<W> for Box<dyn FinishWrite<W> + '_> {
+    fn finish_boxed(self: Box<Self>) -> io::Result<W> {
+        (*self).finish_boxed()
+    }
+}
+
+impl<W: Write> FinishWrite<W> for StreamEncryptor<'_, W> {
+    fn finish_boxed(self: Box<Self>) -> io::Result<W> {
+        self.finish()
+    }
+}
+
+impl<W, T: FinishWrite<W>> FinishWrite<W> for Compressor<T> {
+    fn finish_boxed(self: Box<Self>) -> io::Result<W> {
+        Box::new(self.finish()?).finish_boxed()
+    }
+}
+
+impl<W, T: FinishWrite<W>> FinishWrite<W> for Padder<T> {
+    fn finish_boxed(self: Box<Self>) -> io::Result<W> {
+        Box::new(self.finish()?).finish_boxed()
+    }
+}
 
 pub fn setup<R: Rng + ?Sized>(
-    attributes: &Vec<String>,
+    attributes: &[String],
     g: G1,
     g2: G2,
     rng: &mut R,
 ) -> (AbePublicKey, AbeMasterKey) {
     // Generate random elements for each attribute
     // tj = random field element
-    let mut small_t = HashMap::new();
-    for i in 0..attributes.len() {
-        small_t.insert(attributes[i].clone(), rng.gen());
+    let mut small_t = BTreeMap::new();
+    for attribute in attributes {
+        small_t.insert(attribute.clone(), rng.gen());
     }
     let alpha: Fr = rng.gen();
 
@@ -28,9 +136,9 @@ pub fn setup<R: Rng + ?Sized>(
     let y = pair.pow(alpha);
 
     // Tj = g^tj
-    let mut big_t = HashMap::new();
-    for i in 0..attributes.len() {
-        big_t.insert(attributes[i].clone(), g * small_t[&attributes[i]]);
+    let mut big_t = BTreeMap::new();
+    for attribute in attributes {
+        big_t.insert(attribute.clone(), g * small_t[attribute]);
     }
 
     (
@@ -48,14 +156,14 @@ pub fn setup<R: Rng + ?Sized>(
 pub fn adapt<R: Rng + ?Sized>(
     public_key: &AbePublicKey,
     master_key: &AbeMasterKey,
-    new_attributes: &Vec<String>,
+    new_attributes: &[String],
     rng: &mut R,
 ) -> (AbePublicKey, AbeMasterKey) {
     // Generate random elements for each attribute
     // tj = random field element
     let mut small_t = master_key.small_t.clone();
-    for i in 0..new_attributes.len() {
-        small_t.insert(new_attributes[i].clone(), rng.gen());
+    for attribute in new_attributes {
+        small_t.insert(attribute.clone(), rng.gen());
     }
 
     // y=e(g1,g2)^alpha
@@ -64,11 +172,8 @@ pub fn adapt<R: Rng + ?Sized>(
 
     // Tj = g^tj
     let mut big_t = public_key.big_t.clone();
-    for i in 0..new_attributes.len() {
-        big_t.insert(
-            new_attributes[i].clone(),
-            public_key.g1 * small_t[&new_attributes[i]],
-        );
+    for attribute in new_attributes {
+        big_t.insert(attribute.clone(), public_key.g1 * small_t[attribute]);
     }
 
     (
@@ -86,7 +191,7 @@ pub fn adapt<R: Rng + ?Sized>(
     )
 }
 pub fn keygen<R: Rng + ?Sized>(
-    attributes: &Vec<String>,
+    attributes: &[String],
     public_key: &AbePublicKey,
     master_key: &AbeMasterKey,
     rng: &mut R,
@@ -99,9 +204,17 @@ pub fn keygen<R: Rng + ?Sized>(
     // dj = g2^(r * tj^-1)
     let arr_d = attributes.iter().map(|a| {
         let clone = a.clone();
-        let inverse = master_key.small_t[a].inverse().ok_or(AbeError::new(
-            format!("Could not calculate inverse of {}", a).as_str(),
-        ));
+        let inverse = master_key
+            .small_t
+            .get(a)
+            .ok_or(AbeError::new(
+                format!("Attribute {} not found in master key", a).as_str(),
+            ))
+            .and_then(|t| {
+                t.inverse().ok_or(AbeError::new(
+                    format!("Could not calculate inverse of {}", a).as_str(),
+                ))
+            });
         match inverse {
             Ok(inverse) => Ok((clone, public_key.g2 * (r * inverse))),
             Err(e) => Err(e),
@@ -115,7 +228,7 @@ pub fn keygen<R: Rng + ?Sized>(
         .map(|d| d.err().unwrap())
         .collect::<Vec<AbeError>>();
 
-    if errors.len() > 0 {
+    if !errors.is_empty() {
         let mut error_message = String::from("Could not calculate dj for attributes: ");
         for error in errors {
             error_message.push_str(&format!("{:?}, ", error));
@@ -124,18 +237,300 @@ pub fn keygen<R: Rng + ?Sized>(
     }
 
     Ok(AbeSecretKey {
+        system_id: public_key.system_id(),
+        d_0,
+        arr_d: arr_d.map(|d| d.unwrap()).collect::<BTreeMap<String, G2>>(),
+        arr_e: BTreeMap::new(),
+    })
+}
+
+/// Like `keygen`, additionally giving the key `ej = g2^(tj^-1)` for each of its attributes, so
+/// its holder can derive keys for subsets of them with `delegate`.
+///
+/// Only issue these keys to holders that may hand out their attributes on their own. The `ej`
+/// are the same in every delegable key with attribute j and are not bound to the key, so a
+/// leaked one cannot be traced to a holder, and every key delegated from it can delegate
+/// further. Collusion resistance is unaffected as far as known: combining keys needs
+/// `dj = ej^r` for the `r` of one of them, which no component reveals. The `ej` do give
+/// anyone holding one a test of its attribute against a leaf of a hidden policy,
+/// `e(ui, ej) = e(vi, g2)`, without the `d0` and `dj` of a key, see `hidden`.
+pub fn keygen_delegable<R: Rng + ?Sized>(
+    attributes: &[String],
+    public_key: &AbePublicKey,
+    master_key: &AbeMasterKey,
+    rng: &mut R,
+) -> Result<AbeSecretKey, AbeError> {
+    if attributes.is_empty() {
+        return Err(AbeError::new("No attributes given"));
+    }
+
+    // ej = g2^(tj^-1)
+    let mut arr_e = BTreeMap::new();
+    for a in attributes {
+        let t = master_key.small_t.get(a).ok_or(AbeError::new(
+            format!("Attribute {} not found in master key", a).as_str(),
+        ))?;
+        let inverse = t.inverse().ok_or(AbeError::new(
+            format!("Could not calculate inverse of {}", a).as_str(),
+        ))?;
+        arr_e.insert(a.clone(), public_key.g2 * inverse);
+    }
+
+    // d0 = g2^(alpha-r), dj = ej^r = g2^(r * tj^-1)
+    let r: Fr = rng.gen();
+    let d_0 = public_key.g2 * (master_key.alpha - r);
+    let arr_d = arr_e
+        .iter()
+        .map(|(a, e)| (a.clone(), *e * r))
+        .collect::<BTreeMap<String, G2>>();
+
+    Ok(AbeSecretKey {
+        system_id: public_key.system_id(),
+        d_0,
+        arr_d,
+        arr_e,
+    })
+}
+
+/// Derives a key for a subset of the attributes of the secret key, without the master key. `r`
+/// is re-randomized, so the derived key cannot be linked to the key it was derived from. Only
+/// keys from `keygen_delegable` can be delegated, the derived key can be delegated further.
+pub fn delegate<R: Rng + ?Sized>(
+    attributes: &[String],
+    public_key: &AbePublicKey,
+    secret_key: &AbeSecretKey,
+    rng: &mut R,
+) -> Result<AbeSecretKey, AbeError> {
+    if attributes.is_empty() {
+        return Err(AbeError::new("No attributes given"));
+    }
+    if secret_key.system_id != public_key.system_id() {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::SystemMismatch,
+            "Secret key belongs to a different system than the public key",
+        ));
+    }
+
+    let not_found = attributes
+        .iter()
+        .filter(|a| !secret_key.arr_d.contains_key(*a))
+        .collect::<Vec<&String>>();
+    if !not_found.is_empty() {
+        return Err(AbeError::new(
+            format!("Attributes not found in secret key: {:?}", not_found).as_str(),
+        ));
+    }
+    let not_delegable = attributes
+        .iter()
+        .filter(|a| !secret_key.arr_e.contains_key(*a))
+        .collect::<Vec<&String>>();
+    if !not_delegable.is_empty() {
+        return Err(AbeError::new(
+            format!(
+                "Secret key cannot delegate attributes, it was not issued with keygen_delegable: {:?}",
+                not_delegable
+            )
+            .as_str(),
+        ));
+    }
+
+    // r' = r + delta
+    let delta: Fr = rng.gen();
+
+    // d0' = d0 * g2^-delta = g2^(alpha - r')
+    let d_0 = secret_key.d_0 - public_key.g2 * delta;
+
+    // dj' = dj * ej^delta = g2^(r' * tj^-1)
+    let mut arr_d = BTreeMap::new();
+    let mut arr_e = BTreeMap::new();
+    for a in attributes {
+        let e = secret_key.arr_e[a];
+        arr_d.insert(a.clone(), secret_key.arr_d[a] + e * delta);
+        arr_e.insert(a.clone(), e);
+    }
+
+    Ok(AbeSecretKey {
+        system_id: secret_key.system_id,
         d_0,
-        arr_d: arr_d.map(|d| d.unwrap()).collect::<HashMap<String, G2>>(),
+        arr_d,
+        arr_e,
     })
 }
 
 pub fn encrypt<R: Rng + ?Sized>(
     secret: &Gt,
-    message: &Vec<u8>,
+    message: &[u8],
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeCipherText, AbeError> {
+    encrypt_with(&Aes256Gcm, secret, message, public_key, access_tree, rng)
+}
+
+/// Like `encrypt`, protecting the payload with the given data encapsulation mechanism
+pub fn encrypt_with<R: Rng + ?Sized>(
+    dem: &dyn DataEncapsulation,
+    secret: &Gt,
+    message: &[u8],
     public_key: &AbePublicKey,
     access_tree: &AccessTree,
     rng: &mut R,
 ) -> Result<AbeCipherText, AbeError> {
+    let mut buffer = Vec::with_capacity(message.len() + dem.overhead());
+    buffer.extend_from_slice(message);
+    let header = encrypt_in_place(dem, secret, &mut buffer, public_key, access_tree, rng)?;
+
+    Ok(AbeCipherText {
+        header,
+        message: buffer,
+    })
+}
+
+/// Like `encrypt_with`, replacing the message in `buffer` with the payload instead of copying
+/// it. Nothing is allocated for the payload if `buffer` has capacity for `dem.overhead()` more
+/// bytes.
+pub fn encrypt_in_place<R: Rng + ?Sized>(
+    dem: &dyn DataEncapsulation,
+    secret: &Gt,
+    buffer: &mut Vec<u8>,
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeHeader, AbeError> {
+    let header = encapsulate_secret(
+        dem.algorithm(),
+        &PayloadOptions::default(),
+        secret,
+        public_key,
+        access_tree,
+        rng,
+    )?;
+    dem.encrypt_in_place(&payload_key(secret, &header), buffer)?;
+
+    Ok(header)
+}
+
+/// Like `encrypt_with`, segmenting and compressing the payload as given by the options
+pub fn encrypt_with_options<R: Rng + ?Sized>(
+    dem: &dyn DataEncapsulation,
+    options: &PayloadOptions,
+    secret: &Gt,
+    message: &[u8],
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeCipherText, AbeError> {
+    options.validate()?;
+    let mut buffer = match &options.compression {
+        None => message.to_vec(),
+        Some(algorithm) => compression::compress(algorithm, message)?,
+    };
+    if let Some(padding) = &options.padding {
+        padding::pad(padding, &mut buffer, rng)?;
+    }
+    let header = encapsulate_secret(
+        dem.algorithm(),
+        options,
+        secret,
+        public_key,
+        access_tree,
+        rng,
+    )?;
+    let key = payload_key(secret, &header);
+    match options.segment_size {
+        None => dem.encrypt_in_place(&key, &mut buffer)?,
+        Some(segment_size) => {
+            stream::encrypt_segmented_in_place(dem, &key, segment_size, &mut buffer, rng)?
+        }
+    }
+
+    Ok(AbeCipherText {
+        header,
+        message: buffer,
+    })
+}
+
+/// Starts a segmented encryption whose payload is written to `output`, so arbitrarily large
+/// messages can be encrypted with only one segment held in memory.
+///
+/// Returns the header needed for decryption and the writer the message has to be written to.
+/// `PayloadWriter::finish` must be called once the whole message was written.
+pub fn encrypt_stream<'a, R: Rng + ?Sized, W: Write + 'a>(
+    dem: &'a dyn DataEncapsulation,
+    secret: &Gt,
+    options: &PayloadOptions,
+    output: W,
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<(AbeHeader, PayloadWriter<'a, W>), AbeError> {
+    options.validate()?;
+    let segment_size = options.segment_size.unwrap_or(stream::DEFAULT_SEGMENT_SIZE);
+    let options = PayloadOptions {
+        segment_size: Some(segment_size),
+        ..options.clone()
+    };
+    let header = encapsulate_secret(
+        dem.algorithm(),
+        &options,
+        secret,
+        public_key,
+        access_tree,
+        rng,
+    )?;
+
+    let to_error =
+        |e: io::Error| AbeError::new(format!("Could not encrypt stream: {}", e).as_str());
+    let encryptor = StreamEncryptor::new(
+        dem,
+        &payload_key(secret, &header),
+        segment_size,
+        output,
+        rng,
+    )
+    .map_err(to_error)?;
+    // the message is compressed, then padded, then encrypted
+    let mut writer: Box<dyn FinishWrite<W> + 'a> = Box::new(encryptor);
+    if let Some(padding) = options.padding {
+        writer = Box::new(Padder::new(padding, writer, rng));
+    }
+    if let Some(algorithm) = &options.compression {
+        writer = Box::new(Compressor::new(algorithm, writer).map_err(to_error)?);
+    }
+
+    Ok((header, PayloadWriter { writer }))
+}
+
+/// Encapsulates a fresh random key under the access tree, for callers that encrypt their data
+/// outside of this crate. The key is recovered from the returned header with `decapsulate`.
+pub fn encapsulate<R: Rng + ?Sized>(
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<(AbeHeader, [u8; kdf::KEY_SIZE]), AbeError> {
+    let secret: Gt = rng.gen();
+    let header = encapsulate_secret(
+        EXTERNAL_ALGORITHM,
+        &PayloadOptions::default(),
+        &secret,
+        public_key,
+        access_tree,
+        rng,
+    )?;
+    let key = KeySchedule::for_header(&secret, &header).derive(kdf::EXTERNAL_KEY);
+
+    Ok((header, key))
+}
+
+// Encrypts the secret under the access tree
+fn encapsulate_secret<R: Rng + ?Sized>(
+    algorithm: &str,
+    options: &PayloadOptions,
+    secret: &Gt,
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeHeader, AbeError> {
     // s = random field element
     let s = rng.gen();
 
@@ -145,8 +540,8 @@ pub fn encrypt<R: Rng + ?Sized>(
     // c1 = m * y^s
     let c_1 = *secret * public_key.y.pow(s);
 
-    // assign values to the tree according to scheme
-    let mut filled_tree = access_tree.assign_values(s, None, rng);
+    // assign values to the tree according to scheme, a policy of a single leaf gets s itself
+    let mut filled_tree = access_tree.assign_values(s, Some(s), rng);
     filled_tree.assign_indices();
 
     // cj = g1^tj * sj
@@ -156,12 +551,13 @@ pub fn encrypt<R: Rng + ?Sized>(
             format!("Expected value for {} but got None", x.name).as_str(),
         ));
 
-        match value {
-            Ok(value) => Ok((
-                AbeIdentifier::from(x.clone()),
-                public_key.big_t[&x.name] * value,
-            )),
-            Err(e) => Err(e),
+        let big_t = public_key.big_t.get(&x.name).ok_or(AbeError::new(
+            format!("Attribute {} not found in public key", x.name).as_str(),
+        ));
+
+        match (value, big_t) {
+            (Ok(value), Ok(big_t)) => Ok((AbeIdentifier::from(x.clone()), *big_t * value)),
+            (Err(e), _) | (_, Err(e)) => Err(e),
         }
     });
 
@@ -171,47 +567,628 @@ pub fn encrypt<R: Rng + ?Sized>(
         .map(|d| d.clone().err().unwrap())
         .collect::<Vec<AbeError>>();
 
-    if errors.len() > 0 {
+    if !errors.is_empty() {
         let mut error_message = String::from("Could not calculate cj for attributes: ");
         for error in errors {
             error_message.push_str(&format!("{:?}, ", error));
         }
         return Err(AbeError::new(error_message.as_str()));
     }
+    let arr_c = c_j
+        .map(|c| c.clone().unwrap())
+        .collect::<Vec<(AbeIdentifier, G1)>>();
 
-    let message = aes::encrypt_symmetric(*secret, message)?;
-
-    Ok(AbeCipherText {
+    // the shares add up to s, so only the structure and indices of the tree are published
+    filled_tree.clear_values();
+    let mut header = AbeHeader {
+        version: kdf::FORMAT_VERSION,
+        system_id: public_key.system_id(),
+        algorithm: algorithm.to_string(),
+        segment_size: options.segment_size,
+        compression: options.compression.clone(),
+        padding: options.padding,
         access_tree: Box::new(filled_tree),
         c_0,
         c_1,
-        arr_c: c_j
-            .map(|c| c.clone().unwrap())
-            .collect::<Vec<(AbeIdentifier, G1)>>(),
-        message,
-    })
+        arr_c,
+        mac: None,
+    };
+    header.mac = Some(header_mac(secret, &header));
+
+    Ok(header)
+}
+
+/// Tag over the fingerprint of the header, keyed with `kdf::MAC_KEY` derived from the secret it
+/// encapsulates. Only the right secret reproduces it, which commits the header to its secret.
+pub fn header_mac(secret: &Gt, header: &AbeHeader) -> Fingerprint {
+    let mut data = Vec::new();
+    KeySchedule::for_header(secret, header)
+        .derive(kdf::MAC_KEY)
+        .encode_canonical(&mut data);
+    header.fingerprint().encode_canonical(&mut data);
+    Fingerprint::of("abe-header-mac", data.as_slice())
+}
+
+/// Recovers the key encapsulated by `encapsulate` if the secret key satisfies the access tree
+pub fn decapsulate(
+    header: &AbeHeader,
+    secret_key: &AbeSecretKey,
+) -> Result<[u8; kdf::KEY_SIZE], AbeError> {
+    if header.algorithm != EXTERNAL_ALGORITHM {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::UnknownAlgorithm,
+            format!(
+                "Header protects a {} payload instead of an external key",
+                header.algorithm
+            )
+            .as_str(),
+        ));
+    }
+    header.validate()?;
+
+    let secret = decapsulate_secret(header, secret_key)?;
+    Ok(KeySchedule::for_header(&secret, header).derive(kdf::EXTERNAL_KEY))
 }
 
+/// Decrypts a copy of the payload if the secret key satisfies the access tree. Large payloads
+/// are better decrypted with `decrypt_in_place`, which reuses the buffer of the cipher text.
 pub fn decrypt(
     cipher_text: &AbeCipherText,
     secret_key: &AbeSecretKey,
 ) -> Result<AbeDecrypted, AbeError> {
-    // find minimal set of attributes required to decrypt
-    let original_set = secret_key
+    decrypt_in_place(&BuiltinDems, cipher_text.clone(), secret_key)
+}
+
+/// Like `decrypt`, decrypting the payload in the buffer of the cipher text instead of copying it.
+/// The payload may use any data encapsulation mechanism `dems` resolves, e.g. one implemented
+/// outside of this crate.
+pub fn decrypt_in_place(
+    dems: &dyn DemResolver,
+    cipher_text: AbeCipherText,
+    secret_key: &AbeSecretKey,
+) -> Result<AbeDecrypted, AbeError> {
+    let dem = cipher_text.validate(dems)?;
+
+    let AbeCipherText {
+        header,
+        mut message,
+    } = cipher_text;
+    let secret = decrypt_payload(dem, &header, secret_key, &mut message)?;
+
+    Ok(AbeDecrypted { message, secret })
+}
+
+/// Derives a transformation key for outsourced decryption and the retrieval key `z` that
+/// finishes it. The transformation key is the secret key raised to `z^-1`, so it can be handed to
+/// an untrusted server.
+pub fn transformation_key<R: Rng + ?Sized>(
+    secret_key: &AbeSecretKey,
+    rng: &mut R,
+) -> Result<(AbeTransformationKey, AbeRetrievalKey), AbeError> {
+    // z = random field element
+    let z: Fr = rng.gen();
+    let inverse = z
+        .inverse()
+        .ok_or(AbeError::new("Could not calculate inverse of z"))?;
+
+    // d0' = d0^(z^-1), dj' = dj^(z^-1)
+    let arr_d = secret_key
         .arr_d
         .iter()
-        .map(|(name, _)| AbeAttribute::new(name))
+        .map(|(a, d)| (a.clone(), *d * inverse))
+        .collect::<BTreeMap<String, G2>>();
+
+    Ok((
+        AbeTransformationKey {
+            system_id: secret_key.system_id,
+            d_0: secret_key.d_0 * inverse,
+            arr_d,
+        },
+        AbeRetrievalKey {
+            system_id: secret_key.system_id,
+            z,
+        },
+    ))
+}
+
+/// Partially decrypts the header with a transformation key, which is what a server does. All
+/// pairings happen here, `decrypt_transformed` finishes with one exponentiation.
+pub fn transform(
+    header: &AbeHeader,
+    transformation_key: &AbeTransformationKey,
+) -> Result<AbeTransformed, AbeError> {
+    header.validate()?;
+
+    // t = e(g1, g2)^(alpha * s / z)
+    let t = pair_key(
+        header,
+        &transformation_key.system_id,
+        transformation_key.d_0,
+        &transformation_key.arr_d,
+    )?;
+
+    Ok(AbeTransformed {
+        header_id: header.fingerprint(),
+        t,
+    })
+}
+
+/// Finishes the decryption of a cipher text transformed by a server, in the buffer of the cipher
+/// text.
+///
+/// The result of the server is verified before the payload is touched: it has to belong to the
+/// header of the cipher text, and the recovered secret has to reproduce the mac of the header,
+/// see `header_mac`. The server does not know `z`, so it cannot make up a `t` that passes.
+/// Wrong results fail with `AbeErrorKind::InvalidTransformation`, headers without a mac cannot be
+/// verified and fail with `AbeErrorKind::VerificationFailed`.
+pub fn decrypt_transformed(
+    dems: &dyn DemResolver,
+    cipher_text: AbeCipherText,
+    transformed: &AbeTransformed,
+    retrieval_key: &AbeRetrievalKey,
+) -> Result<AbeDecrypted, AbeError> {
+    let dem = cipher_text.validate(dems)?;
+    let header = &cipher_text.header;
+    if header.system_id != retrieval_key.system_id {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::SystemMismatch,
+            "Retrieval key belongs to a different system than the cipher text",
+        ));
+    }
+    if transformed.header_id != header.fingerprint() {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::VerificationFailed,
+            "Transformed cipher text belongs to a different header",
+        ));
+    }
+    let mac = header.mac.ok_or(AbeError::with_kind(
+        AbeErrorKind::VerificationFailed,
+        "Header has no mac to verify the transformed cipher text with",
+    ))?;
+
+    // m' = c1 / t^z
+    let secret = header.c_1 * transformed.t.pow(retrieval_key.z).inverse();
+    if header_mac(&secret, header) != mac {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::InvalidTransformation,
+            "Secret of the transformed cipher text does not match the mac of the header",
+        ));
+    }
+
+    let mut message = cipher_text.message;
+    open_payload(
+        dem,
+        &payload_key(&secret, header),
+        header.into(),
+        &mut message,
+    )?;
+
+    Ok(AbeDecrypted { message, secret })
+}
+
+/// Decrypts a payload read from `input` and writes the plaintext to `output`. Segmented
+/// payloads are processed one segment at a time, others are read into memory first.
+///
+/// Compressed payloads fail with `AbeErrorKind::SizeLimitExceeded` once more than `size_limit`
+/// bytes were decompressed, everything written to `output` up to then has to be discarded.
+pub fn decrypt_stream<I: Read, W: Write>(
+    dems: &dyn DemResolver,
+    header: &AbeHeader,
+    secret_key: &AbeSecretKey,
+    input: I,
+    output: &mut W,
+    size_limit: u64,
+) -> Result<(), AbeError> {
+    let dem = header.resolve(dems)?;
+    header.validate()?;
+
+    let secret = decapsulate_secret(header, secret_key)?;
+    open_stream(
+        dem,
+        &payload_key(&secret, header),
+        header.into(),
+        size_limit,
+        input,
+        output,
+    )
+}
+
+/// Opens a segmented payload for reading at arbitrary positions, see `SeekableDecryptor`.
+/// `input` has to be positioned at the start of the payload, which extends to its end.
+/// Payloads that are not segmented or are compressed cannot be read this way.
+pub fn decrypt_seekable<'d, I: Read + Seek>(
+    dems: &'d dyn DemResolver,
+    header: &AbeHeader,
+    secret_key: &AbeSecretKey,
+    input: I,
+) -> Result<SeekableDecryptor<'d, I>, AbeError> {
+    let dem = header.resolve(dems)?;
+    header.validate()?;
+
+    let secret = decapsulate_secret(header, secret_key)?;
+    open_seekable(dem, &payload_key(&secret, header), header.into(), input)
+}
+
+// Opens a segmented, uncompressed payload for random access
+fn open_seekable<'d, I: Read + Seek>(
+    dem: &'d dyn DataEncapsulation,
+    key: &[u8],
+    layout: PayloadLayout,
+    input: I,
+) -> Result<SeekableDecryptor<'d, I>, AbeError> {
+    let segment_size = layout.segment_size.ok_or(AbeError::new(
+        "Only segmented payloads can be read at random positions",
+    ))?;
+    if layout.compression.is_some() || layout.padded {
+        return Err(AbeError::new(
+            "Compressed or padded payloads cannot be read at random positions",
+        ));
+    }
+
+    SeekableDecryptor::new(dem, key, segment_size, input)
+        .map_err(|e| AbeError::new(format!("Could not decrypt stream: {}", e).as_str()))
+}
+
+// Decrypts a plain or segmented payload read from `input` to `output`
+fn open_stream<I: Read, W: Write>(
+    dem: &dyn DataEncapsulation,
+    key: &[u8],
+    layout: PayloadLayout,
+    size_limit: u64,
+    mut input: I,
+    output: &mut W,
+) -> Result<(), AbeError> {
+    let to_error =
+        |e: io::Error| AbeError::new(format!("Could not decrypt stream: {}", e).as_str());
+    match layout.segment_size {
+        None => {
+            let mut payload = Vec::new();
+            input.read_to_end(&mut payload).map_err(to_error)?;
+            dem.decrypt_in_place(key, &mut payload)?;
+            copy_plaintext(payload.as_slice(), layout, size_limit, output)
+        }
+        Some(segment_size) => {
+            let decryptor =
+                StreamDecryptor::new(dem, key, segment_size, input).map_err(to_error)?;
+            copy_plaintext(decryptor, layout, size_limit, output)
+        }
+    }
+}
+
+// Copies the decrypted payload to `output`, stripping the padding and decompressing it if needed
+fn copy_plaintext<'i, I: Read + 'i, W: Write>(
+    input: I,
+    layout: PayloadLayout,
+    size_limit: u64,
+    output: &mut W,
+) -> Result<(), AbeError> {
+    let to_error =
+        |e: io::Error| AbeError::new(format!("Could not decrypt stream: {}", e).as_str());
+    let mut input: Box<dyn Read + 'i> = Box::new(input);
+    if layout.padded {
+        input = Box::new(Unpadder::new(input));
+    }
+    match layout.compression {
+        None => {
+            io::copy(&mut input, output).map_err(to_error)?;
+        }
+        Some(algorithm) => {
+            let mut decompressor =
+                Decompressor::new(algorithm, input, size_limit).map_err(to_error)?;
+            io::copy(&mut decompressor, output).map_err(|e| {
+                if decompressor.limit_exceeded() {
+                    compression::size_limit_error(size_limit)
+                } else {
+                    to_error(e)
+                }
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+// Recovers the secret and decrypts the payload in `buffer` with the key derived from it
+fn decrypt_payload(
+    dem: &dyn DataEncapsulation,
+    header: &AbeHeader,
+    secret_key: &AbeSecretKey,
+    buffer: &mut Vec<u8>,
+) -> Result<Gt, AbeError> {
+    let secret = decapsulate_secret(header, secret_key)?;
+    let key = payload_key(&secret, header);
+    open_payload(dem, &key, header.into(), buffer)?;
+
+    Ok(secret)
+}
+
+// Decrypts a plain or segmented payload in place, strips the padding and decompresses it up to
+// the default size limit
+fn open_payload(
+    dem: &dyn DataEncapsulation,
+    key: &[u8],
+    layout: PayloadLayout,
+    buffer: &mut Vec<u8>,
+) -> Result<(), AbeError> {
+    match layout.segment_size {
+        None => dem.decrypt_in_place(key, buffer)?,
+        Some(segment_size) => stream::decrypt_segmented_in_place(dem, key, segment_size, buffer)?,
+    }
+    if layout.padded {
+        padding::unpad(buffer)?;
+    }
+    if let Some(algorithm) = layout.compression {
+        *buffer = compression::decompress(algorithm, buffer, compression::DEFAULT_SIZE_LIMIT)?;
+    }
+    Ok(())
+}
+
+// The parts of a cipher text or envelope header that describe the layout of the payload
+#[derive(Clone, Copy)]
+struct PayloadLayout<'a> {
+    segment_size: Option<usize>,
+    compression: Option<&'a str>,
+    padded: bool,
+}
+
+impl<'a, H: CipherTextHeader> From<&'a H> for PayloadLayout<'a> {
+    fn from(header: &'a H) -> Self {
+        PayloadLayout {
+            segment_size: header.segment_size(),
+            compression: header.compression(),
+            padded: header.padding().is_some(),
+        }
+    }
+}
+
+/// Encrypts the message once and wraps its key for every recipient, given as the public key of
+/// its system and the access tree it has to satisfy. A secret key satisfying any of the access
+/// trees decrypts the envelope with `decrypt_envelope`.
+pub fn encrypt_envelope<R: Rng + ?Sized>(
+    dem: &dyn DataEncapsulation,
+    message: &[u8],
+    recipients: &[(&AbePublicKey, &AccessTree)],
+    rng: &mut R,
+) -> Result<AbeEnvelope, AbeError> {
+    if recipients.is_empty() {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::NoRecipient,
+            "Envelope needs at least one recipient",
+        ));
+    }
+
+    let payload_key: [u8; kdf::KEY_SIZE] = rng.gen();
+    let recipients = recipients
+        .iter()
+        .map(|(public_key, access_tree)| wrap_key(&payload_key, public_key, access_tree, rng))
+        .collect::<Result<Vec<AbeRecipient>, AbeError>>()?;
+
+    Ok(AbeEnvelope {
+        header: AbeEnvelopeHeader {
+            version: kdf::FORMAT_VERSION,
+            algorithm: dem.algorithm().to_string(),
+            segment_size: None,
+            compression: None,
+            padding: None,
+            recipients,
+        },
+        message: dem.encrypt(&payload_key, message)?,
+    })
+}
+
+/// Decrypts an envelope with the first recipient whose access tree the secret key satisfies. The
+/// payload is decrypted in the buffer of the envelope.
+pub fn decrypt_envelope(
+    dems: &dyn DemResolver,
+    envelope: AbeEnvelope,
+    secret_key: &AbeSecretKey,
+) -> Result<Vec<u8>, AbeError> {
+    let dem = envelope.validate(dems)?;
+
+    let payload_key = unwrap_key(&envelope.header.recipients, secret_key)?;
+    let mut message = envelope.message;
+    open_payload(dem, &payload_key, (&envelope.header).into(), &mut message)?;
+
+    Ok(message)
+}
+
+/// Like `decrypt_stream`, for the payload of an envelope
+pub fn decrypt_envelope_stream<I: Read, W: Write>(
+    dems: &dyn DemResolver,
+    header: &AbeEnvelopeHeader,
+    secret_key: &AbeSecretKey,
+    input: I,
+    output: &mut W,
+    size_limit: u64,
+) -> Result<(), AbeError> {
+    let dem = header.resolve(dems)?;
+    header.validate()?;
+
+    let payload_key = unwrap_key(&header.recipients, secret_key)?;
+    open_stream(dem, &payload_key, header.into(), size_limit, input, output)
+}
+
+/// Like `decrypt_seekable`, for the payload of an envelope
+pub fn decrypt_envelope_seekable<'d, I: Read + Seek>(
+    dems: &'d dyn DemResolver,
+    header: &AbeEnvelopeHeader,
+    secret_key: &AbeSecretKey,
+    input: I,
+) -> Result<SeekableDecryptor<'d, I>, AbeError> {
+    let dem = header.resolve(dems)?;
+    header.validate()?;
+
+    let payload_key = unwrap_key(&header.recipients, secret_key)?;
+    open_seekable(dem, &payload_key, header.into(), input)
+}
+
+/// Makes the payload of a cipher text readable under a new access tree without re-encrypting
+/// it. The secret key has to satisfy the current access tree, the payload is moved as is into
+/// an envelope for the new access tree. `dems` has to resolve the algorithm of the payload, so
+/// the envelope can be decrypted.
+pub fn rewrap<R: Rng + ?Sized>(
+    dems: &dyn DemResolver,
+    cipher_text: AbeCipherText,
+    secret_key: &AbeSecretKey,
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeEnvelope, AbeError> {
+    cipher_text.validate(dems)?;
+
+    let header = rewrap_header(
+        dems,
+        &cipher_text.header,
+        secret_key,
+        public_key,
+        access_tree,
+        rng,
+    )?;
+    Ok(AbeEnvelope {
+        header,
+        message: cipher_text.message,
+    })
+}
+
+/// Like `rewrap` for the header of a cipher text, whose payload is not needed
+pub fn rewrap_header<R: Rng + ?Sized>(
+    dems: &dyn DemResolver,
+    header: &AbeHeader,
+    secret_key: &AbeSecretKey,
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeEnvelopeHeader, AbeError> {
+    header.resolve(dems)?;
+    header.validate()?;
+
+    let secret = decapsulate_secret(header, secret_key)?;
+    let payload_key = payload_key(&secret, header);
+    Ok(AbeEnvelopeHeader {
+        version: kdf::FORMAT_VERSION,
+        algorithm: header.algorithm.clone(),
+        segment_size: header.segment_size,
+        compression: header.compression.clone(),
+        padding: header.padding,
+        recipients: vec![wrap_key(&payload_key, public_key, access_tree, rng)?],
+    })
+}
+
+/// Replaces the recipients of an envelope header with a single one for a new access tree. The
+/// secret key has to satisfy the access tree of one of the current recipients.
+pub fn rewrap_envelope_header<R: Rng + ?Sized>(
+    dems: &dyn DemResolver,
+    header: &AbeEnvelopeHeader,
+    secret_key: &AbeSecretKey,
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeEnvelopeHeader, AbeError> {
+    header.resolve(dems)?;
+    header.validate()?;
+
+    let payload_key = unwrap_key(&header.recipients, secret_key)?;
+    Ok(AbeEnvelopeHeader {
+        recipients: vec![wrap_key(&payload_key, public_key, access_tree, rng)?],
+        ..header.clone()
+    })
+}
+
+// Wraps the payload key under a fresh key encapsulated for the recipient
+fn wrap_key<R: Rng + ?Sized>(
+    payload_key: &[u8],
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeRecipient, AbeError> {
+    let (header, key) = encapsulate(public_key, access_tree, rng)?;
+    Ok(AbeRecipient {
+        header,
+        wrapped_key: Aes256Gcm.encrypt(&key, payload_key)?,
+    })
+}
+
+// Tries every recipient whose access tree the secret key satisfies until one unwraps
+fn unwrap_key(recipients: &[AbeRecipient], secret_key: &AbeSecretKey) -> Result<Vec<u8>, AbeError> {
+    let attributes = secret_key
+        .arr_d
+        .keys()
+        .map(|name| AbeAttribute::new(name))
         .collect::<Vec<AbeAttribute>>();
 
-    let minimal_set = cipher_text.access_tree.find_minimal_set(&original_set)?;
+    let mut error = AbeError::with_kind(
+        AbeErrorKind::NoRecipient,
+        "Secret key does not satisfy the access tree of any recipient",
+    );
+    for recipient in recipients.iter().filter(|recipient| {
+        recipient.header.system_id == secret_key.system_id
+            && recipient.header.access_tree.is_satisfiable(&attributes)
+    }) {
+        match decapsulate(&recipient.header, secret_key)
+            .and_then(|key| Ok(Aes256Gcm.decrypt(&key, &recipient.wrapped_key)?))
+        {
+            Ok(payload_key) => return Ok(payload_key),
+            Err(e) => error = e,
+        }
+    }
+
+    Err(error)
+}
 
-    let product = cipher_text
+// Derives the key of the payload from the secret encapsulated in the header, headers without a
+// format version use the hash of the secret from before the key schedule was introduced
+fn payload_key(secret: &Gt, header: &AbeHeader) -> Vec<u8> {
+    match header.version {
+        0 => aes::kdf(*secret),
+        _ => KeySchedule::for_header(secret, header)
+            .derive(kdf::PAYLOAD_KEY)
+            .to_vec(),
+    }
+}
+
+// Recovers the secret from a header the secret key satisfies
+fn decapsulate_secret(header: &AbeHeader, secret_key: &AbeSecretKey) -> Result<Gt, AbeError> {
+    let egsga = pair_key(
+        header,
+        &secret_key.system_id,
+        secret_key.d_0,
+        &secret_key.arr_d,
+    )?;
+
+    // m' = c1 / e(g^s,g^a)
+    Ok(header.c_1 * egsga.inverse())
+}
+
+// e(c0, d0) * prod e(cj, dj) over a minimal satisfying set of the key attributes, which is
+// e(g^s,g^a) for the components of a secret key
+fn pair_key(
+    header: &AbeHeader,
+    system_id: &Fingerprint,
+    d_0: G2,
+    arr_d: &BTreeMap<String, G2>,
+) -> Result<Gt, AbeError> {
+    if header.system_id != *system_id {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::SystemMismatch,
+            "Secret key belongs to a different system than the cipher text",
+        ));
+    }
+
+    // find minimal set of attributes required to decrypt
+    let original_set = arr_d
+        .keys()
+        .map(|name| AbeAttribute::new(name))
+        .collect::<Vec<AbeAttribute>>();
+
+    let minimal_set = header.access_tree.find_minimal_set(&original_set)?;
+
+    let product = header
         .arr_c
         .iter()
         .filter(|(identifier, _)| {
             minimal_set.contains(&AbeAttribute::new(identifier.name.as_str()))
         })
-        .map(|(identifier, c)| pairing(*c, secret_key.arr_d[&identifier.name]))
+        .map(|(identifier, c)| pairing(*c, arr_d[&identifier.name]))
         .fold(None, |acc, e| match acc {
             None => Some(e),
             Some(acc) => Some(acc * e),
@@ -219,15 +1196,5 @@ pub fn decrypt(
         .ok_or(AbeError::new("Could not calculate product of e(cj,dj)"))?;
 
     // e(g^s,g^a) = e(c0,d0) * e(g,g)^rs
-    let egsga = pairing(cipher_text.c_0, secret_key.d_0) * product;
-
-    // m' = c1 / e(g^s,g^a)
-    let m_prime = cipher_text.c_1 * egsga.inverse();
-
-    let message_bytes = aes::decrypt_symmetric(m_prime, &cipher_text.message)?;
-
-    Ok(AbeDecrypted {
-        secret: m_prime,
-        message: message_bytes,
-    })
+    Ok(pairing(header.c_0, d_0) * product)
 }
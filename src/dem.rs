@@ -0,0 +1,486 @@
+use std::fmt::{Debug, Formatter};
+
+use aes_gcm::aead::consts::U12;
+use aes_gcm::aead::{AeadInPlace, NewAead};
+use aes_gcm::{Key, Tag};
+use rand::thread_rng;
+use rand::Rng;
+
+use crate::errors::symmetric_encryption_error::SymmetricEncryptionError;
+
+/// Algorithm used when none is requested, and assumed for cipher texts that do not record one
+pub const DEFAULT_ALGORITHM: &str = Aes256Gcm::ALGORITHM;
+
+/// Data encapsulation mechanism, the authenticated symmetric cipher that protects the payload
+/// under the key derived from the ABE secret.
+///
+/// The identifier returned by `algorithm` is recorded in the cipher text, so decryption can pick
+/// the same implementation without the ABE layer knowing about it, see `DemResolver`.
+/// Implementations only provide in place encryption under a caller chosen nonce, the message
+/// format on top is shared.
+pub trait DataEncapsulation {
+    /// Identifier of the algorithm, e.g. "AES-256-GCM"
+    fn algorithm(&self) -> &str;
+
+    /// Size of the nonces `seal_detached` and `open_detached` take
+    fn nonce_size(&self) -> usize;
+
+    /// Size of the authentication tag produced by `seal_detached`
+    fn tag_size(&self) -> usize;
+
+    /// Encrypts `buffer` in place under the given nonce of `nonce_size` bytes, which must never
+    /// repeat for a key, and writes the authentication tag to `tag`
+    fn seal_detached(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        buffer: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<(), SymmetricEncryptionError>;
+
+    /// Reverses `seal_detached`, failing if the cipher text or tag was modified. `buffer` is
+    /// left unspecified on failure.
+    fn open_detached(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), SymmetricEncryptionError>;
+
+    /// Number of bytes the output of `encrypt` is longer than the plaintext
+    fn overhead(&self) -> usize {
+        self.nonce_size() + self.tag_size()
+    }
+
+    /// Encrypts the plaintext in `buffer` under the given nonce and appends the tag
+    fn seal_in_place(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SymmetricEncryptionError> {
+        let length = buffer.len();
+        buffer.resize(length + self.tag_size(), 0);
+        let (data, tag) = buffer.split_at_mut(length);
+        self.seal_detached(key, nonce, data, tag)
+    }
+
+    /// Reverses `seal_in_place`, leaving only the plaintext in `buffer`
+    fn open_in_place(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SymmetricEncryptionError> {
+        let length =
+            buffer
+                .len()
+                .checked_sub(self.tag_size())
+                .ok_or(SymmetricEncryptionError::new(
+                    "Cipher text is too short to contain a tag",
+                ))?;
+        let (data, tag) = buffer.split_at_mut(length);
+        self.open_detached(key, nonce, data, tag)?;
+        buffer.truncate(length);
+        Ok(())
+    }
+
+    /// Encrypts and authenticates under the given nonce into a new buffer
+    fn seal(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, SymmetricEncryptionError> {
+        let mut buffer = Vec::with_capacity(plaintext.len() + self.tag_size());
+        buffer.extend_from_slice(plaintext);
+        self.seal_in_place(key, nonce, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Reverses `seal` into a new buffer
+    fn open(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, SymmetricEncryptionError> {
+        let mut buffer = ciphertext.to_vec();
+        self.open_in_place(key, nonce, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Encrypts under a random nonce and prepends the nonce, i.e. `[nonce|ciphertext|tag]`
+    fn encrypt(&self, key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, SymmetricEncryptionError> {
+        let mut buffer = Vec::with_capacity(plaintext.len() + self.overhead());
+        buffer.extend_from_slice(plaintext);
+        self.encrypt_in_place(key, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Splits off the nonce prepended by `encrypt` and decrypts the remainder
+    fn decrypt(&self, key: &[u8], nonce_ct: &[u8]) -> Result<Vec<u8>, SymmetricEncryptionError> {
+        let mut buffer = nonce_ct.to_vec();
+        self.decrypt_in_place(key, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Like `encrypt`, replacing the plaintext in `buffer` with the cipher text. Nothing is
+    /// allocated if `buffer` has capacity for `overhead` more bytes.
+    fn encrypt_in_place(
+        &self,
+        key: &[u8],
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SymmetricEncryptionError> {
+        let length = buffer.len();
+        buffer.resize(length + self.overhead(), 0);
+        buffer.copy_within(..length, self.nonce_size());
+        self.encrypt_in_slice(key, buffer)
+    }
+
+    /// Like `decrypt`, replacing the cipher text in `buffer` with the plaintext
+    fn decrypt_in_place(
+        &self,
+        key: &[u8],
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SymmetricEncryptionError> {
+        let length = self.decrypt_in_slice(key, buffer)?.len();
+        let nonce_size = self.nonce_size();
+        buffer.copy_within(nonce_size..nonce_size + length, 0);
+        buffer.truncate(length);
+        Ok(())
+    }
+
+    /// Like `encrypt` for a buffer laid out as `[space for nonce|plaintext|space for tag]`,
+    /// which is overwritten with the cipher text
+    fn encrypt_in_slice(
+        &self,
+        key: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), SymmetricEncryptionError> {
+        let length =
+            buffer
+                .len()
+                .checked_sub(self.overhead())
+                .ok_or(SymmetricEncryptionError::new(
+                    "Buffer is too short to hold a nonce and tag",
+                ))?;
+
+        let (nonce, data) = buffer.split_at_mut(self.nonce_size());
+        thread_rng().fill(nonce);
+        let (data, tag) = data.split_at_mut(length);
+        self.seal_detached(key, nonce, data, tag)
+    }
+
+    /// Like `decrypt` for a cipher text held in `buffer`, returning the part of `buffer` the
+    /// plaintext was decrypted to
+    fn decrypt_in_slice<'b>(
+        &self,
+        key: &[u8],
+        buffer: &'b mut [u8],
+    ) -> Result<&'b mut [u8], SymmetricEncryptionError> {
+        let length =
+            buffer
+                .len()
+                .checked_sub(self.overhead())
+                .ok_or(SymmetricEncryptionError::new(
+                    "Cipher text is too short to contain a nonce and tag",
+                ))?;
+
+        let (nonce, data) = buffer.split_at_mut(self.nonce_size());
+        let (data, tag) = data.split_at_mut(length);
+        self.open_detached(key, nonce, data, tag)?;
+        Ok(data)
+    }
+}
+
+impl Debug for dyn DataEncapsulation + '_ {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DataEncapsulation({})", self.algorithm())
+    }
+}
+
+/// Finds the implementation for the algorithm identifier recorded in a cipher text. Decryption
+/// takes a resolver, so payloads of implementations outside of this crate can be opened as well.
+pub trait DemResolver {
+    /// Returns the implementation of the algorithm, or `None` if it is not available
+    fn resolve(&self, algorithm: &str) -> Option<&dyn DataEncapsulation>;
+}
+
+/// Resolves the built in implementations
+pub struct BuiltinDems;
+
+impl DemResolver for BuiltinDems {
+    fn resolve(&self, algorithm: &str) -> Option<&dyn DataEncapsulation> {
+        from_algorithm(algorithm)
+    }
+}
+
+/// A single implementation resolves its own algorithm
+impl<D: DataEncapsulation> DemResolver for D {
+    fn resolve(&self, algorithm: &str) -> Option<&dyn DataEncapsulation> {
+        (self.algorithm() == algorithm).then_some(self as &dyn DataEncapsulation)
+    }
+}
+
+/// A list of implementations resolves the algorithms of all of them
+impl DemResolver for &[&dyn DataEncapsulation] {
+    fn resolve(&self, algorithm: &str) -> Option<&dyn DataEncapsulation> {
+        self.iter()
+            .find(|dem| dem.algorithm() == algorithm)
+            .copied()
+    }
+}
+
+/// Looks up one of the built in implementations by its identifier
+pub fn from_algorithm(algorithm: &str) -> Option<&'static dyn DataEncapsulation> {
+    match algorithm {
+        Aes256Gcm::ALGORITHM => Some(&Aes256Gcm),
+        Aes256GcmSiv::ALGORITHM => Some(&Aes256GcmSiv),
+        _ => None,
+    }
+}
+
+/// AES-256-GCM with a 96 bit nonce
+pub struct Aes256Gcm;
+
+impl Aes256Gcm {
+    pub const ALGORITHM: &'static str = "AES-256-GCM";
+}
+
+impl DataEncapsulation for Aes256Gcm {
+    fn algorithm(&self) -> &str {
+        Self::ALGORITHM
+    }
+
+    fn nonce_size(&self) -> usize {
+        NONCE_SIZE
+    }
+
+    fn tag_size(&self) -> usize {
+        TAG_SIZE
+    }
+
+    fn seal_detached(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        buffer: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<(), SymmetricEncryptionError> {
+        check_tag(tag)?;
+        check_nonce(nonce)?;
+        let cipher = aes_gcm::Aes256Gcm::new(Key::from_slice(check_key(key)?));
+        let computed = cipher
+            .encrypt_in_place_detached(Nonce::from_slice(nonce), b"", buffer)
+            .map_err(|e| SymmetricEncryptionError::new(&format!("{:?}", e.to_string())))?;
+        tag.copy_from_slice(&computed);
+        Ok(())
+    }
+
+    fn open_detached(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), SymmetricEncryptionError> {
+        check_tag(tag)?;
+        check_nonce(nonce)?;
+        let cipher = aes_gcm::Aes256Gcm::new(Key::from_slice(check_key(key)?));
+        cipher
+            .decrypt_in_place_detached(Nonce::from_slice(nonce), b"", buffer, Tag::from_slice(tag))
+            .map_err(|e| {
+                SymmetricEncryptionError::new(&format!("decryption error: {:?}", e.to_string()))
+            })
+    }
+}
+
+/// AES-256-GCM-SIV with a 96 bit nonce, which stays secure if a nonce is ever repeated
+pub struct Aes256GcmSiv;
+
+impl Aes256GcmSiv {
+    pub const ALGORITHM: &'static str = "AES-256-GCM-SIV";
+}
+
+impl DataEncapsulation for Aes256GcmSiv {
+    fn algorithm(&self) -> &str {
+        Self::ALGORITHM
+    }
+
+    fn nonce_size(&self) -> usize {
+        NONCE_SIZE
+    }
+
+    fn tag_size(&self) -> usize {
+        TAG_SIZE
+    }
+
+    fn seal_detached(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        buffer: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<(), SymmetricEncryptionError> {
+        check_tag(tag)?;
+        check_nonce(nonce)?;
+        let cipher = aes_gcm_siv::Aes256GcmSiv::new(Key::from_slice(check_key(key)?));
+        let computed = cipher
+            .encrypt_in_place_detached(Nonce::from_slice(nonce), b"", buffer)
+            .map_err(|e| SymmetricEncryptionError::new(&format!("{:?}", e.to_string())))?;
+        tag.copy_from_slice(&computed);
+        Ok(())
+    }
+
+    fn open_detached(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), SymmetricEncryptionError> {
+        check_tag(tag)?;
+        check_nonce(nonce)?;
+        let cipher = aes_gcm_siv::Aes256GcmSiv::new(Key::from_slice(check_key(key)?));
+        cipher
+            .decrypt_in_place_detached(Nonce::from_slice(nonce), b"", buffer, Tag::from_slice(tag))
+            .map_err(|e| {
+                SymmetricEncryptionError::new(&format!("decryption error: {:?}", e.to_string()))
+            })
+    }
+}
+
+const KEY_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+
+type Nonce = aes_gcm::Nonce<U12>;
+
+fn check_tag(tag: &[u8]) -> Result<(), SymmetricEncryptionError> {
+    if tag.len() != TAG_SIZE {
+        return Err(SymmetricEncryptionError::new(
+            format!("Expected a tag of {} bytes but got {}", TAG_SIZE, tag.len()).as_str(),
+        ));
+    }
+    Ok(())
+}
+
+fn check_nonce(nonce: &[u8]) -> Result<(), SymmetricEncryptionError> {
+    if nonce.len() != NONCE_SIZE {
+        return Err(SymmetricEncryptionError::new(
+            format!(
+                "Expected a nonce of {} bytes but got {}",
+                NONCE_SIZE,
+                nonce.len()
+            )
+            .as_str(),
+        ));
+    }
+    Ok(())
+}
+
+fn check_key(key: &[u8]) -> Result<&[u8], SymmetricEncryptionError> {
+    if key.len() != KEY_SIZE {
+        return Err(SymmetricEncryptionError::new(
+            format!("Expected a key of {} bytes but got {}", KEY_SIZE, key.len()).as_str(),
+        ));
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dem::{
+        from_algorithm, Aes256Gcm, Aes256GcmSiv, BuiltinDems, DataEncapsulation, DemResolver,
+    };
+
+    fn roundtrip(dem: &dyn DataEncapsulation) {
+        let key = [7u8; 32];
+        let plaintext = b"dance like no one's watching, encrypt like everyone is!";
+
+        let ciphertext = dem.encrypt(&key, plaintext).unwrap();
+        assert_eq!(ciphertext.len(), plaintext.len() + dem.overhead());
+        assert_eq!(dem.decrypt(&key, &ciphertext).unwrap(), plaintext);
+
+        let mut tampered = ciphertext.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(dem.decrypt(&key, &tampered).is_err());
+        assert!(dem.decrypt(&[8u8; 32], &ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_aes_gcm() {
+        roundtrip(&Aes256Gcm);
+    }
+
+    #[test]
+    fn test_aes_gcm_siv() {
+        roundtrip(&Aes256GcmSiv);
+    }
+
+    #[test]
+    fn test_in_place() {
+        let key = [7u8; 32];
+        let plaintext = b"dance like no one's watching, encrypt like everyone is!";
+
+        let mut buffer = Vec::with_capacity(plaintext.len() + Aes256Gcm.overhead());
+        buffer.extend_from_slice(plaintext);
+        let capacity = buffer.capacity();
+        Aes256Gcm.encrypt_in_place(&key, &mut buffer).unwrap();
+        assert_eq!(buffer.capacity(), capacity);
+        assert_eq!(Aes256Gcm.decrypt(&key, &buffer).unwrap(), plaintext);
+
+        let decrypted = Aes256Gcm.decrypt_in_slice(&key, &mut buffer).unwrap();
+        assert_eq!(decrypted, plaintext);
+
+        let nonce_size = Aes256Gcm.nonce_size();
+        let mut buffer = vec![0u8; plaintext.len() + Aes256Gcm.overhead()];
+        buffer[nonce_size..nonce_size + plaintext.len()].copy_from_slice(plaintext);
+        Aes256Gcm.encrypt_in_slice(&key, &mut buffer).unwrap();
+        Aes256Gcm.decrypt_in_place(&key, &mut buffer).unwrap();
+        assert_eq!(buffer, plaintext);
+
+        assert!(Aes256Gcm.decrypt_in_slice(&key, &mut [0u8; 8]).is_err());
+    }
+
+    #[test]
+    fn test_from_algorithm() {
+        for dem in [&Aes256Gcm as &dyn DataEncapsulation, &Aes256GcmSiv] {
+            assert_eq!(
+                from_algorithm(dem.algorithm()).unwrap().algorithm(),
+                dem.algorithm()
+            );
+        }
+        assert!(from_algorithm("ROT13").is_none());
+    }
+
+    #[test]
+    fn test_resolvers() {
+        assert!(BuiltinDems.resolve(Aes256GcmSiv::ALGORITHM).is_some());
+        assert!(Aes256Gcm.resolve(Aes256Gcm::ALGORITHM).is_some());
+        assert!(Aes256Gcm.resolve(Aes256GcmSiv::ALGORITHM).is_none());
+
+        let dems: &[&dyn DataEncapsulation] = &[&Aes256GcmSiv];
+        assert_eq!(
+            dems.resolve(Aes256GcmSiv::ALGORITHM).unwrap().algorithm(),
+            Aes256GcmSiv::ALGORITHM
+        );
+        assert!(dems.resolve(Aes256Gcm::ALGORITHM).is_none());
+    }
+
+    #[test]
+    fn test_invalid_nonce_size() {
+        let mut buffer = b"data".to_vec();
+        assert!(Aes256Gcm
+            .seal_in_place(&[0u8; 32], &[0u8; 8], &mut buffer)
+            .is_err());
+    }
+
+    #[test]
+    fn test_invalid_key_size() {
+        assert!(Aes256Gcm.encrypt(&[0u8; 16], b"data").is_err());
+    }
+}
@@ -1,16 +1,63 @@
 use std::fmt::{Display, Formatter};
 
+use crate::errors::armor_error::ArmorError;
 use crate::errors::parse_error::ParseError;
 use crate::errors::symmetric_encryption_error::SymmetricEncryptionError;
 
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum AbeErrorKind {
+    #[default]
+    Other,
+    /// The key and cipher text were created under different setups
+    SystemMismatch,
+    /// A detached payload does not belong to the header it was joined with
+    PayloadMismatch,
+    /// The `arr_c` entries do not correspond one to one with the leaves of the access tree
+    LeafMismatch,
+    /// Two leaves of the access tree share the same index
+    DuplicateIndex,
+    /// A group element of the cipher text is the identity
+    IdentityElement,
+    /// The symmetric payload is too short to hold a nonce and authentication tag
+    PayloadTooShort,
+    /// The cipher text was encrypted with a symmetric algorithm that is not available
+    UnknownAlgorithm,
+    /// The header records a segment size of zero
+    InvalidSegmentSize,
+    /// The header was written by a newer version of the format
+    UnsupportedVersion,
+    /// No recipient of an envelope can be decrypted with the secret key
+    NoRecipient,
+    /// The decompressed payload is larger than the accepted limit
+    SizeLimitExceeded,
+    /// The padding scheme is malformed or the decrypted message is not padded
+    InvalidPadding,
+    /// The partial decryption of a server does not belong to the cipher text, or the header has
+    /// no mac to verify it with
+    VerificationFailed,
+    /// The secret recovered from the partial decryption of a server does not match the mac of
+    /// the header, the server returned a wrong result
+    InvalidTransformation,
+    /// Keys of the multi-authority scheme were issued for different global identifiers
+    IdentityMismatch,
+    /// The scheme does not support the operation, e.g. rewrapping or delegation
+    Unsupported,
+}
+
 #[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct AbeError {
+    pub kind: AbeErrorKind,
     pub message: String,
 }
 
 impl AbeError {
     pub fn new(message: &str) -> AbeError {
+        AbeError::with_kind(AbeErrorKind::Other, message)
+    }
+
+    pub fn with_kind(kind: AbeErrorKind, message: &str) -> AbeError {
         AbeError {
+            kind,
             message: message.to_string(),
         }
     }
@@ -33,3 +80,9 @@ impl From<ParseError> for AbeError {
         AbeError::new(&format!("Parse error: {}", value))
     }
 }
+
+impl From<ArmorError> for AbeError {
+    fn from(value: ArmorError) -> Self {
+        AbeError::new(&format!("Armor error: {}", value))
+    }
+}
@@ -0,0 +1,20 @@
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ArmorError {
+    pub message: String,
+}
+
+impl ArmorError {
+    pub fn new(message: &str) -> ArmorError {
+        ArmorError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for ArmorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ArmorError: {}", self.message)
+    }
+}
@@ -1,3 +1,4 @@
 pub mod abe_error;
+pub mod armor_error;
 pub mod parse_error;
 pub mod symmetric_encryption_error;
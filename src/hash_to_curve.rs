@@ -0,0 +1,140 @@
+use rabe_bn::arith::U256;
+use rabe_bn::G1;
+use serde_json::json;
+use sha3::{Digest, Sha3_256};
+
+use crate::canonical::CanonicalEncode;
+
+// rabe-bn does not expose the base field of G1, so the few operations needed here are done on
+// its `U256` limbs (least significant first). Like rabe-bn, elements are kept in Montgomery
+// form, which is also the form its group elements are serialized in.
+const MODULUS: U256 = U256([
+    0x3c208c16d87cfd47,
+    0x97816a916871ca8d,
+    0xb85045b68181585d,
+    0x30644e72e131a029,
+]);
+const INV: u64 = 0x87d20782e4866389;
+const R_SQUARED: U256 = U256([
+    0xf32cfc5b538afa89,
+    0xb5e71911d44501fb,
+    0x47ab1eff0a417ff6,
+    0x06d89f71cab8351f,
+]);
+// 1 in Montgomery form
+const ONE: U256 = U256([
+    0xd35d438dc58f0d9d,
+    0x0a78eb28f5c70b3d,
+    0x666ea36f7879462c,
+    0x0e0a77c19a07df2f,
+]);
+// b = 3 of the curve y^2 = x^3 + b, in Montgomery form
+const COEFF_B: U256 = U256([
+    0x7a17caa950ad28d7,
+    0x1f6ac17ae15521b9,
+    0x334bea4e696bd284,
+    0x2a1f6744ce179d8e,
+]);
+// (p + 1) / 4, as p = 3 mod 4 a^((p + 1) / 4) is a square root of every square a
+const SQRT_EXPONENT: U256 = U256([
+    0x4f082305b61f3f52,
+    0x65e05aa45a1c72a3,
+    0x6e14116da0605617,
+    0x0c19139cb84c680a,
+]);
+
+/// Hashes the data to an element of G1 whose discrete logarithm is unknown to everyone.
+///
+/// Uses try-and-increment: candidate x coordinates are derived from SHA3-256 over the domain
+/// label, the data and a counter until x^3 + b is a square. G1 has prime order, so every point
+/// found this way is in the group.
+pub fn hash_to_g1(label: &str, data: &[u8]) -> G1 {
+    let mut counter: u32 = 0;
+    loop {
+        let mut hasher = Sha3_256::new();
+        hasher.update(label.to_canonical_bytes());
+        hasher.update(data.to_canonical_bytes());
+        hasher.update(counter.to_be_bytes());
+        counter += 1;
+
+        // the modulus has 254 bits, clearing the top two rejects less than a quarter of them
+        let mut x = U256::from_slice(&hasher.finalize()).expect("digest has 32 bytes");
+        x.0[3] &= u64::MAX >> 2;
+        if x >= MODULUS {
+            continue;
+        }
+        x.mul(&R_SQUARED, &MODULUS, INV);
+
+        let mut rhs = multiply(&multiply(&x, &x), &x);
+        rhs.add(&COEFF_B, &MODULUS);
+        let y = power(&rhs, &SQRT_EXPONENT);
+        if multiply(&y, &y) == rhs {
+            return point(x, y);
+        }
+    }
+}
+
+fn multiply(a: &U256, b: &U256) -> U256 {
+    let mut product = *a;
+    product.mul(b, &MODULUS, INV);
+    product
+}
+
+fn power(base: &U256, exponent: &U256) -> U256 {
+    let mut result = ONE;
+    for bit in exponent.bits() {
+        result = multiply(&result, &result);
+        if bit {
+            result = multiply(&result, base);
+        }
+    }
+    result
+}
+
+// Builds the point from its affine coordinates, serde is the only way to construct a group
+// element from coordinates in rabe-bn
+fn point(x: U256, y: U256) -> G1 {
+    serde_json::from_value(json!({ "x": x, "y": y, "z": ONE }))
+        .expect("coordinates are valid field elements")
+}
+
+#[cfg(test)]
+mod tests {
+    use rabe_bn::{pairing, Fr, Group, G1, G2};
+    use rand::Rng;
+
+    use crate::hash_to_curve::{hash_to_g1, multiply, point, COEFF_B, MODULUS, ONE};
+
+    #[test]
+    fn test_generator_is_on_curve() {
+        // the generator of rabe-bn is (1, 2)
+        let mut two = ONE;
+        two.add(&ONE, &MODULUS);
+        assert_eq!(point(ONE, two), G1::one());
+
+        let mut rhs = multiply(&multiply(&ONE, &ONE), &ONE);
+        rhs.add(&COEFF_B, &MODULUS);
+        assert_eq!(multiply(&two, &two), rhs);
+    }
+
+    #[test]
+    fn test_hash_is_deterministic() {
+        assert_eq!(hash_to_g1("test", b"A"), hash_to_g1("test", b"A"));
+        assert_ne!(hash_to_g1("test", b"A"), hash_to_g1("test", b"B"));
+        assert_ne!(hash_to_g1("test", b"A"), hash_to_g1("other", b"A"));
+        assert!(!hash_to_g1("test", b"").is_zero());
+    }
+
+    #[test]
+    fn test_hash_is_in_group() {
+        let rng = &mut rand::thread_rng();
+        let h = hash_to_g1("test", b"Engineering");
+        let (a, b): (Fr, Fr) = (rng.gen(), rng.gen());
+
+        // bilinearity only holds for points on the curve
+        assert_eq!(
+            pairing(h * a, G2::one() * b),
+            pairing(h, G2::one()).pow(a * b)
+        );
+    }
+}
@@ -0,0 +1,219 @@
+// Partially hidden policies for the ciphertext-policy scheme of `crypto`, after Nishide,
+// Yoneyama and Ohta, "Attribute-Based Encryption with Partially Hidden Encryptor-Specified Access
+// Structures" (ACNS 2008). Attributes are written as `category:value`, e.g. `clinic:hiv`, and the
+// cipher text only shows the categories of its policy, the values stay hidden.
+//
+// The secret `s` is shared over the access tree as in `crypto`, leaf i with attribute j gets
+// `c_i = Tj^lambda_i`. The decryptor does not know j, so every leaf also gets a match test for a
+// random `sigma_i`: `v_i = g1^sigma_i`, `u_i = Tj^sigma_i` and `w_i = y^sigma_i`. A key holds
+// `d_0 = g2^(alpha - r)` and `d_j = g2^(r / tj)`, so `e(v_i, d_0) * e(u_i, d_j) = w_i` for the
+// attribute of the leaf, while the other attributes of the category fail the test. The matched
+// leaves decrypt as in `crypto`, `e(c_0, d_0) * prod e(c_i, d_j) = e(g1, g2)^(alpha * s)`.
+//
+// Telling from the public key alone whether `u_i` belongs to a guessed value is a DDH instance in
+// G1, which is hard for the asymmetric pairing of rabe-bn. Holders of a key for a value recognise
+// it in any policy though, that is inherent to testing candidate matches.
+// Delegable keys of `crypto::keygen_delegable` hold `ej = g2^(1/tj)`, which tests a leaf as
+// `e(ui, ej) = e(vi, g2)`, the same recognition without `d_0`, passed on to every delegated key.
+
+use std::collections::BTreeMap;
+
+use rabe_bn::{pairing, Fr, Gt};
+use rand::Rng;
+
+use crate::abe_attribute::{AbeAttribute, AbeIdentifier};
+use crate::access_tree::{AccessTree, AssignValues, GetAttributes};
+use crate::dem::{Aes256Gcm, BuiltinDems, DataEncapsulation, DemResolver};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+use crate::kdf;
+use crate::kdf::KeySchedule;
+use crate::models::{
+    AbeDecrypted, AbeHiddenCipherText, AbeHiddenHeader, AbeMatchTest, AbePublicKey, AbeSecretKey,
+};
+
+/// Returns the category of an attribute written as `category:value`
+pub fn category(attribute: &str) -> Option<&str> {
+    attribute
+        .split_once(':')
+        .filter(|(category, value)| !category.is_empty() && !value.is_empty())
+        .map(|(category, _)| category)
+}
+
+pub fn encrypt<R: Rng + ?Sized>(
+    secret: &Gt,
+    message: &[u8],
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeHiddenCipherText, AbeError> {
+    encrypt_with(&Aes256Gcm, secret, message, public_key, access_tree, rng)
+}
+
+/// Like `encrypt`, protecting the payload with the given data encapsulation mechanism. Every
+/// attribute of the access tree has to be written as `category:value`.
+pub fn encrypt_with<R: Rng + ?Sized>(
+    dem: &dyn DataEncapsulation,
+    secret: &Gt,
+    message: &[u8],
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeHiddenCipherText, AbeError> {
+    // s = random field element, lambda_i = shares of s
+    let s: Fr = rng.gen();
+    let mut access_tree = access_tree.assign_values(s, Some(s), rng);
+    access_tree.assign_indices();
+
+    // ci = Tj^lambda_i, vi = g1^sigma_i, ui = Tj^sigma_i, wi = y^sigma_i
+    let mut arr_c = Vec::new();
+    let mut arr_test = Vec::new();
+    for leaf in access_tree.get_attributes() {
+        let category = category(&leaf.name).ok_or(AbeError::new(
+            format!(
+                "Attribute {} has no category, expected category:value",
+                leaf.name
+            )
+            .as_str(),
+        ))?;
+        let big_t = public_key.big_t.get(&leaf.name).ok_or(AbeError::new(
+            format!("Attribute {} not found in public key", leaf.name).as_str(),
+        ))?;
+        let lambda = leaf.value.ok_or(AbeError::new(
+            format!("Expected value for {} but got None", leaf.name).as_str(),
+        ))?;
+        let sigma: Fr = rng.gen();
+
+        let identifier = AbeIdentifier {
+            index: leaf.index,
+            name: category.to_string(),
+        };
+        arr_c.push((identifier.clone(), *big_t * lambda));
+        arr_test.push((
+            identifier,
+            AbeMatchTest {
+                v: public_key.g1 * sigma,
+                u: *big_t * sigma,
+                w: public_key.y.pow(sigma),
+            },
+        ));
+    }
+
+    // c0 = g1^s, c1 = m * y^s
+    let c_0 = public_key.g1 * s;
+    let c_1 = *secret * public_key.y.pow(s);
+
+    // only the categories and the structure of the policy are published
+    access_tree.rename_leaves(&mut |leaf| category(&leaf.name).unwrap_or_default().to_string());
+    access_tree.clear_values();
+    let header = AbeHiddenHeader {
+        version: kdf::FORMAT_VERSION,
+        system_id: public_key.system_id(),
+        algorithm: dem.algorithm().to_string(),
+        access_tree: Box::new(access_tree),
+        c_0,
+        c_1,
+        arr_c,
+        arr_test,
+    };
+    let message = dem.encrypt(&payload_key(secret, &header), message)?;
+
+    Ok(AbeHiddenCipherText { header, message })
+}
+
+/// Decrypts the cipher text if the attributes of the secret key satisfy its hidden access tree.
+/// Every leaf is tested against the attributes of the key in its category.
+pub fn decrypt(
+    cipher_text: &AbeHiddenCipherText,
+    secret_key: &AbeSecretKey,
+) -> Result<AbeDecrypted, AbeError> {
+    decrypt_with(&BuiltinDems, cipher_text, secret_key)
+}
+
+/// Like `decrypt`, for a payload protected with a data encapsulation mechanism `dems` resolves
+pub fn decrypt_with(
+    dems: &dyn DemResolver,
+    cipher_text: &AbeHiddenCipherText,
+    secret_key: &AbeSecretKey,
+) -> Result<AbeDecrypted, AbeError> {
+    let dem = cipher_text.validate(dems)?;
+    let header = &cipher_text.header;
+    if header.system_id != secret_key.system_id {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::SystemMismatch,
+            "Secret key belongs to a different system than the cipher text",
+        ));
+    }
+
+    let matches = match_leaves(header, secret_key);
+
+    // matched leaves get their attribute back, the others keep the category, which no
+    // attribute written as category:value equals
+    let mut access_tree = header.access_tree.clone();
+    access_tree.rename_leaves(&mut |leaf| {
+        matches
+            .get(&leaf.index)
+            .cloned()
+            .unwrap_or_else(|| leaf.name.clone())
+    });
+    let attributes = matches
+        .values()
+        .map(|name| AbeAttribute::new(name))
+        .collect::<Vec<AbeAttribute>>();
+    let leaves = access_tree
+        .find_satisfying_leaves(&attributes)
+        .ok_or(AbeError::new(
+            "Secret key attributes do not satisfy the access tree of the cipher text",
+        ))?;
+
+    // prod e(ci, dj) = e(g1, g2)^(r * s), validation made sure the indices of the components are
+    // unique
+    let mut product = Gt::one();
+    for leaf in &leaves {
+        let (_, c) = header
+            .arr_c
+            .iter()
+            .find(|(identifier, _)| identifier.index == leaf.index)
+            .ok_or(AbeError::with_kind(
+                AbeErrorKind::LeafMismatch,
+                format!("No cipher text component for leaf {:?}", leaf.index).as_str(),
+            ))?;
+        product = product * pairing(*c, secret_key.arr_d[&leaf.name]);
+    }
+
+    // e(c0, d0) * e(g1, g2)^(r * s) = e(g1, g2)^(alpha * s)
+    let blinding = pairing(header.c_0, secret_key.d_0) * product;
+    let secret = header.c_1 * blinding.inverse();
+    let message = dem.decrypt(&payload_key(&secret, header), &cipher_text.message)?;
+
+    Ok(AbeDecrypted { message, secret })
+}
+
+// Finds the attribute of the key every leaf is for, by index of the leaf. Only attributes of the
+// category of a leaf are candidates, e(vi, d0) * e(ui, dj) = wi for the matching one.
+fn match_leaves(
+    header: &AbeHiddenHeader,
+    secret_key: &AbeSecretKey,
+) -> BTreeMap<Option<usize>, String> {
+    header
+        .arr_test
+        .iter()
+        .filter_map(|(identifier, test)| {
+            let mut candidates = secret_key
+                .arr_d
+                .iter()
+                .filter(|(name, _)| category(name) == Some(identifier.name.as_str()))
+                .peekable();
+            candidates.peek()?;
+
+            let v_pairing = pairing(test.v, secret_key.d_0);
+            candidates
+                .find(|(_, d)| v_pairing * pairing(test.u, **d) == test.w)
+                .map(|(name, _)| (identifier.index, name.clone()))
+        })
+        .collect()
+}
+
+// Derives the key of the payload from the secret, bound to the header
+fn payload_key(secret: &Gt, header: &AbeHiddenHeader) -> [u8; kdf::KEY_SIZE] {
+    KeySchedule::for_header(secret, header).derive(kdf::PAYLOAD_KEY)
+}
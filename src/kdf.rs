@@ -0,0 +1,109 @@
+use hkdf::Hkdf;
+use rabe_bn::Gt;
+use sha3::Sha3_256;
+
+use crate::canonical::{CanonicalEncode, Fingerprinted};
+use crate::models::CipherTextHeader;
+
+/// Version of the cipher text format written by this crate. Headers without a version predate
+/// this module and derive their payload key with a plain hash of the secret.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Size of every derived key
+pub const KEY_SIZE: usize = 32;
+
+/// Label of the key that protects the payload
+pub const PAYLOAD_KEY: &str = "payload-key";
+
+/// Label of the key handed out by `crypto::encapsulate`
+pub const EXTERNAL_KEY: &str = "external-key";
+
+/// Label of the key for authenticating data outside of the payload, see `crypto::header_mac`
+pub const MAC_KEY: &str = "mac-key";
+
+const SALT: &str = "abe-kdf";
+
+/// HKDF (RFC 5869) over SHA3-256, keyed with the canonical encoding of a `Gt` secret.
+///
+/// Keys are expanded from their label, the format version and a context, usually the hash of the
+/// header that encapsulates the secret. Keys for different purposes, format versions or headers
+/// are therefore independent of each other, even if they stem from the same secret.
+pub struct KeySchedule {
+    hkdf: Hkdf<Sha3_256>,
+    version: u32,
+    context: Vec<u8>,
+}
+
+impl KeySchedule {
+    pub fn new(secret: &Gt, version: u32, context: &[u8]) -> KeySchedule {
+        KeySchedule {
+            hkdf: Hkdf::new(
+                Some(&SALT.to_canonical_bytes()),
+                &secret.to_canonical_bytes(),
+            ),
+            version,
+            context: context.to_vec(),
+        }
+    }
+
+    /// Key schedule for the secret encapsulated in `header`, bound to the version and hash of
+    /// the header
+    pub fn for_header<H: CipherTextHeader + Fingerprinted>(secret: &Gt, header: &H) -> KeySchedule {
+        KeySchedule::new(secret, header.version(), &header.fingerprint().0)
+    }
+
+    /// Derives the key with the given label
+    pub fn derive(&self, label: &str) -> [u8; KEY_SIZE] {
+        let mut info = Vec::new();
+        label.encode_canonical(&mut info);
+        self.version.encode_canonical(&mut info);
+        self.context.encode_canonical(&mut info);
+
+        let mut key = [0u8; KEY_SIZE];
+        self.hkdf
+            .expand(&info, &mut key)
+            .expect("key size is below the HKDF output limit");
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rabe_bn::Gt;
+    use rand::Rng;
+
+    use crate::kdf::{KeySchedule, EXTERNAL_KEY, FORMAT_VERSION, MAC_KEY, PAYLOAD_KEY};
+
+    #[test]
+    fn test_derivation_is_deterministic() {
+        let secret: Gt = rand::thread_rng().gen();
+
+        assert_eq!(
+            KeySchedule::new(&secret, FORMAT_VERSION, b"context").derive(PAYLOAD_KEY),
+            KeySchedule::new(&secret, FORMAT_VERSION, b"context").derive(PAYLOAD_KEY)
+        );
+    }
+
+    #[test]
+    fn test_keys_are_separated() {
+        let rng = &mut rand::thread_rng();
+        let secret: Gt = rng.gen();
+        let schedule = KeySchedule::new(&secret, FORMAT_VERSION, b"context");
+        let payload_key = schedule.derive(PAYLOAD_KEY);
+
+        assert_ne!(payload_key, schedule.derive(MAC_KEY));
+        assert_ne!(payload_key, schedule.derive(EXTERNAL_KEY));
+        assert_ne!(
+            payload_key,
+            KeySchedule::new(&secret, FORMAT_VERSION, b"other context").derive(PAYLOAD_KEY)
+        );
+        assert_ne!(
+            payload_key,
+            KeySchedule::new(&secret, FORMAT_VERSION + 1, b"context").derive(PAYLOAD_KEY)
+        );
+        assert_ne!(
+            payload_key,
+            KeySchedule::new(&rng.gen(), FORMAT_VERSION, b"context").derive(PAYLOAD_KEY)
+        );
+    }
+}
@@ -0,0 +1,179 @@
+// Key-policy ABE, the dual of the scheme in `crypto`: cipher texts are labelled with
+// attributes and secret keys carry the access tree. It shares the setup with `crypto`, public
+// and master keys are created with `crypto::setup`.
+//
+// The master secret `alpha` is shared over the access tree of every key with `AssignValues`,
+// each leaf `j` receiving a share `lambda_j`. A cipher text under the secret `s` holds
+// `T_j^s = g1^(t_j * s)` for each of its attributes, and pairing it with the key component
+// `g2^(lambda_j / t_j)` gives `e(g1, g2)^(s * lambda_j)`. Shares of a satisfying set of leaves
+// add up to `alpha`, so their product is `y^s`, which unblinds the secret.
+
+use std::collections::BTreeMap;
+
+use rabe_bn::{pairing, Fr, Gt};
+use rand::Rng;
+
+use crate::abe_attribute::{AbeAttribute, AbeIdentifier};
+use crate::access_tree::{AccessTree, AssignValues, GetAttributes};
+use crate::dem::{Aes256Gcm, BuiltinDems, DataEncapsulation, DemResolver};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+use crate::kdf;
+use crate::kdf::KeySchedule;
+use crate::models::{
+    AbeDecrypted, AbeKpCipherText, AbeKpHeader, AbeKpSecretKey, AbeMasterKey, AbePublicKey,
+};
+
+/// Creates a key that decrypts every cipher text whose attributes satisfy the access tree
+pub fn keygen<R: Rng + ?Sized>(
+    access_tree: &AccessTree,
+    public_key: &AbePublicKey,
+    master_key: &AbeMasterKey,
+    rng: &mut R,
+) -> Result<AbeKpSecretKey, AbeError> {
+    // lambda_j = shares of alpha, a fresh sharing for every key prevents collusion
+    let mut filled_tree = access_tree.assign_values(master_key.alpha, Some(master_key.alpha), rng);
+    filled_tree.assign_indices();
+
+    // dj = g2^(lambda_j * tj^-1)
+    let arr_d = filled_tree
+        .get_attributes()
+        .into_iter()
+        .map(|leaf| {
+            let t = master_key.small_t.get(&leaf.name).ok_or(AbeError::new(
+                format!("Attribute {} not found in master key", leaf.name).as_str(),
+            ))?;
+            let inverse = t.inverse().ok_or(AbeError::new(
+                format!("Could not calculate inverse of {}", leaf.name).as_str(),
+            ))?;
+            let lambda = leaf.value.ok_or(AbeError::new(
+                format!("Expected value for {} but got None", leaf.name).as_str(),
+            ))?;
+            Ok((
+                AbeIdentifier::from(leaf),
+                public_key.g2 * (lambda * inverse),
+            ))
+        })
+        .collect::<Result<Vec<_>, AbeError>>()?;
+
+    // the shares are only needed to compute dj, the key keeps the bare policy
+    filled_tree.clear_values();
+    Ok(AbeKpSecretKey {
+        system_id: public_key.system_id(),
+        access_tree: Box::new(filled_tree),
+        arr_d,
+    })
+}
+
+pub fn encrypt<R: Rng + ?Sized>(
+    secret: &Gt,
+    message: &[u8],
+    public_key: &AbePublicKey,
+    attributes: &[String],
+    rng: &mut R,
+) -> Result<AbeKpCipherText, AbeError> {
+    encrypt_with(&Aes256Gcm, secret, message, public_key, attributes, rng)
+}
+
+/// Like `encrypt`, protecting the payload with the given data encapsulation mechanism
+pub fn encrypt_with<R: Rng + ?Sized>(
+    dem: &dyn DataEncapsulation,
+    secret: &Gt,
+    message: &[u8],
+    public_key: &AbePublicKey,
+    attributes: &[String],
+    rng: &mut R,
+) -> Result<AbeKpCipherText, AbeError> {
+    if attributes.is_empty() {
+        return Err(AbeError::new("No attributes given"));
+    }
+
+    // s = random field element
+    let s: Fr = rng.gen();
+
+    // c1 = m * y^s
+    let c_1 = *secret * public_key.y.pow(s);
+
+    // cj = Tj^s
+    let arr_c = attributes
+        .iter()
+        .map(|attribute| {
+            let big_t = public_key.big_t.get(attribute).ok_or(AbeError::new(
+                format!("Attribute {} not found in public key", attribute).as_str(),
+            ))?;
+            Ok((attribute.clone(), *big_t * s))
+        })
+        .collect::<Result<BTreeMap<_, _>, AbeError>>()?;
+
+    let header = AbeKpHeader {
+        version: kdf::FORMAT_VERSION,
+        system_id: public_key.system_id(),
+        algorithm: dem.algorithm().to_string(),
+        c_1,
+        arr_c,
+    };
+    let message = dem.encrypt(&payload_key(secret, &header), message)?;
+
+    Ok(AbeKpCipherText { header, message })
+}
+
+/// Decrypts the cipher text if its attributes satisfy the access tree of the secret key
+pub fn decrypt(
+    cipher_text: &AbeKpCipherText,
+    secret_key: &AbeKpSecretKey,
+) -> Result<AbeDecrypted, AbeError> {
+    decrypt_with(&BuiltinDems, cipher_text, secret_key)
+}
+
+/// Like `decrypt`, for a payload protected with a data encapsulation mechanism `dems` resolves
+pub fn decrypt_with(
+    dems: &dyn DemResolver,
+    cipher_text: &AbeKpCipherText,
+    secret_key: &AbeKpSecretKey,
+) -> Result<AbeDecrypted, AbeError> {
+    let dem = cipher_text.validate(dems)?;
+    secret_key.validate()?;
+    let header = &cipher_text.header;
+    if header.system_id != secret_key.system_id {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::SystemMismatch,
+            "Secret key belongs to a different system than the cipher text",
+        ));
+    }
+
+    let attributes = header
+        .arr_c
+        .keys()
+        .map(|name| AbeAttribute::new(name))
+        .collect::<Vec<AbeAttribute>>();
+    let leaves = secret_key
+        .access_tree
+        .find_satisfying_leaves(&attributes)
+        .ok_or(AbeError::new(
+            "Cipher text attributes do not satisfy the access tree of the key",
+        ))?;
+
+    // e(cj, dj) = e(g1, g2)^(s * lambda_j), the shares of the leaves add up to alpha
+    let mut product = Gt::one();
+    for leaf in &leaves {
+        let (_, d) = secret_key
+            .arr_d
+            .iter()
+            .find(|(identifier, _)| leaf == identifier)
+            .ok_or(AbeError::with_kind(
+                AbeErrorKind::LeafMismatch,
+                format!("No key component for leaf {}", leaf.name).as_str(),
+            ))?;
+        product = product * pairing(header.arr_c[&leaf.name], *d);
+    }
+
+    // m = c1 / y^s
+    let secret = header.c_1 * product.inverse();
+    let message = dem.decrypt(&payload_key(&secret, header), &cipher_text.message)?;
+
+    Ok(AbeDecrypted { message, secret })
+}
+
+// Derives the key of the payload from the secret, bound to the header
+fn payload_key(secret: &Gt, header: &AbeKpHeader) -> [u8; kdf::KEY_SIZE] {
+    KeySchedule::for_header(secret, header).derive(kdf::PAYLOAD_KEY)
+}
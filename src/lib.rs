@@ -1,7 +1,23 @@
 pub mod abe_attribute;
 pub mod access_tree;
 mod aes;
+pub mod armor;
+pub mod canonical;
+pub mod compression;
 pub mod crypto;
+pub mod dem;
 pub mod errors;
+pub mod hash_to_curve;
+pub mod hidden;
+pub mod kdf;
+pub mod kp_abe;
+pub mod lsss;
+pub mod lu_abe;
+pub mod ma_abe;
 pub mod models;
+pub mod online;
+pub mod padding;
 pub mod parser;
+pub mod scheme;
+pub mod stream;
+pub mod waters;
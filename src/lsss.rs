@@ -0,0 +1,339 @@
+use rabe_bn::Fr;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::abe_attribute::AbeAttribute;
+use crate::access_tree::{AccessTree, Leaf, Operator, TreeOperator};
+use crate::errors::abe_error::AbeError;
+
+/// Monotone policy of threshold gates, the input of the LSSS conversion. AND and OR of an
+/// `AccessTree` are the gates 2-of-2 and 1-of-2.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Policy {
+    Leaf(String),
+    /// Satisfied if at least `threshold` of the children are
+    Threshold {
+        threshold: usize,
+        children: Vec<Policy>,
+    },
+}
+
+impl Policy {
+    /// Checks if the given set of attributes satisfies the policy
+    pub fn is_satisfied(&self, attributes: &[AbeAttribute]) -> bool {
+        match self {
+            Policy::Leaf(name) => attributes.iter().any(|a| a.name == *name),
+            Policy::Threshold {
+                threshold,
+                children,
+            } => {
+                children
+                    .iter()
+                    .filter(|child| child.is_satisfied(attributes))
+                    .count()
+                    >= *threshold
+            }
+        }
+    }
+}
+
+impl From<&AccessTree> for Policy {
+    fn from(access_tree: &AccessTree) -> Self {
+        match access_tree {
+            AccessTree::Operator(Operator {
+                operator,
+                left,
+                right,
+                ..
+            }) => Policy::Threshold {
+                threshold: match operator {
+                    TreeOperator::And => 2,
+                    TreeOperator::Or => 1,
+                },
+                children: vec![Policy::from(left.as_ref()), Policy::from(right.as_ref())],
+            },
+            AccessTree::Leaf(Leaf { attribute, .. }) => Policy::Leaf(attribute.name.clone()),
+        }
+    }
+}
+
+/// Linear secret sharing scheme matrix `M` with the row to attribute mapping `rho`.
+///
+/// A secret `s` is shared as `lambda = M * (s, r_2, ..., r_n)`, row `i` belonging to the
+/// attribute `rho[i]`. The rows of a set of attributes that satisfies the policy span
+/// `(1, 0, ..., 0)`, the reconstruction coefficients `w` with `sum w_i * M_i = (1, 0, ..., 0)`
+/// then recover `s = sum w_i * lambda_i`. Rows are in the order of the leaves of the policy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LsssMatrix {
+    pub rows: Vec<Vec<Fr>>,
+    pub rho: Vec<String>,
+}
+
+impl LsssMatrix {
+    /// Converts the policy, with the construction of Liu, Cao and Wong: the children of a
+    /// t-of-n gate with vector `v` get `v` extended by `(i, i^2, ..., i^(t-1))` in t - 1 new
+    /// columns, i.e. the shares of a polynomial of degree t - 1 whose constant term is the
+    /// share of the gate.
+    pub fn from_policy(policy: &Policy) -> Result<LsssMatrix, AbeError> {
+        let mut rows = Vec::new();
+        let mut rho = Vec::new();
+        let mut columns = 1;
+        insert_rows(policy, vec![Fr::one()], &mut columns, &mut rows, &mut rho)?;
+
+        for row in rows.iter_mut() {
+            row.resize(columns, Fr::zero());
+        }
+        Ok(LsssMatrix { rows, rho })
+    }
+
+    /// Converts the access tree, whose leaves then correspond to the rows by index
+    pub fn from_access_tree(access_tree: &AccessTree) -> LsssMatrix {
+        // AND and OR are valid gates, so the conversion cannot fail
+        Self::from_policy(&Policy::from(access_tree)).expect("access tree gates are valid")
+    }
+
+    /// Number of columns, i.e. of values in the vector a secret is shared with
+    pub fn columns(&self) -> usize {
+        self.rows.first().map_or(0, Vec::len)
+    }
+
+    /// Shares the secret, one share per row
+    pub fn share<R: Rng + ?Sized>(&self, secret: Fr, rng: &mut R) -> Vec<Fr> {
+        let mut vector = vec![secret];
+        vector.extend((1..self.columns()).map(|_| rng.gen::<Fr>()));
+
+        self.rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(vector.iter())
+                    .fold(Fr::zero(), |share, (m, v)| share + *m * *v)
+            })
+            .collect()
+    }
+
+    /// Returns the rows of the attributes with their reconstruction coefficients, or `None` if
+    /// the attributes do not satisfy the policy. Rows with a coefficient of zero are left out.
+    pub fn reconstruction_coefficients(
+        &self,
+        attributes: &[AbeAttribute],
+    ) -> Option<Vec<(usize, Fr)>> {
+        let selected = (0..self.rows.len())
+            .filter(|i| attributes.iter().any(|a| a.name == self.rho[*i]))
+            .collect::<Vec<usize>>();
+
+        // solve sum w_i * M_i = (1, 0, ..., 0) by Gaussian elimination on the transposed
+        // system, one equation per column with the target as last entry
+        let unknowns = selected.len();
+        let mut system = (0..self.columns())
+            .map(|column| {
+                let mut equation = selected
+                    .iter()
+                    .map(|i| self.rows[*i][column])
+                    .collect::<Vec<Fr>>();
+                equation.push(if column == 0 { Fr::one() } else { Fr::zero() });
+                equation
+            })
+            .collect::<Vec<Vec<Fr>>>();
+
+        let mut pivots = Vec::new();
+        for unknown in 0..unknowns {
+            let row = pivots.len();
+            let Some(pivot) = (row..system.len()).find(|r| !system[*r][unknown].is_zero()) else {
+                continue;
+            };
+            system.swap(row, pivot);
+
+            let inverse = system[row][unknown].inverse()?;
+            for entry in system[row].iter_mut() {
+                *entry = *entry * inverse;
+            }
+            let pivot_equation = system[row].clone();
+            for (other, equation) in system.iter_mut().enumerate() {
+                let factor = equation[unknown];
+                if other != row && !factor.is_zero() {
+                    for (entry, value) in equation.iter_mut().zip(pivot_equation.iter()) {
+                        *entry = *entry - factor * *value;
+                    }
+                }
+            }
+            pivots.push(unknown);
+        }
+
+        // an equation without unknowns left must have a zero target
+        if system[pivots.len()..]
+            .iter()
+            .any(|equation| !equation[unknowns].is_zero())
+        {
+            return None;
+        }
+
+        // free unknowns are zero, so each pivot takes the target of its equation
+        Some(
+            pivots
+                .iter()
+                .enumerate()
+                .map(|(row, unknown)| (selected[*unknown], system[row][unknowns]))
+                .filter(|(_, coefficient)| !coefficient.is_zero())
+                .collect(),
+        )
+    }
+}
+
+fn insert_rows(
+    policy: &Policy,
+    vector: Vec<Fr>,
+    columns: &mut usize,
+    rows: &mut Vec<Vec<Fr>>,
+    rho: &mut Vec<String>,
+) -> Result<(), AbeError> {
+    match policy {
+        Policy::Leaf(name) => {
+            rows.push(vector);
+            rho.push(name.clone());
+        }
+        Policy::Threshold {
+            threshold,
+            children,
+        } => {
+            if *threshold == 0 || *threshold > children.len() {
+                return Err(AbeError::new(
+                    format!(
+                        "Threshold {} is not between 1 and the {} children of the gate",
+                        threshold,
+                        children.len()
+                    )
+                    .as_str(),
+                ));
+            }
+
+            let first_column = *columns;
+            *columns += threshold - 1;
+            let mut x = Fr::zero();
+            for child in children {
+                x = x + Fr::one();
+                let mut child_vector = vector.clone();
+                child_vector.resize(first_column, Fr::zero());
+                let mut power = x;
+                for _ in 1..*threshold {
+                    child_vector.push(power);
+                    power = power * x;
+                }
+                insert_rows(child, child_vector, columns, rows, rho)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rabe_bn::Fr;
+    use rand::Rng;
+
+    use crate::abe_attribute::AbeAttribute;
+    use crate::access_tree::{GetAttributes, MinimalSetFinder};
+    use crate::lsss::{LsssMatrix, Policy};
+    use crate::parser::AccessTreeParser;
+
+    // checks every subset of the universe against `is_satisfied` and reconstructs the secret
+    // from the satisfying ones
+    fn check_policy(
+        matrix: &LsssMatrix,
+        universe: &[&str],
+        is_satisfied: impl Fn(&[AbeAttribute]) -> bool,
+    ) {
+        let rng = &mut rand::thread_rng();
+        let secret: Fr = rng.gen();
+        let shares = matrix.share(secret, rng);
+
+        for size in 0..=universe.len() {
+            for subset in universe.iter().combinations(size) {
+                let attributes = subset
+                    .iter()
+                    .map(|name| AbeAttribute::new(name))
+                    .collect::<Vec<AbeAttribute>>();
+                let coefficients = matrix.reconstruction_coefficients(&attributes);
+                assert_eq!(
+                    coefficients.is_some(),
+                    is_satisfied(&attributes),
+                    "{:?}",
+                    subset
+                );
+
+                if let Some(coefficients) = coefficients {
+                    let reconstructed = coefficients
+                        .iter()
+                        .fold(Fr::zero(), |sum, (i, w)| sum + *w * shares[*i]);
+                    assert_eq!(reconstructed, secret, "{:?}", subset);
+                    assert!(coefficients
+                        .iter()
+                        .all(|(i, _)| subset.contains(&&matrix.rho[*i].as_str())));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_access_tree_matches_is_satisfiable() {
+        for policy in [
+            "A",
+            "A&B",
+            "A|B",
+            "(A&B)|(C&D)",
+            "(A|B)&(C|D)",
+            "(A&B)|(A&C)",
+            "((A|B)&C)|(D&(E|A))",
+            "(A|D)&(B|E)&C&A",
+        ] {
+            let access_tree = AccessTreeParser::new(policy).parse().unwrap();
+            let matrix = LsssMatrix::from_access_tree(&access_tree);
+
+            let leaves = access_tree.get_attributes();
+            assert_eq!(matrix.rows.len(), leaves.len());
+            assert!(matrix
+                .rho
+                .iter()
+                .zip(leaves.iter())
+                .all(|(r, l)| *r == l.name));
+            check_policy(&matrix, &["A", "B", "C", "D", "E"], |attributes| {
+                access_tree.is_satisfiable(attributes)
+            });
+        }
+    }
+
+    #[test]
+    fn test_threshold_gates() {
+        let leaf = |name: &str| Policy::Leaf(name.to_string());
+        let policy = Policy::Threshold {
+            threshold: 2,
+            children: vec![
+                leaf("A"),
+                leaf("B"),
+                Policy::Threshold {
+                    threshold: 3,
+                    children: vec![leaf("C"), leaf("D"), leaf("E"), leaf("A")],
+                },
+            ],
+        };
+        let matrix = LsssMatrix::from_policy(&policy).unwrap();
+
+        assert_eq!(matrix.columns(), 4);
+        check_policy(&matrix, &["A", "B", "C", "D", "E"], |attributes| {
+            policy.is_satisfied(attributes)
+        });
+    }
+
+    #[test]
+    fn test_invalid_thresholds() {
+        for threshold in [0, 3] {
+            let policy = Policy::Threshold {
+                threshold,
+                children: vec![Policy::Leaf("A".to_string()), Policy::Leaf("B".to_string())],
+            };
+            assert!(LsssMatrix::from_policy(&policy).is_err());
+        }
+    }
+}
@@ -0,0 +1,221 @@
+// Large-universe ciphertext-policy ABE after Bethencourt, Sahai and Waters (2007), adapted to
+// the asymmetric pairing of rabe-bn. Attributes are hashed to G1 with `hash_to_g1` instead of
+// being registered at setup, so any string can be used in policies and keys and the public key
+// has a fixed size.
+//
+// A key for the attribute set S holds `d = g1^((alpha + r) / beta)` and, for every j in S,
+// `d_j = g1^r * H(j)^r_j` and `d'_j = g2^r_j`. The secret `s` of a cipher text is shared over
+// its access tree, leaf y receiving `q_y`, and the cipher text holds `c_0 = h^s` and per leaf
+// `c_y = g2^q_y` and `c'_y = H(att(y))^q_y`. For a leaf with an attribute of the key
+// `e(d_j, c_y) / e(c'_y, d'_j) = e(g1, g2)^(r * q_y)`, the shares of a satisfying set of leaves
+// combine to `e(g1, g2)^(r * s)`, which divides out of `e(d, c_0) = e(g1, g2)^((alpha + r) * s)`.
+
+use std::collections::BTreeMap;
+
+use rabe_bn::{pairing, Fr, Gt, G1, G2};
+use rand::Rng;
+
+use crate::abe_attribute::{AbeAttribute, AbeIdentifier};
+use crate::access_tree::{AccessTree, AssignValues, GetAttributes};
+use crate::dem::{Aes256Gcm, BuiltinDems, DataEncapsulation, DemResolver};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+use crate::hash_to_curve::hash_to_g1;
+use crate::kdf;
+use crate::kdf::KeySchedule;
+use crate::models::{
+    AbeDecrypted, AbeLuCipherText, AbeLuHeader, AbeLuMasterKey, AbeLuPublicKey, AbeLuSecretKey,
+};
+
+const ATTRIBUTE_LABEL: &str = "abe-lu-attribute";
+
+pub fn setup<R: Rng + ?Sized>(g1: G1, g2: G2, rng: &mut R) -> (AbeLuPublicKey, AbeLuMasterKey) {
+    let alpha: Fr = rng.gen();
+    let beta: Fr = rng.gen();
+
+    (
+        AbeLuPublicKey {
+            g1,
+            g2,
+            h: g2 * beta,
+            y: pairing(g1, g2).pow(alpha),
+        },
+        AbeLuMasterKey { alpha, beta },
+    )
+}
+
+/// Creates a key for the attributes, which need not be known to anyone beforehand
+pub fn keygen<R: Rng + ?Sized>(
+    attributes: &[String],
+    public_key: &AbeLuPublicKey,
+    master_key: &AbeLuMasterKey,
+    rng: &mut R,
+) -> Result<AbeLuSecretKey, AbeError> {
+    if attributes.is_empty() {
+        return Err(AbeError::new("No attributes given"));
+    }
+    let beta_inverse = master_key
+        .beta
+        .inverse()
+        .ok_or(AbeError::new("Could not calculate inverse of beta"))?;
+
+    // d = g1^((alpha + r) / beta), a fresh r for every key prevents collusion
+    let r: Fr = rng.gen();
+    let d = public_key.g1 * ((master_key.alpha + r) * beta_inverse);
+
+    // dj = g1^r * H(j)^rj, d'j = g2^rj
+    let mut arr_d = BTreeMap::new();
+    let mut arr_d_prime = BTreeMap::new();
+    for attribute in attributes {
+        let r_j: Fr = rng.gen();
+        arr_d.insert(
+            attribute.clone(),
+            public_key.g1 * r + hash_attribute(attribute) * r_j,
+        );
+        arr_d_prime.insert(attribute.clone(), public_key.g2 * r_j);
+    }
+
+    Ok(AbeLuSecretKey {
+        system_id: public_key.system_id(),
+        d,
+        arr_d,
+        arr_d_prime,
+    })
+}
+
+pub fn encrypt<R: Rng + ?Sized>(
+    secret: &Gt,
+    message: &[u8],
+    public_key: &AbeLuPublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeLuCipherText, AbeError> {
+    encrypt_with(&Aes256Gcm, secret, message, public_key, access_tree, rng)
+}
+
+/// Like `encrypt`, protecting the payload with the given data encapsulation mechanism
+pub fn encrypt_with<R: Rng + ?Sized>(
+    dem: &dyn DataEncapsulation,
+    secret: &Gt,
+    message: &[u8],
+    public_key: &AbeLuPublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeLuCipherText, AbeError> {
+    // s = random field element
+    let s: Fr = rng.gen();
+
+    // c0 = h^s
+    let c_0 = public_key.h * s;
+
+    // c1 = m * y^s
+    let c_1 = *secret * public_key.y.pow(s);
+
+    // q_y = shares of s
+    let mut filled_tree = access_tree.assign_values(s, Some(s), rng);
+    filled_tree.assign_indices();
+
+    // cy = g2^q_y, c'y = H(att(y))^q_y
+    let mut arr_c = Vec::new();
+    let mut arr_c_prime = Vec::new();
+    for leaf in filled_tree.get_attributes() {
+        let share = leaf.value.ok_or(AbeError::new(
+            format!("Expected value for {} but got None", leaf.name).as_str(),
+        ))?;
+        arr_c.push((AbeIdentifier::from(leaf.clone()), public_key.g2 * share));
+        arr_c_prime.push((
+            AbeIdentifier::from(leaf.clone()),
+            hash_attribute(&leaf.name) * share,
+        ));
+    }
+
+    // the shares reveal s, the cipher text keeps the bare policy
+    filled_tree.clear_values();
+    let header = AbeLuHeader {
+        version: kdf::FORMAT_VERSION,
+        system_id: public_key.system_id(),
+        algorithm: dem.algorithm().to_string(),
+        access_tree: Box::new(filled_tree),
+        c_0,
+        c_1,
+        arr_c,
+        arr_c_prime,
+    };
+    let message = dem.encrypt(&payload_key(secret, &header), message)?;
+
+    Ok(AbeLuCipherText { header, message })
+}
+
+/// Decrypts the cipher text if the attributes of the secret key satisfy its access tree
+pub fn decrypt(
+    cipher_text: &AbeLuCipherText,
+    secret_key: &AbeLuSecretKey,
+) -> Result<AbeDecrypted, AbeError> {
+    decrypt_with(&BuiltinDems, cipher_text, secret_key)
+}
+
+/// Like `decrypt`, for a payload protected with a data encapsulation mechanism `dems` resolves
+pub fn decrypt_with(
+    dems: &dyn DemResolver,
+    cipher_text: &AbeLuCipherText,
+    secret_key: &AbeLuSecretKey,
+) -> Result<AbeDecrypted, AbeError> {
+    let dem = cipher_text.validate(dems)?;
+    secret_key.validate()?;
+    let header = &cipher_text.header;
+    if header.system_id != secret_key.system_id {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::SystemMismatch,
+            "Secret key belongs to a different system than the cipher text",
+        ));
+    }
+
+    let attributes = secret_key
+        .arr_d
+        .keys()
+        .map(|name| AbeAttribute::new(name))
+        .collect::<Vec<AbeAttribute>>();
+    let leaves = header
+        .access_tree
+        .find_satisfying_leaves(&attributes)
+        .ok_or(AbeError::new(
+            "Secret key attributes do not satisfy the access tree of the cipher text",
+        ))?;
+
+    // e(dj, cy) / e(c'y, d'j) = e(g1, g2)^(r * q_y), the shares of the leaves add up to s
+    let mut product = Gt::one();
+    for leaf in &leaves {
+        let (_, c) = find_component(&header.arr_c, leaf)?;
+        let (_, c_prime) = find_component(&header.arr_c_prime, leaf)?;
+        product = product
+            * pairing(secret_key.arr_d[&leaf.name], *c)
+            * pairing(*c_prime, secret_key.arr_d_prime[&leaf.name]).inverse();
+    }
+
+    // m = c1 * e(g1, g2)^(r * s) / e(d, c0)
+    let secret = header.c_1 * product * pairing(secret_key.d, header.c_0).inverse();
+    let message = dem.decrypt(&payload_key(&secret, header), &cipher_text.message)?;
+
+    Ok(AbeDecrypted { message, secret })
+}
+
+fn hash_attribute(attribute: &str) -> G1 {
+    hash_to_g1(ATTRIBUTE_LABEL, attribute.as_bytes())
+}
+
+fn find_component<'a, G>(
+    components: &'a [(AbeIdentifier, G)],
+    leaf: &AbeAttribute,
+) -> Result<&'a (AbeIdentifier, G), AbeError> {
+    components
+        .iter()
+        .find(|(identifier, _)| leaf == identifier)
+        .ok_or(AbeError::with_kind(
+            AbeErrorKind::LeafMismatch,
+            format!("No cipher text component for leaf {}", leaf.name).as_str(),
+        ))
+}
+
+// Derives the key of the payload from the secret, bound to the header
+fn payload_key(secret: &Gt, header: &AbeLuHeader) -> [u8; kdf::KEY_SIZE] {
+    KeySchedule::for_header(secret, header).derive(kdf::PAYLOAD_KEY)
+}
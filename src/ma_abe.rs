@@ -0,0 +1,356 @@
+// Decentralized multi-authority ciphertext-policy ABE of Lewko and Waters, "Decentralizing
+// Attribute-Based Encryption" (EUROCRYPT 2011), the prime order construction in the random oracle
+// model adapted to the asymmetric pairing of rabe-bn. Any party can become an authority by
+// creating keys for its own attributes, there is no central authority beyond the global
+// parameters.
+//
+// Authority attribute i has the secrets `alpha_i` and `y_i` and is published as
+// `e(g1, g2)^alpha_i` and `g2^y_i`. A user with the global identifier GID gets
+// `k_i = g1^alpha_i * H(GID)^y_i` for each of its attributes, where H hashes to G1 with
+// `hash_to_g1`. Keys are bound to the GID, so keys of different users cannot be combined.
+//
+// The access tree is converted to an LSSS matrix with `lsss::LsssMatrix`, `s` is shared as
+// `lambda_x` and zero as `omega_x`. The cipher text holds `c_0 = m * e(g1, g2)^s` and per row x
+// with attribute i `c1_x = e(g1, g2)^lambda_x * e(g1, g2)^(alpha_i * r_x)`, `c2_x = g2^r_x` and
+// `c3_x = g2^(y_i * r_x) * g2^omega_x`. Then
+// `c1_x * e(H(GID), c3_x) / e(k_i, c2_x) = e(g1, g2)^lambda_x * e(H(GID), g2)^omega_x`, and with
+// the reconstruction coefficients of the rows of the key the `omega_x` cancel to leave
+// `e(g1, g2)^s`.
+//
+// The construction maps every row to a distinct attribute, its security proof does not hold for
+// policies that use an attribute more than once, so encryption refuses them.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use rabe_bn::{pairing, Fr, Gt, G1, G2};
+use rand::Rng;
+
+use crate::abe_attribute::{AbeAttribute, AbeIdentifier};
+use crate::access_tree::{AccessTree, GetAttributes};
+use crate::dem::{Aes256Gcm, BuiltinDems, DataEncapsulation, DemResolver};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+use crate::hash_to_curve::hash_to_g1;
+use crate::kdf;
+use crate::kdf::KeySchedule;
+use crate::lsss::LsssMatrix;
+use crate::models::{
+    AbeDecrypted, AbeMaAttributeMasterKey, AbeMaAttributePublicKey, AbeMaCipherText,
+    AbeMaGlobalParameters, AbeMaHeader, AbeMaMasterKey, AbeMaPublicKey, AbeMaSecretKey,
+};
+
+const GID_LABEL: &str = "abe-ma-gid";
+
+/// Creates the parameters all authorities of a system share
+pub fn global_setup(g1: G1, g2: G2) -> AbeMaGlobalParameters {
+    AbeMaGlobalParameters { g1, g2 }
+}
+
+/// Creates the keys of an authority for its attributes. Attributes are qualified with the name
+/// of the authority, "manager" of the authority "hr" is "hr:manager" in keys and policies.
+pub fn authority_setup<R: Rng + ?Sized>(
+    parameters: &AbeMaGlobalParameters,
+    authority: &str,
+    attributes: &[String],
+    rng: &mut R,
+) -> Result<(AbeMaPublicKey, AbeMaMasterKey), AbeError> {
+    // the qualified names have to be valid attribute names of a policy
+    if authority.is_empty()
+        || !authority
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return Err(AbeError::new(
+            format!("Invalid authority name {:?}", authority).as_str(),
+        ));
+    }
+    if attributes.is_empty() {
+        return Err(AbeError::new("No attributes given"));
+    }
+
+    let e = pairing(parameters.g1, parameters.g2);
+    let mut public_attributes = BTreeMap::new();
+    let mut master_attributes = BTreeMap::new();
+    for attribute in attributes {
+        // alpha_i, y_i = random field elements
+        let alpha: Fr = rng.gen();
+        let y: Fr = rng.gen();
+
+        let name = format!("{}:{}", authority, attribute);
+        public_attributes.insert(
+            name.clone(),
+            AbeMaAttributePublicKey {
+                e_alpha: e.pow(alpha),
+                g2_y: parameters.g2 * y,
+            },
+        );
+        master_attributes.insert(name, AbeMaAttributeMasterKey { alpha, y });
+    }
+
+    let system_id = parameters.system_id();
+    Ok((
+        AbeMaPublicKey {
+            system_id,
+            authority: authority.to_string(),
+            attributes: public_attributes,
+        },
+        AbeMaMasterKey {
+            system_id,
+            authority: authority.to_string(),
+            attributes: master_attributes,
+        },
+    ))
+}
+
+/// Issues the attributes of the authority, by their qualified names, to the user with the
+/// global identifier
+pub fn keygen(
+    gid: &str,
+    attributes: &[String],
+    parameters: &AbeMaGlobalParameters,
+    master_key: &AbeMaMasterKey,
+) -> Result<AbeMaSecretKey, AbeError> {
+    if attributes.is_empty() {
+        return Err(AbeError::new("No attributes given"));
+    }
+    if master_key.system_id != parameters.system_id() {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::SystemMismatch,
+            "Master key belongs to a different system than the parameters",
+        ));
+    }
+
+    // ki = g1^alpha_i * H(GID)^y_i
+    let h = hash_gid(gid);
+    let arr_k = attributes
+        .iter()
+        .map(|attribute| {
+            let secret = master_key.attributes.get(attribute).ok_or(AbeError::new(
+                format!(
+                    "Attribute {} is not managed by authority {}",
+                    attribute, master_key.authority
+                )
+                .as_str(),
+            ))?;
+            Ok((
+                attribute.clone(),
+                parameters.g1 * secret.alpha + h * secret.y,
+            ))
+        })
+        .collect::<Result<BTreeMap<String, G1>, AbeError>>()?;
+
+    Ok(AbeMaSecretKey {
+        system_id: master_key.system_id,
+        gid: gid.to_string(),
+        arr_k,
+    })
+}
+
+/// Combines keys that authorities issued for the same global identifier
+pub fn combine(keys: &[AbeMaSecretKey]) -> Result<AbeMaSecretKey, AbeError> {
+    let (first, others) = keys
+        .split_first()
+        .ok_or(AbeError::new("No secret keys given"))?;
+
+    let mut combined = first.clone();
+    for key in others {
+        if key.system_id != combined.system_id {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::SystemMismatch,
+                "Secret keys belong to different systems",
+            ));
+        }
+        if key.gid != combined.gid {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::IdentityMismatch,
+                format!(
+                    "Secret keys were issued for {} and {}",
+                    combined.gid, key.gid
+                )
+                .as_str(),
+            ));
+        }
+        combined
+            .arr_k
+            .extend(key.arr_k.iter().map(|(a, k)| (a.clone(), *k)));
+    }
+
+    Ok(combined)
+}
+
+pub fn encrypt<R: Rng + ?Sized>(
+    secret: &Gt,
+    message: &[u8],
+    parameters: &AbeMaGlobalParameters,
+    public_keys: &[AbeMaPublicKey],
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeMaCipherText, AbeError> {
+    encrypt_with(
+        &Aes256Gcm,
+        secret,
+        message,
+        parameters,
+        public_keys,
+        access_tree,
+        rng,
+    )
+}
+
+/// Like `encrypt`, protecting the payload with the given data encapsulation mechanism. The
+/// public keys have to cover the authorities of all attributes of the access tree.
+pub fn encrypt_with<R: Rng + ?Sized>(
+    dem: &dyn DataEncapsulation,
+    secret: &Gt,
+    message: &[u8],
+    parameters: &AbeMaGlobalParameters,
+    public_keys: &[AbeMaPublicKey],
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeMaCipherText, AbeError> {
+    let system_id = parameters.system_id();
+    let mut attributes = BTreeMap::new();
+    for public_key in public_keys {
+        if public_key.system_id != system_id {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::SystemMismatch,
+                format!(
+                    "Public key of authority {} belongs to a different system",
+                    public_key.authority
+                )
+                .as_str(),
+            ));
+        }
+        attributes.extend(public_key.attributes.iter());
+    }
+
+    let mut names = BTreeSet::new();
+    if let Some(leaf) = access_tree
+        .get_attributes()
+        .into_iter()
+        .find(|leaf| !names.insert(leaf.name.clone()))
+    {
+        return Err(AbeError::new(
+            format!(
+                "Attribute {} is used more than once in the access tree",
+                leaf.name
+            )
+            .as_str(),
+        ));
+    }
+
+    let mut access_tree = access_tree.clone();
+    access_tree.clear_values();
+    access_tree.assign_indices();
+
+    // s = random field element, lambda_x = shares of s, omega_x = shares of zero
+    let s: Fr = rng.gen();
+    let matrix = LsssMatrix::from_access_tree(&access_tree);
+    let lambdas = matrix.share(s, rng);
+    let omegas = matrix.share(Fr::zero(), rng);
+
+    // c0 = m * e(g1, g2)^s
+    let e = pairing(parameters.g1, parameters.g2);
+    let c_0 = *secret * e.pow(s);
+
+    // c1x = e(g1, g2)^lambda_x * e(g1, g2)^(alpha_i * r_x), c2x = g2^r_x,
+    // c3x = g2^(y_i * r_x) * g2^omega_x
+    let mut arr_c1 = Vec::new();
+    let mut arr_c2 = Vec::new();
+    let mut arr_c3 = Vec::new();
+    for ((leaf, lambda), omega) in access_tree
+        .get_attributes()
+        .into_iter()
+        .zip(lambdas)
+        .zip(omegas)
+    {
+        let attribute = attributes.get(&leaf.name).ok_or(AbeError::new(
+            format!(
+                "Attribute {} is not managed by any of the authorities",
+                leaf.name
+            )
+            .as_str(),
+        ))?;
+        let r: Fr = rng.gen();
+        let identifier = AbeIdentifier::from(leaf);
+        arr_c1.push((identifier.clone(), e.pow(lambda) * attribute.e_alpha.pow(r)));
+        arr_c2.push((identifier.clone(), parameters.g2 * r));
+        arr_c3.push((identifier, attribute.g2_y * r + parameters.g2 * omega));
+    }
+
+    let header = AbeMaHeader {
+        version: kdf::FORMAT_VERSION,
+        system_id,
+        algorithm: dem.algorithm().to_string(),
+        access_tree: Box::new(access_tree),
+        c_0,
+        arr_c1,
+        arr_c2,
+        arr_c3,
+    };
+    let message = dem.encrypt(&payload_key(secret, &header), message)?;
+
+    Ok(AbeMaCipherText { header, message })
+}
+
+/// Decrypts the cipher text if the attributes of the secret key satisfy its access tree
+pub fn decrypt(
+    cipher_text: &AbeMaCipherText,
+    secret_key: &AbeMaSecretKey,
+) -> Result<AbeDecrypted, AbeError> {
+    decrypt_with(&BuiltinDems, cipher_text, secret_key)
+}
+
+/// Like `decrypt`, for a payload protected with a data encapsulation mechanism `dems` resolves
+pub fn decrypt_with(
+    dems: &dyn DemResolver,
+    cipher_text: &AbeMaCipherText,
+    secret_key: &AbeMaSecretKey,
+) -> Result<AbeDecrypted, AbeError> {
+    let dem = cipher_text.validate(dems)?;
+    let header = &cipher_text.header;
+    if header.system_id != secret_key.system_id {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::SystemMismatch,
+            "Secret key belongs to a different system than the cipher text",
+        ));
+    }
+
+    let attributes = secret_key
+        .arr_k
+        .keys()
+        .map(|name| AbeAttribute::new(name))
+        .collect::<Vec<AbeAttribute>>();
+    let matrix = LsssMatrix::from_access_tree(&header.access_tree);
+    let coefficients = matrix
+        .reconstruction_coefficients(&attributes)
+        .ok_or(AbeError::new(
+            "Secret key attributes do not satisfy the access tree of the cipher text",
+        ))?;
+
+    // prod (c1x * e(H(GID), c3x) / e(ki, c2x))^wx = e(g1, g2)^s, validation made sure the rows
+    // of the matrix line up with the components
+    let h = hash_gid(&secret_key.gid);
+    let mut product = Gt::one();
+    for (row, coefficient) in coefficients {
+        let c1 = header.arr_c1[row].1;
+        let c2 = header.arr_c2[row].1;
+        let c3 = header.arr_c3[row].1;
+        let k = secret_key.arr_k[&matrix.rho[row]];
+        product = product * (c1 * pairing(h, c3) * pairing(k, c2).inverse()).pow(coefficient);
+    }
+
+    // m = c0 / e(g1, g2)^s
+    let secret = header.c_0 * product.inverse();
+    let message = dem.decrypt(&payload_key(&secret, header), &cipher_text.message)?;
+
+    Ok(AbeDecrypted { message, secret })
+}
+
+fn hash_gid(gid: &str) -> G1 {
+    hash_to_g1(GID_LABEL, gid.as_bytes())
+}
+
+// Derives the key of the payload from the secret, bound to the header
+fn payload_key(secret: &Gt, header: &AbeMaHeader) -> [u8; kdf::KEY_SIZE] {
+    KeySchedule::for_header(secret, header).derive(kdf::PAYLOAD_KEY)
+}
@@ -1,32 +1,44 @@
 extern crate rabe_bn;
 extern crate rand;
 
+use std::ffi::OsString;
 use std::fs;
-use std::fs::{read, read_to_string};
-use std::path::PathBuf;
+use std::fs::{read_to_string, File, OpenOptions};
+use std::io::{copy, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
 
 use clap::{Args, Parser, Subcommand};
-use itertools::Itertools;
-use rabe_bn::{Group, Gt, G1, G2};
+use rabe_bn::Gt;
 use rand::Rng;
-use serde::Deserialize;
 
-use crate::errors::abe_error::AbeError;
-use crate::models::{AbeCipherText, AbeMasterKey, AbePublicKey, AbeSecretKey};
-use crate::parser::AccessTreeParser;
-
-mod abe_attribute;
-mod access_tree;
-mod aes;
-mod crypto;
-mod errors;
-mod models;
-mod parser;
+use abe::access_tree::AccessTree;
+use abe::armor;
+use abe::armor::{is_armored, Armored};
+use abe::canonical::{Fingerprint, Fingerprinted};
+use abe::compression;
+use abe::crypto;
+use abe::crypto::PayloadOptions;
+use abe::dem;
+use abe::dem::BuiltinDems;
+use abe::errors::abe_error::AbeError;
+use abe::models::{
+    AbeCipherText, AbeDetachedEnvelope, AbeDetachedHeader, AbeEnvelope, AbePublicKey, AbeSecretKey,
+};
+use abe::padding::Padding;
+use abe::scheme::{
+    AbeScheme, CpAbe, HiddenAbe, KpAbe, LuAbe, MaAbe, OnlineAbe, OutsourcedAbe, SchemeInput,
+    SchemeKey, WatersAbe,
+};
+use abe::stream;
 
 #[derive(Parser)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Write keys and encrypted data as ASCII armored text instead of JSON
+    #[arg(long, global = true)]
+    armor: bool,
 }
 
 #[derive(Subcommand, Clone)]
@@ -35,6 +47,11 @@ enum Commands {
     Keygen(KeygenArgs),
     Encrypt(EncryptArgs),
     Decrypt(DecryptArgs),
+    Rewrap(RewrapArgs),
+    Fingerprint(FingerprintArgs),
+    /// Derive a secret key for a subset of the attributes of a secret key, e.g. to hand it to an
+    /// automation job without asking the authority
+    Delegate(DelegateArgs),
 }
 
 #[derive(Args, Clone)]
@@ -45,9 +62,17 @@ struct SetupArgs {
     /// OUT | Where to save master key
     master_key: PathBuf,
 
-    /// Attribute set of the system
-    #[arg(required = true)]
+    /// Attribute set of the system, not needed by schemes that accept any attribute
     attributes: Vec<String>,
+
+    /// Scheme of the system: "cp" (ciphertext-policy), "kp" (key-policy), "lu"
+    /// (large-universe ciphertext-policy), "waters" (LSSS-based ciphertext-policy), "ma"
+    /// (multi-authority, attributes qualified as "<authority>:<attribute>"), "online"
+    /// (online/offline encryption), "hidden" (policies with hidden values, attributes written as
+    /// "<category>:<value>") or "outsourced" (outsourced decryption). It is recorded in the keys,
+    /// so the other commands pick it up from there
+    #[arg(long, default_value = CpAbe::NAME)]
+    scheme: String,
 }
 
 #[derive(Args, Clone)]
@@ -61,14 +86,22 @@ struct KeygenArgs {
     /// OUT | Where to save secret key
     secret_key: PathBuf,
 
-    /// Attribute set of the key
+    /// Attribute set of the key, or its access policy for key-policy schemes. For the "ma" scheme
+    /// the global identifier of the user comes first
     #[arg(required = true)]
     attributes: Vec<String>,
+
+    /// Let the holder of the key derive keys for subsets of its attributes with "delegate".
+    /// Only for the schemes on the keys of "cp", see `crypto::keygen_delegable` for what such
+    /// keys expose
+    #[arg(long)]
+    delegable: bool,
 }
 
 #[derive(Args, Clone)]
 struct EncryptArgs {
-    /// Access policy, e.g. "(A & B) | (C & D)"
+    /// Access policy, e.g. "(A & B) | (C & D)", or the attributes of the data for key-policy
+    /// schemes
     policy: String,
 
     /// IN | Path to public key
@@ -79,6 +112,37 @@ struct EncryptArgs {
 
     /// OUT | Where to save encrypted data
     output: PathBuf,
+
+    /// OUT | Save the symmetric payload to this file and only the header to the output. The input
+    /// is then encrypted in constant memory, regardless of its size. Without it the whole cipher
+    /// text is held in memory before it is written. Only for the "cp" scheme
+    #[arg(long)]
+    payload: Option<PathBuf>,
+
+    /// Symmetric algorithm for the payload, e.g. "AES-256-GCM" or "AES-256-GCM-SIV"
+    #[arg(long, default_value = dem::DEFAULT_ALGORITHM)]
+    algorithm: String,
+
+    /// Plaintext bytes per encrypted segment of the payload, 65536 if not given. Only for the
+    /// "cp" scheme
+    #[arg(long)]
+    segment_size: Option<usize>,
+
+    /// Compress the input before encryption, e.g. "DEFLATE". The size of the payload then
+    /// depends on the content of the input. Only for the "cp" scheme
+    #[arg(long)]
+    compression: Option<String>,
+
+    /// Pad the input to hide its exact size: "pow2" for the next power of two, "block:<size>"
+    /// for the next multiple of a block size or "random:<bound>" for up to that many random bytes.
+    /// Sizes and bounds are at most 1 GiB. Only for the "cp" scheme
+    #[arg(long, value_parser = parse_padding)]
+    padding: Option<Padding>,
+
+    /// Scheme to encrypt with instead of the one recorded in the public key, e.g. "hidden" with
+    /// a key of "cp"
+    #[arg(long)]
+    scheme: Option<String>,
 }
 
 #[derive(Args, Clone)]
@@ -86,21 +150,87 @@ struct DecryptArgs {
     /// IN | Path to secret key
     private_key: PathBuf,
 
-    /// IN | Path to encrypted data
+    /// IN | Path to encrypted data or envelope
+    input: PathBuf,
+
+    /// OUT | Where to save decrypted data. It only appears once the whole payload is decrypted and
+    /// authenticated
+    output: PathBuf,
+
+    /// IN | Path to the payload if the encrypted data is a detached header or envelope, which is
+    /// decrypted in constant memory. Without it the whole cipher text is read into memory. Only
+    /// for the "cp" scheme
+    #[arg(long)]
+    payload: Option<PathBuf>,
+
+    /// Largest number of bytes a compressed payload may decompress to
+    #[arg(long, default_value_t = compression::DEFAULT_SIZE_LIMIT)]
+    size_limit: u64,
+
+    /// Scheme to decrypt with instead of the one recorded in the secret key, e.g. "hidden" with
+    /// a key of "cp"
+    #[arg(long)]
+    scheme: Option<String>,
+}
+
+#[derive(Args, Clone)]
+struct RewrapArgs {
+    /// New access policy, e.g. "(A & B) | (C & D)"
+    policy: String,
+
+    /// IN | Path to secret key satisfying the current policy
+    private_key: PathBuf,
+
+    /// IN | Path to public key of the system of the new policy, envelopes are for the "cp"
+    /// scheme
+    public_key: PathBuf,
+
+    /// IN | Path to encrypted data, envelope or detached header
     input: PathBuf,
 
-    /// OUT | Where to save decrypted data
+    /// OUT | Where to save the envelope for the new policy
     output: PathBuf,
+
+    /// The input is a detached header, its payload is shared with the output without being read
+    #[arg(long)]
+    detached: bool,
 }
+
+#[derive(Args, Clone)]
+struct DelegateArgs {
+    /// IN | Path to public key
+    public_key: PathBuf,
+
+    /// IN | Path to secret key to delegate
+    private_key: PathBuf,
+
+    /// OUT | Where to save delegated secret key
+    secret_key: PathBuf,
+
+    /// Attribute set of the delegated key, a subset of the attributes of the secret key
+    #[arg(required = true)]
+    attributes: Vec<String>,
+}
+
+#[derive(Args, Clone)]
+struct FingerprintArgs {
+    /// IN | Path to public key, secret key, encrypted data, detached header or envelope. They
+    /// are looked up in the scheme recorded in them
+    input: PathBuf,
+}
+
 fn main() {
     let cli = Cli::parse();
     let rng = &mut rand::thread_rng();
 
     let result = match cli.command {
-        Commands::Setup(args) => do_setup(&args, rng),
-        Commands::Keygen(args) => do_keygen(&args, rng),
-        Commands::Encrypt(args) => do_encrypt(&args, rng),
+        Commands::Setup(args) => do_setup(&args, cli.armor, rng),
+        Commands::Keygen(args) => do_keygen(&args, cli.armor, rng),
+        Commands::Encrypt(args) => do_encrypt(&args, cli.armor, rng),
         Commands::Decrypt(args) => do_decrypt(&args),
+        Commands::Rewrap(args) => do_rewrap(&args, cli.armor, rng),
+        Commands::Fingerprint(args) => do_fingerprint(&args),
+        Commands::Delegate(args) => do_delegate(&args, cli.armor, rng),
     };
     match result {
         Ok(_) => println!("Done"),
@@ -108,30 +238,73 @@ fn main() {
     }
 }
 
-// constrain output types to have the `Deserialize` trait
-fn deserialize<'a, T>(data: &'a str) -> Option<T>
-where
-    T: Deserialize<'a>,
-{
-    match serde_json::from_str::<T>(data) {
-        Ok(value) => Some(value),
-        Err(_) => None,
+// accept both JSON and ASCII armored input
+fn deserialize<T: Armored>(data: &str) -> Option<T> {
+    if is_armored(data) {
+        T::from_armored(data).ok()
+    } else {
+        serde_json::from_str::<T>(data).ok()
     }
 }
 
-fn do_setup<R: Rng + ?Sized>(args: &SetupArgs, rng: &mut R) -> Result<(), AbeError> {
-    if args.attributes.len() == 0 {
-        return Err(AbeError::new("No attributes given"));
+fn parse_padding(value: &str) -> Result<Padding, String> {
+    value.parse::<Padding>().map_err(|e| e.message)
+}
+
+// write JSON unless ASCII armored output was requested
+fn serialize<T: Armored>(value: &T, armor: bool) -> Result<String, String> {
+    if armor {
+        value.to_armored().map_err(|e| e.to_string())
+    } else {
+        serde_json::to_string(value).map_err(|e| e.to_string())
     }
+}
 
-    let (public, master) = crypto::setup(&args.attributes, G1::one(), G2::one(), rng);
+// calls the generic function for the scheme of the given name
+macro_rules! with_scheme {
+    ($scheme:expr, $function:ident($($arg:expr),*)) => {
+        match $scheme {
+            CpAbe::NAME => $function::<CpAbe>($($arg),*),
+            KpAbe::NAME => $function::<KpAbe>($($arg),*),
+            LuAbe::NAME => $function::<LuAbe>($($arg),*),
+            WatersAbe::NAME => $function::<WatersAbe>($($arg),*),
+            MaAbe::NAME => $function::<MaAbe>($($arg),*),
+            OnlineAbe::NAME => $function::<OnlineAbe>($($arg),*),
+            HiddenAbe::NAME => $function::<HiddenAbe>($($arg),*),
+            OutsourcedAbe::NAME => $function::<OutsourcedAbe>($($arg),*),
+            other => Err(AbeError::new(format!("Unknown scheme: {:?}", other).as_str())),
+        }
+    };
+}
 
-    let serialized_master_key = serde_json::to_string(&master).map_err(|e| {
-        AbeError::new(format!("Could not serialize master key: {:?}", e.to_string()).as_str())
-    })?;
-    let serialized_public_key = serde_json::to_string(&public).map_err(|e| {
-        AbeError::new(format!("Could not serialize public key: {:?}", e.to_string()).as_str())
-    })?;
+// reads the scheme recorded in a key or cipher text file, which is "cp" for files without one
+fn scheme_of(data: &str) -> Result<String, AbeError> {
+    let json = if is_armored(data) {
+        let label = armor::label(data).ok_or(AbeError::new("Missing BEGIN line"))?;
+        armor::dearmor(label, data).map_err(|e| AbeError::new(e.to_string().as_str()))?
+    } else {
+        data.as_bytes().to_vec()
+    };
+    serde_json::from_slice::<SchemeKey<serde_json::Value>>(&json)
+        .map(|key| key.scheme)
+        .map_err(|e| AbeError::new(format!("Could not read scheme: {:?}", e.to_string()).as_str()))
+}
+
+fn do_setup<R: Rng + ?Sized>(args: &SetupArgs, armor: bool, rng: &mut R) -> Result<(), AbeError> {
+    with_scheme!(args.scheme.as_str(), setup_scheme(args, armor, rng))
+}
+
+fn setup_scheme<S: AbeScheme>(
+    args: &SetupArgs,
+    armor: bool,
+    rng: &mut (impl Rng + ?Sized),
+) -> Result<(), AbeError> {
+    let (public, master) = S::setup(&args.attributes, rng)?;
+
+    let serialized_master_key = serialize(&SchemeKey::new::<S>(master), armor)
+        .map_err(|e| AbeError::new(format!("Could not serialize master key: {:?}", e).as_str()))?;
+    let serialized_public_key = serialize(&SchemeKey::new::<S>(public), armor)
+        .map_err(|e| AbeError::new(format!("Could not serialize public key: {:?}", e).as_str()))?;
 
     fs::write(&args.master_key, serialized_master_key).map_err(|e| {
         AbeError::new(format!("Could not write master key: {:?}", e.to_string()).as_str())
@@ -142,11 +315,7 @@ fn do_setup<R: Rng + ?Sized>(args: &SetupArgs, rng: &mut R) -> Result<(), AbeErr
     Ok(())
 }
 
-fn do_keygen<R: Rng + ?Sized>(args: &KeygenArgs, rng: &mut R) -> Result<(), AbeError> {
-    if args.attributes.len() == 0 {
-        return Err(AbeError::new("No attributes given"));
-    }
-
+fn do_keygen<R: Rng + ?Sized>(args: &KeygenArgs, armor: bool, rng: &mut R) -> Result<(), AbeError> {
     let public_key = read_to_string(&args.public_key).map_err(|e| {
         AbeError::new(format!("Could not read public key: {:?}", e.to_string()).as_str())
     })?;
@@ -154,88 +323,672 @@ fn do_keygen<R: Rng + ?Sized>(args: &KeygenArgs, rng: &mut R) -> Result<(), AbeE
         AbeError::new(format!("Could not read master key: {:?}", e.to_string()).as_str())
     })?;
 
-    let public_key = deserialize::<AbePublicKey>(&public_key)
-        .ok_or(AbeError::new("Could not deserialize public key"))?;
-    let master_key = deserialize::<AbeMasterKey>(&master_key)
-        .ok_or(AbeError::new("Could not deserialize master key"))?;
-
-    // check if attributes exist in public key
-    let public_key_attributes = public_key.big_t.iter().map(|(attr, _)| attr).collect_vec();
-    let not_found = args
-        .attributes
-        .iter()
-        .filter(|attr| !public_key_attributes.contains(attr))
-        .collect_vec();
-    if not_found.len() > 0 {
+    let scheme = scheme_of(&public_key)?;
+    if scheme_of(&master_key)? != scheme {
         return Err(AbeError::new(
-            format!("Attributes not found in public key: {:?}", not_found).as_str(),
+            "Public key and master key belong to different schemes",
         ));
     }
+    with_scheme!(
+        scheme.as_str(),
+        keygen_scheme(args, &public_key, &master_key, armor, rng)
+    )
+}
 
-    let secret_key =
-        crypto::keygen(&args.attributes, &public_key, &master_key, rng).map_err(|e| {
-            AbeError::new(format!("Could not serialize secret key: {:?}", e.to_string()).as_str())
-        })?;
+fn keygen_scheme<S: SchemeFiles>(
+    args: &KeygenArgs,
+    public_key: &str,
+    master_key: &str,
+    armor: bool,
+    rng: &mut (impl Rng + ?Sized),
+) -> Result<(), AbeError> {
+    let public_key = deserialize::<S::PublicKey>(public_key)
+        .ok_or(AbeError::new("Could not deserialize public key"))?;
+    let master_key = deserialize::<S::MasterKey>(master_key)
+        .ok_or(AbeError::new("Could not deserialize master key"))?;
 
-    let serialized_secret_key = serde_json::to_string(&secret_key).map_err(|e| {
-        AbeError::new(format!("Could not serialize secret key: {:?}", e.to_string()).as_str())
-    })?;
-    fs::write(&args.secret_key, serialized_secret_key).map_err(|e| {
+    let input = S::KeyInput::parse(&args.attributes)?;
+    let secret_key = if args.delegable {
+        S::keygen_delegable(&input, &public_key, &master_key, rng)?
+    } else {
+        S::keygen(&input, &public_key, &master_key, rng)?
+    };
+    write_secret_key::<S>(&args.secret_key, secret_key, armor)
+}
+
+fn write_secret_key<S: AbeScheme>(
+    path: &Path,
+    secret_key: S::SecretKey,
+    armor: bool,
+) -> Result<(), AbeError> {
+    let serialized_secret_key = serialize(&SchemeKey::new::<S>(secret_key), armor)
+        .map_err(|e| AbeError::new(format!("Could not serialize secret key: {:?}", e).as_str()))?;
+    fs::write(path, serialized_secret_key).map_err(|e| {
         AbeError::new(format!("Could not write secret key: {:?}", e.to_string()).as_str())
     })?;
 
     Ok(())
 }
 
-fn do_encrypt<R: Rng + ?Sized>(args: &EncryptArgs, rng: &mut R) -> Result<(), AbeError> {
+fn do_encrypt<R: Rng + ?Sized>(
+    args: &EncryptArgs,
+    armor: bool,
+    rng: &mut R,
+) -> Result<(), AbeError> {
     let public_key = read_to_string(&args.public_key).map_err(|e| {
         AbeError::new(format!("Could not read public key: {:?}", e.to_string()).as_str())
     })?;
-    let public_key = deserialize::<AbePublicKey>(&public_key)
-        .ok_or(AbeError::new("Could not deserialize public key"))?;
 
-    let access_tree = AccessTreeParser::new(args.policy.as_str())
-        .parse()
-        .map_err(|e| {
-            AbeError::new(format!("Could not parse access tree: {:?}", e.to_string()).as_str())
-        })?;
+    let scheme = match &args.scheme {
+        Some(scheme) => scheme.clone(),
+        None => scheme_of(&public_key)?,
+    };
+    with_scheme!(
+        scheme.as_str(),
+        encrypt_scheme(args, &public_key, armor, rng)
+    )
+}
 
-    let input = read(&args.input).map_err(|e| {
-        AbeError::new(format!("Could not read input file: {:?}", e.to_string()).as_str())
-    })?;
+fn encrypt_scheme<S: SchemeFiles>(
+    args: &EncryptArgs,
+    public_key: &str,
+    armor: bool,
+    rng: &mut (impl Rng + ?Sized),
+) -> Result<(), AbeError> {
+    let public_key = deserialize::<S::PublicKey>(public_key)
+        .ok_or(AbeError::new("Could not deserialize public key"))?;
+    let input = S::EncryptInput::parse(std::slice::from_ref(&args.policy))?;
+    let dem = dem::from_algorithm(&args.algorithm).ok_or(AbeError::new(
+        format!("Unknown symmetric algorithm: {:?}", args.algorithm).as_str(),
+    ))?;
 
-    let secret: Gt = rng.gen();
-    let ciphertext = crypto::encrypt(&secret, &input, &public_key, &access_tree, rng)
-        .map_err(|e| AbeError::new(format!("Could not encrypt: {:?}", e.to_string()).as_str()))?;
+    S::encrypt_file(args, dem, &public_key, &input, armor, rng)
+}
 
-    let serialized_ciphertext = serde_json::to_string(&ciphertext).map_err(|e| {
-        AbeError::new(format!("Could not serialize cipher text: {:?}", e.to_string()).as_str())
-    })?;
-    fs::write(&args.output, serialized_ciphertext).map_err(|e| {
-        AbeError::new(format!("Could not write output file: {:?}", e.to_string()).as_str())
+fn do_decrypt(args: &DecryptArgs) -> Result<(), AbeError> {
+    let secret_key = read_to_string(&args.private_key).map_err(|e| {
+        AbeError::new(format!("Could not read secret key: {:?}", e.to_string()).as_str())
     })?;
 
-    Ok(())
+    let scheme = match &args.scheme {
+        Some(scheme) => scheme.clone(),
+        None => scheme_of(&secret_key)?,
+    };
+    with_scheme!(scheme.as_str(), decrypt_scheme(args, &secret_key))
 }
 
-fn do_decrypt(args: &DecryptArgs) -> Result<(), AbeError> {
+fn decrypt_scheme<S: SchemeFiles>(args: &DecryptArgs, secret_key: &str) -> Result<(), AbeError> {
+    let secret_key = deserialize::<S::SecretKey>(secret_key)
+        .ok_or(AbeError::new("Could not deserialize secret key"))?;
     let cipher_text = read_to_string(&args.input).map_err(|e| {
         AbeError::new(format!("Could not read cipher text: {:?}", e.to_string()).as_str())
     })?;
-    let cipher_text = deserialize::<AbeCipherText>(&cipher_text)
-        .ok_or(AbeError::new("Could not deserialize cipher text"))?;
 
+    S::decrypt_file(args, &cipher_text, &secret_key)
+}
+
+fn do_rewrap<R: Rng + ?Sized>(args: &RewrapArgs, armor: bool, rng: &mut R) -> Result<(), AbeError> {
     let secret_key = read_to_string(&args.private_key).map_err(|e| {
         AbeError::new(format!("Could not read secret key: {:?}", e.to_string()).as_str())
     })?;
-    let secret_key =
-        deserialize::<AbeSecretKey>(&secret_key).expect("Could not deserialize secret key");
+    // envelopes are for the scheme of `crypto`, whatever the scheme of the input
+    let public_key = read_to_string(&args.public_key).map_err(|e| {
+        AbeError::new(format!("Could not read public key: {:?}", e.to_string()).as_str())
+    })?;
+    let public_key = deserialize::<AbePublicKey>(&public_key)
+        .ok_or(AbeError::new("Could not deserialize public key"))?;
+    let access_tree = AccessTree::parse(std::slice::from_ref(&args.policy))?;
 
-    let decrypted = crypto::decrypt(&cipher_text, &secret_key)
-        .map_err(|e| AbeError::new(format!("Could not decrypt: {:?}", e.to_string()).as_str()))?;
-    fs::write(&args.output, decrypted.message).map_err(|e| {
+    with_scheme!(
+        scheme_of(&secret_key)?.as_str(),
+        rewrap_scheme(args, &secret_key, &public_key, &access_tree, armor, rng)
+    )
+}
+
+fn rewrap_scheme<S: SchemeFiles>(
+    args: &RewrapArgs,
+    secret_key: &str,
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    armor: bool,
+    rng: &mut (impl Rng + ?Sized),
+) -> Result<(), AbeError> {
+    let secret_key = deserialize::<S::SecretKey>(secret_key)
+        .ok_or(AbeError::new("Could not deserialize secret key"))?;
+    let input = read_to_string(&args.input).map_err(|e| {
+        AbeError::new(format!("Could not read input file: {:?}", e.to_string()).as_str())
+    })?;
+
+    let serialized_envelope = S::rewrap_file(
+        args,
+        &input,
+        &secret_key,
+        public_key,
+        access_tree,
+        armor,
+        rng,
+    )?;
+    fs::write(&args.output, serialized_envelope).map_err(|e| {
         AbeError::new(format!("Could not write output file: {:?}", e.to_string()).as_str())
     })?;
+    Ok(())
+}
+
+// How the commands handle the files of a scheme. The defaults hold a cipher text in memory as a
+// whole, `CpAbe` adds payload options, detached payloads and envelopes.
+trait SchemeFiles: AbeScheme + Sized {
+    fn encrypt_file<R: Rng + ?Sized>(
+        args: &EncryptArgs,
+        dem: &dyn dem::DataEncapsulation,
+        public_key: &Self::PublicKey,
+        input: &Self::EncryptInput,
+        armor: bool,
+        rng: &mut R,
+    ) -> Result<(), AbeError> {
+        if args.payload.is_some()
+            || args.segment_size.is_some()
+            || args.compression.is_some()
+            || args.padding.is_some()
+        {
+            return Err(AbeError::new(
+                format!(
+                    "Payload options are not supported by scheme {:?}",
+                    Self::NAME
+                )
+                .as_str(),
+            ));
+        }
+        let message = fs::read(&args.input).map_err(|e| {
+            AbeError::new(format!("Could not read input file: {:?}", e.to_string()).as_str())
+        })?;
+
+        let secret: Gt = rng.gen();
+        let cipher_text = Self::encrypt(dem, &secret, &message, public_key, input, rng)?;
+        let serialized_ciphertext = serialize(&SchemeKey::new::<Self>(cipher_text), armor)
+            .map_err(|e| {
+                AbeError::new(format!("Could not serialize cipher text: {:?}", e).as_str())
+            })?;
+        fs::write(&args.output, serialized_ciphertext).map_err(|e| {
+            AbeError::new(format!("Could not write output file: {:?}", e.to_string()).as_str())
+        })?;
+
+        Ok(())
+    }
+
+    fn decrypt_file(
+        args: &DecryptArgs,
+        cipher_text: &str,
+        secret_key: &Self::SecretKey,
+    ) -> Result<(), AbeError> {
+        if args.payload.is_some() {
+            return Err(AbeError::new(
+                format!(
+                    "Detached payloads are not supported by scheme {:?}",
+                    Self::NAME
+                )
+                .as_str(),
+            ));
+        }
+        let cipher_text = deserialize::<Self::CipherText>(cipher_text)
+            .ok_or(AbeError::new("Could not deserialize cipher text"))?;
+
+        let decrypted = Self::decrypt(&cipher_text, secret_key).map_err(|e| {
+            AbeError::new(format!("Could not decrypt: {:?}", e.to_string()).as_str())
+        })?;
+        let mut output = OutputFile::create(&args.output, "output")?;
+        output.write_all(&decrypted.message).map_err(|e| {
+            AbeError::new(format!("Could not write output file: {:?}", e.to_string()).as_str())
+        })?;
+        output.persist()
+    }
+
+    // returns the serialized envelope
+    fn rewrap_file<R: Rng + ?Sized>(
+        args: &RewrapArgs,
+        input: &str,
+        secret_key: &Self::SecretKey,
+        public_key: &AbePublicKey,
+        access_tree: &AccessTree,
+        armor: bool,
+        rng: &mut R,
+    ) -> Result<String, AbeError> {
+        if args.detached {
+            return Err(AbeError::new(
+                format!(
+                    "Detached headers are not supported by scheme {:?}",
+                    Self::NAME
+                )
+                .as_str(),
+            ));
+        }
+        let cipher_text = deserialize::<Self::CipherText>(input)
+            .ok_or(AbeError::new("Could not deserialize cipher text"))?;
+
+        let envelope = Self::rewrap(
+            &BuiltinDems,
+            cipher_text,
+            secret_key,
+            public_key,
+            access_tree,
+            rng,
+        )?;
+        serialize(&SchemeKey::new::<CpAbe>(envelope), armor)
+            .map_err(|e| AbeError::new(format!("Could not serialize envelope: {:?}", e).as_str()))
+    }
+
+    // fingerprint of a public key, secret key or cipher text of the scheme
+    fn fingerprint(input: &str) -> Option<Fingerprint> {
+        deserialize::<Self::PublicKey>(input)
+            .map(|public_key| public_key.fingerprint())
+            .or_else(|| deserialize::<Self::SecretKey>(input).map(|key| key.fingerprint()))
+            .or_else(|| deserialize::<Self::CipherText>(input).map(|text| text.fingerprint()))
+    }
+}
+
+impl SchemeFiles for KpAbe {}
+
+impl SchemeFiles for LuAbe {}
+
+impl SchemeFiles for WatersAbe {}
+
+impl SchemeFiles for MaAbe {}
+
+impl SchemeFiles for OnlineAbe {}
+
+impl SchemeFiles for HiddenAbe {}
+
+impl SchemeFiles for OutsourcedAbe {}
+
+impl SchemeFiles for CpAbe {
+    fn encrypt_file<R: Rng + ?Sized>(
+        args: &EncryptArgs,
+        dem: &dyn dem::DataEncapsulation,
+        public_key: &AbePublicKey,
+        access_tree: &AccessTree,
+        armor: bool,
+        rng: &mut R,
+    ) -> Result<(), AbeError> {
+        let mut input = File::open(&args.input).map(BufReader::new).map_err(|e| {
+            AbeError::new(format!("Could not read input file: {:?}", e.to_string()).as_str())
+        })?;
+
+        let options = PayloadOptions {
+            segment_size: Some(args.segment_size.unwrap_or(stream::DEFAULT_SEGMENT_SIZE)),
+            compression: args.compression.clone(),
+            padding: args.padding,
+        };
+        let secret: Gt = rng.gen();
+        let serialized_ciphertext = match &args.payload {
+            None => {
+                let (header, mut encryptor) = crypto::encrypt_stream(
+                    dem,
+                    &secret,
+                    &options,
+                    Vec::new(),
+                    public_key,
+                    access_tree,
+                    rng,
+                )
+                .map_err(|e| {
+                    AbeError::new(format!("Could not encrypt: {:?}", e.to_string()).as_str())
+                })?;
+                // the cipher text embeds the payload, so it is built in memory as a whole
+                let message = copy(&mut input, &mut encryptor)
+                    .and_then(|_| encryptor.finish())
+                    .map_err(|e| {
+                        AbeError::new(format!("Could not encrypt: {:?}", e.to_string()).as_str())
+                    })?;
+                serialize(
+                    &SchemeKey::new::<Self>(AbeCipherText { header, message }),
+                    armor,
+                )
+            }
+            Some(payload_path) => {
+                // the payload goes straight to disk, only one segment is held in memory
+                let payload = OutputFile::create(payload_path, "payload")?;
+                let (header, mut encryptor) = crypto::encrypt_stream(
+                    dem,
+                    &secret,
+                    &options,
+                    payload,
+                    public_key,
+                    access_tree,
+                    rng,
+                )
+                .map_err(|e| {
+                    AbeError::new(format!("Could not encrypt: {:?}", e.to_string()).as_str())
+                })?;
+                copy(&mut input, &mut encryptor)
+                    .and_then(|_| encryptor.finish())
+                    .map_err(|e| {
+                        AbeError::new(
+                            format!("Could not write payload file: {:?}", e.to_string()).as_str(),
+                        )
+                    })?
+                    .persist()?;
+
+                let (length, payload) = open_payload(payload_path)?;
+                serialize(
+                    &SchemeKey::new::<Self>(AbeDetachedHeader::for_payload(
+                        header, length, payload,
+                    )?),
+                    armor,
+                )
+            }
+        }
+        .map_err(|e| AbeError::new(format!("Could not serialize cipher text: {:?}", e).as_str()))?;
+        let mut output = OutputFile::create(&args.output, "output")?;
+        output
+            .write_all(serialized_ciphertext.as_bytes())
+            .map_err(|e| {
+                AbeError::new(format!("Could not write output file: {:?}", e.to_string()).as_str())
+            })?;
+        output.persist()
+    }
+
+    // also decrypts envelopes and detached payloads
+    fn decrypt_file(
+        args: &DecryptArgs,
+        cipher_text: &str,
+        secret_key: &AbeSecretKey,
+    ) -> Result<(), AbeError> {
+        // segments are written as soon as they are authenticated, the output only replaces the
+        // file at its path once the last one was
+        let mut output = OutputFile::create(&args.output, "output")?;
+
+        match &args.payload {
+            None => {
+                if let Some(envelope) = deserialize::<AbeEnvelope>(cipher_text) {
+                    envelope.validate(&BuiltinDems)?;
+                    crypto::decrypt_envelope_stream(
+                        &BuiltinDems,
+                        &envelope.header,
+                        secret_key,
+                        envelope.message.as_slice(),
+                        &mut output,
+                        args.size_limit,
+                    )
+                } else {
+                    let cipher_text = deserialize::<AbeCipherText>(cipher_text)
+                        .ok_or(AbeError::new("Could not deserialize cipher text"))?;
+                    cipher_text.validate(&BuiltinDems)?;
+                    crypto::decrypt_stream(
+                        &BuiltinDems,
+                        &cipher_text.header,
+                        secret_key,
+                        cipher_text.message.as_slice(),
+                        &mut output,
+                        args.size_limit,
+                    )
+                }
+                .map_err(|e| {
+                    AbeError::new(format!("Could not decrypt: {:?}", e.to_string()).as_str())
+                })?;
+            }
+            Some(payload_path) => {
+                // check the whole payload before writing any plaintext, then decrypt it segment
+                // by segment in a second pass
+                let (length, payload) = open_payload(payload_path)?;
+                if let Some(envelope) = deserialize::<AbeDetachedEnvelope>(cipher_text) {
+                    envelope.verify_payload(length, payload)?;
+                    let (_, payload) = open_payload(payload_path)?;
+                    crypto::decrypt_envelope_stream(
+                        &BuiltinDems,
+                        &envelope.header,
+                        secret_key,
+                        payload,
+                        &mut output,
+                        args.size_limit,
+                    )
+                } else {
+                    let header = deserialize::<AbeDetachedHeader>(cipher_text)
+                        .ok_or(AbeError::new("Could not deserialize detached header"))?;
+                    header.verify_payload(length, payload)?;
+                    let (_, payload) = open_payload(payload_path)?;
+                    crypto::decrypt_stream(
+                        &BuiltinDems,
+                        &header.header,
+                        secret_key,
+                        payload,
+                        &mut output,
+                        args.size_limit,
+                    )
+                }
+                .map_err(|e| {
+                    AbeError::new(format!("Could not decrypt: {:?}", e.to_string()).as_str())
+                })?;
+            }
+        }
+
+        output.persist()
+    }
+
+    // also rewraps envelopes and detached headers
+    fn rewrap_file<R: Rng + ?Sized>(
+        args: &RewrapArgs,
+        input: &str,
+        secret_key: &AbeSecretKey,
+        public_key: &AbePublicKey,
+        access_tree: &AccessTree,
+        armor: bool,
+        rng: &mut R,
+    ) -> Result<String, AbeError> {
+        if args.detached {
+            // the payload hash carries over, the payload itself stays where it is
+            let (header, payload_hash) =
+                if let Some(envelope) = deserialize::<AbeDetachedEnvelope>(input) {
+                    let header = crypto::rewrap_envelope_header(
+                        &BuiltinDems,
+                        &envelope.header,
+                        secret_key,
+                        public_key,
+                        access_tree,
+                        rng,
+                    )?;
+                    (header, envelope.payload_hash)
+                } else {
+                    let detached = deserialize::<AbeDetachedHeader>(input)
+                        .ok_or(AbeError::new("Could not deserialize detached header"))?;
+                    let header = crypto::rewrap_header(
+                        &BuiltinDems,
+                        &detached.header,
+                        secret_key,
+                        public_key,
+                        access_tree,
+                        rng,
+                    )?;
+                    (header, detached.payload_hash)
+                };
+            serialize(
+                &SchemeKey::new::<Self>(AbeDetachedEnvelope {
+                    header,
+                    payload_hash,
+                }),
+                armor,
+            )
+        } else {
+            let envelope = if let Some(envelope) = deserialize::<AbeEnvelope>(input) {
+                let header = crypto::rewrap_envelope_header(
+                    &BuiltinDems,
+                    &envelope.header,
+                    secret_key,
+                    public_key,
+                    access_tree,
+                    rng,
+                )?;
+                AbeEnvelope {
+                    header,
+                    message: envelope.message,
+                }
+            } else {
+                let cipher_text = deserialize::<AbeCipherText>(input)
+                    .ok_or(AbeError::new("Could not deserialize cipher text"))?;
+                Self::rewrap(
+                    &BuiltinDems,
+                    cipher_text,
+                    secret_key,
+                    public_key,
+                    access_tree,
+                    rng,
+                )?
+            };
+            serialize(&SchemeKey::new::<Self>(envelope), armor)
+        }
+        .map_err(|e| AbeError::new(format!("Could not serialize envelope: {:?}", e).as_str()))
+    }
+
+    // also fingerprints detached headers and envelopes
+    fn fingerprint(input: &str) -> Option<Fingerprint> {
+        deserialize::<AbePublicKey>(input)
+            .map(|public_key| public_key.fingerprint())
+            .or_else(|| deserialize::<AbeSecretKey>(input).map(|key| key.fingerprint()))
+            .or_else(|| deserialize::<AbeCipherText>(input).map(|text| text.fingerprint()))
+            .or_else(|| deserialize::<AbeDetachedHeader>(input).map(|header| header.fingerprint()))
+            .or_else(|| deserialize::<AbeEnvelope>(input).map(|envelope| envelope.fingerprint()))
+            .or_else(|| {
+                deserialize::<AbeDetachedEnvelope>(input).map(|envelope| envelope.fingerprint())
+            })
+    }
+}
+
+// a file that only appears at its path once it is complete. It is written to a temporary file
+// next to it, which `persist` renames and which is removed if it is dropped before that.
+struct OutputFile {
+    path: PathBuf,
+    temp_path: PathBuf,
+    // what the file is for, e.g. "output", in error messages
+    name: &'static str,
+    file: Option<BufWriter<File>>,
+}
+
+impl OutputFile {
+    fn create(path: &Path, name: &'static str) -> Result<Self, AbeError> {
+        let mut temp_name = OsString::from(".");
+        temp_name.push(path.file_name().ok_or(AbeError::new(
+            format!("Invalid {} file: {:?}", name, path).as_str(),
+        ))?);
+        temp_name.push(format!(".{}.tmp", std::process::id()));
+        let temp_path = path.with_file_name(temp_name);
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+            .map_err(|e| {
+                AbeError::new(
+                    format!("Could not write {} file: {:?}", name, e.to_string()).as_str(),
+                )
+            })?;
+        Ok(OutputFile {
+            path: path.to_path_buf(),
+            temp_path,
+            name,
+            file: Some(BufWriter::new(file)),
+        })
+    }
+
+    // flushes the file to disk and moves it to its path
+    fn persist(mut self) -> Result<(), AbeError> {
+        let name = self.name;
+        let to_error = |e: std::io::Error| {
+            AbeError::new(format!("Could not write {} file: {:?}", name, e.to_string()).as_str())
+        };
+        if let Some(file) = self.file.take() {
+            let file = file.into_inner().map_err(|e| to_error(e.into_error()))?;
+            file.sync_all().map_err(to_error)?;
+        }
+        fs::rename(&self.temp_path, &self.path).map_err(to_error)
+    }
+
+    fn writer(&mut self) -> &mut BufWriter<File> {
+        self.file
+            .as_mut()
+            .expect("output file is open until it is persisted")
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer().flush()
+    }
+}
+
+impl Drop for OutputFile {
+    fn drop(&mut self) {
+        // close the file first, then remove what is left of it. After `persist` there is
+        // nothing left, the temporary file was renamed.
+        self.file.take();
+        let _ = fs::remove_file(&self.temp_path);
+    }
+}
+
+// opens a payload file for reading and returns its length
+fn open_payload(path: &Path) -> Result<(u64, BufReader<File>), AbeError> {
+    let file = File::open(path).map_err(|e| {
+        AbeError::new(format!("Could not read payload file: {:?}", e.to_string()).as_str())
+    })?;
+    let length = file.metadata().map(|m| m.len()).map_err(|e| {
+        AbeError::new(format!("Could not read payload file: {:?}", e.to_string()).as_str())
+    })?;
+    Ok((length, BufReader::new(file)))
+}
+
+fn do_fingerprint(args: &FingerprintArgs) -> Result<(), AbeError> {
+    let input = read_to_string(&args.input).map_err(|e| {
+        AbeError::new(format!("Could not read input file: {:?}", e.to_string()).as_str())
+    })?;
+
+    let fingerprint = with_scheme!(scheme_of(&input)?.as_str(), fingerprint_scheme(&input))?;
+    println!("{}", fingerprint);
 
     Ok(())
 }
+
+fn fingerprint_scheme<S: SchemeFiles>(input: &str) -> Result<Fingerprint, AbeError> {
+    S::fingerprint(input).ok_or(AbeError::new(
+        format!(
+            "Input is not a public key, secret key or cipher text of scheme {:?}",
+            S::NAME
+        )
+        .as_str(),
+    ))
+}
+
+fn do_delegate<R: Rng + ?Sized>(
+    args: &DelegateArgs,
+    armor: bool,
+    rng: &mut R,
+) -> Result<(), AbeError> {
+    let public_key = read_to_string(&args.public_key).map_err(|e| {
+        AbeError::new(format!("Could not read public key: {:?}", e.to_string()).as_str())
+    })?;
+    let secret_key = read_to_string(&args.private_key).map_err(|e| {
+        AbeError::new(format!("Could not read secret key: {:?}", e.to_string()).as_str())
+    })?;
+
+    with_scheme!(
+        scheme_of(&secret_key)?.as_str(),
+        delegate_scheme(args, &public_key, &secret_key, armor, rng)
+    )
+}
+
+fn delegate_scheme<S: SchemeFiles>(
+    args: &DelegateArgs,
+    public_key: &str,
+    secret_key: &str,
+    armor: bool,
+    rng: &mut (impl Rng + ?Sized),
+) -> Result<(), AbeError> {
+    let public_key = deserialize::<S::PublicKey>(public_key)
+        .ok_or(AbeError::new("Could not deserialize public key"))?;
+    let secret_key = deserialize::<S::SecretKey>(secret_key)
+        .ok_or(AbeError::new("Could not deserialize secret key"))?;
+
+    let input = S::KeyInput::parse(&args.attributes)?;
+    let delegated = S::delegate(&input, &public_key, &secret_key, rng)?;
+    write_secret_key::<S>(&args.secret_key, delegated, armor)
+}
@@ -0,0 +1,256 @@
+// Models of the ciphertext-policy scheme of `crypto` and of outsourced decryption, see
+// `crypto::transform`
+
+use std::collections::BTreeMap;
+
+use rabe_bn::{Fr, Group, Gt, G1, G2};
+use serde::{Deserialize, Serialize};
+
+use super::{check_leaf_components, default_algorithm, CipherText, CipherTextHeader, Detached};
+use crate::abe_attribute::AbeIdentifier;
+use crate::access_tree::{AccessTree, GetAttributes};
+use crate::canonical::{
+    serialize_group, serialize_group_map, serialize_group_pairs, CanonicalEncode, Fingerprint,
+    Fingerprinted,
+};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+use crate::padding::Padding;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbePublicKey {
+    pub map: Gt,
+    #[serde(serialize_with = "serialize_group")]
+    pub g1: G1,
+    #[serde(serialize_with = "serialize_group")]
+    pub g2: G2,
+    pub y: Gt,
+    #[serde(serialize_with = "serialize_group_map")]
+    pub big_t: BTreeMap<String, G1>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeMasterKey {
+    pub alpha: Fr,
+    pub small_t: BTreeMap<String, Fr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeSecretKey {
+    pub system_id: Fingerprint,
+    #[serde(serialize_with = "serialize_group")]
+    pub d_0: G2,
+    #[serde(serialize_with = "serialize_group_map")]
+    pub arr_d: BTreeMap<String, G2>,
+    /// g2^(tj^-1) per attribute, lets the holder delegate the key, see `crypto::delegate`. Only
+    /// keys from `crypto::keygen_delegable` have them, see there for what they expose.
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        serialize_with = "serialize_group_map"
+    )]
+    pub arr_e: BTreeMap<String, G2>,
+}
+
+/// The ABE part of a cipher text, which encapsulates the key of the symmetric payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeHeader {
+    /// Format version, see `kdf::FORMAT_VERSION`
+    #[serde(default)]
+    pub version: u32,
+    pub system_id: Fingerprint,
+    /// Identifier of the data encapsulation mechanism used for the payload
+    #[serde(default = "default_algorithm")]
+    pub algorithm: String,
+    /// Plaintext bytes per segment if the payload is segmented, see `stream`
+    #[serde(default)]
+    pub segment_size: Option<usize>,
+    /// Algorithm the message was compressed with before encryption, see `compression`
+    #[serde(default)]
+    pub compression: Option<String>,
+    /// Padding appended to the (compressed) message before encryption
+    #[serde(default)]
+    pub padding: Option<Padding>,
+    /// Policy of the cipher text, with indices but without the shares of the secret
+    pub access_tree: Box<AccessTree>,
+    #[serde(serialize_with = "serialize_group")]
+    pub c_0: G1,
+    pub c_1: Gt,
+    #[serde(serialize_with = "serialize_group_pairs")]
+    pub arr_c: Vec<(AbeIdentifier, G1)>,
+    /// Tag over the fingerprint of the header under a key derived from the secret, see
+    /// `crypto::header_mac`. Lets a client check the secret a server recovered for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mac: Option<Fingerprint>,
+}
+
+pub type AbeCipherText = CipherText<AbeHeader>;
+
+/// Header stored apart from its payload, linked to it by the hash of the payload
+pub type AbeDetachedHeader = Detached<AbeHeader>;
+
+/// Secret key with every component raised to `z^-1`, for an untrusted server that partially
+/// decrypts cipher texts, see `crypto::transform`. Without the retrieval key `z` it decrypts
+/// nothing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeTransformationKey {
+    pub system_id: Fingerprint,
+    #[serde(serialize_with = "serialize_group")]
+    pub d_0: G2,
+    #[serde(serialize_with = "serialize_group_map")]
+    pub arr_d: BTreeMap<String, G2>,
+}
+
+/// The blinding exponent of a transformation key, kept by the client
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeRetrievalKey {
+    pub system_id: Fingerprint,
+    pub z: Fr,
+}
+
+/// A transformation key together with its retrieval key, the secret key of
+/// `scheme::OutsourcedAbe`. The holder keeps the retrieval key and may hand the transformation
+/// key to a server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeOutsourcedKey {
+    pub transformation_key: AbeTransformationKey,
+    pub retrieval_key: AbeRetrievalKey,
+}
+
+/// Result of the partial decryption of a header by a server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeTransformed {
+    /// Fingerprint of the header that was transformed
+    pub header_id: Fingerprint,
+    /// e(g1, g2)^(alpha * s / z)
+    pub t: Gt,
+}
+
+impl CanonicalEncode for AbePublicKey {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.map.encode_canonical(out);
+        self.g1.encode_canonical(out);
+        self.g2.encode_canonical(out);
+        self.y.encode_canonical(out);
+        self.big_t.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeSecretKey {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.system_id.encode_canonical(out);
+        self.d_0.encode_canonical(out);
+        self.arr_d.encode_canonical(out);
+        self.arr_e.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeTransformationKey {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.system_id.encode_canonical(out);
+        self.d_0.encode_canonical(out);
+        self.arr_d.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeOutsourcedKey {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.transformation_key.encode_canonical(out);
+        self.retrieval_key.system_id.encode_canonical(out);
+        self.retrieval_key.z.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeHeader {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.version.encode_canonical(out);
+        self.system_id.encode_canonical(out);
+        self.algorithm.encode_canonical(out);
+        self.segment_size.encode_canonical(out);
+        self.compression.encode_canonical(out);
+        self.padding.encode_canonical(out);
+        self.access_tree.encode_canonical(out);
+        self.c_0.encode_canonical(out);
+        self.c_1.encode_canonical(out);
+        self.arr_c.encode_canonical(out);
+        // the mac is computed over this encoding and cannot be part of it
+    }
+}
+
+impl Fingerprinted for AbePublicKey {
+    const FINGERPRINT_LABEL: &'static str = "abe-public-key";
+}
+
+impl Fingerprinted for AbeSecretKey {
+    const FINGERPRINT_LABEL: &'static str = "abe-secret-key";
+}
+
+impl Fingerprinted for AbeTransformationKey {
+    const FINGERPRINT_LABEL: &'static str = "abe-transformation-key";
+}
+
+impl Fingerprinted for AbeOutsourcedKey {
+    const FINGERPRINT_LABEL: &'static str = "abe-outsourced-key";
+}
+
+impl Fingerprinted for AbeHeader {
+    const FINGERPRINT_LABEL: &'static str = "abe-header";
+}
+
+impl Fingerprinted for AbeDetachedHeader {
+    const FINGERPRINT_LABEL: &'static str = "abe-detached-header";
+}
+
+impl AbePublicKey {
+    /// Identifier of the setup this key belongs to, unaffected by `adapt`
+    pub fn system_id(&self) -> Fingerprint {
+        system_id_of(&self.g1, &self.g2, &self.y)
+    }
+}
+
+// the system identifier of a public key with these parameters, lets holders of the parameters
+// alone check that they belong to a system
+pub(crate) fn system_id_of(g1: &G1, g2: &G2, y: &Gt) -> Fingerprint {
+    let mut out = Vec::new();
+    g1.encode_canonical(&mut out);
+    g2.encode_canonical(&mut out);
+    y.encode_canonical(&mut out);
+    Fingerprint::of("abe-system", out.as_slice())
+}
+
+impl CipherTextHeader for AbeHeader {
+    const CIPHER_TEXT_LABEL: &'static str = "abe-cipher-text";
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    fn segment_size(&self) -> Option<usize> {
+        self.segment_size
+    }
+
+    fn compression(&self) -> Option<&str> {
+        self.compression.as_deref()
+    }
+
+    fn padding(&self) -> Option<&Padding> {
+        self.padding.as_ref()
+    }
+
+    /// Checks that every leaf of the access tree has a unique index with a matching entry in
+    /// `arr_c` and no group element is the identity
+    fn validate_components(&self) -> Result<(), AbeError> {
+        if self.c_0.is_zero() || self.c_1 == Gt::one() {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::IdentityElement,
+                "c_0 or c_1 is the identity element",
+            ));
+        }
+
+        let leaves = self.access_tree.get_attributes();
+        check_leaf_components(&leaves, &self.arr_c, "arr_c")
+    }
+}
@@ -0,0 +1,117 @@
+// Models of envelopes, a payload encrypted once for several recipients, see
+// `crypto::encrypt_envelope`
+
+use serde::{Deserialize, Serialize};
+
+use super::{AbeHeader, Base64Standard, CipherText, CipherTextHeader, Detached};
+use crate::canonical::{CanonicalEncode, Fingerprinted};
+use crate::crypto;
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+use crate::padding::Padding;
+
+/// Payload key wrapped for one recipient of an envelope
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeRecipient {
+    /// Header encapsulating the key the payload key is wrapped with, see `crypto::encapsulate`
+    pub header: AbeHeader,
+    #[serde(with = "Base64Standard")]
+    pub wrapped_key: Vec<u8>,
+}
+
+/// Recipients of a payload that is encrypted once under a symmetric key wrapped for each of
+/// them, every recipient with its own access tree and possibly its own system
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeEnvelopeHeader {
+    /// Format version, see `kdf::FORMAT_VERSION`
+    pub version: u32,
+    /// Identifier of the data encapsulation mechanism used for the payload
+    pub algorithm: String,
+    /// Plaintext bytes per segment if the payload is segmented, see `stream`
+    #[serde(default)]
+    pub segment_size: Option<usize>,
+    /// Algorithm the message was compressed with before encryption, see `compression`
+    #[serde(default)]
+    pub compression: Option<String>,
+    /// Padding appended to the (compressed) message before encryption
+    #[serde(default)]
+    pub padding: Option<Padding>,
+    pub recipients: Vec<AbeRecipient>,
+}
+
+/// A single symmetric payload readable by several recipients
+pub type AbeEnvelope = CipherText<AbeEnvelopeHeader>;
+
+/// Envelope header stored apart from its payload, linked to it by the hash of the payload
+pub type AbeDetachedEnvelope = Detached<AbeEnvelopeHeader>;
+
+impl CanonicalEncode for AbeRecipient {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.header.encode_canonical(out);
+        self.wrapped_key.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeEnvelopeHeader {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.version.encode_canonical(out);
+        self.algorithm.encode_canonical(out);
+        self.segment_size.encode_canonical(out);
+        self.compression.encode_canonical(out);
+        self.padding.encode_canonical(out);
+        self.recipients.encode_canonical(out);
+    }
+}
+
+impl Fingerprinted for AbeDetachedEnvelope {
+    const FINGERPRINT_LABEL: &'static str = "abe-detached-envelope";
+}
+
+impl CipherTextHeader for AbeEnvelopeHeader {
+    const CIPHER_TEXT_LABEL: &'static str = "abe-envelope";
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    fn segment_size(&self) -> Option<usize> {
+        self.segment_size
+    }
+
+    fn compression(&self) -> Option<&str> {
+        self.compression.as_deref()
+    }
+
+    fn padding(&self) -> Option<&Padding> {
+        self.padding.as_ref()
+    }
+
+    /// Checks that there are recipients and every recipient header is structurally sound and
+    /// wraps a key
+    fn validate_components(&self) -> Result<(), AbeError> {
+        if self.recipients.is_empty() {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::NoRecipient,
+                "Envelope has no recipients",
+            ));
+        }
+        for recipient in &self.recipients {
+            if recipient.header.algorithm != crypto::EXTERNAL_ALGORITHM {
+                return Err(AbeError::with_kind(
+                    AbeErrorKind::UnknownAlgorithm,
+                    format!(
+                        "Recipient header wraps a {} payload instead of a key",
+                        recipient.header.algorithm
+                    )
+                    .as_str(),
+                ));
+            }
+            recipient.header.validate()?;
+        }
+
+        Ok(())
+    }
+}
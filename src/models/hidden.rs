@@ -0,0 +1,113 @@
+// Models of cipher texts with partially hidden policies, see `hidden`. Keys are the ones of
+// `crypto`.
+
+use rabe_bn::{Group, Gt, G1};
+use serde::{Deserialize, Serialize};
+
+use super::{check_leaf_components, check_leaf_identifiers, CipherText, CipherTextHeader};
+use crate::abe_attribute::AbeIdentifier;
+use crate::access_tree::{AccessTree, GetAttributes};
+use crate::canonical::{
+    serialize_group, serialize_group_pairs, CanonicalEncode, Fingerprint, Fingerprinted,
+};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+
+/// Lets a key holder test whether a leaf with a hidden value is for one of its attributes,
+/// see `hidden`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeMatchTest {
+    /// g1^sigma
+    #[serde(serialize_with = "serialize_group")]
+    pub v: G1,
+    /// Tj^sigma
+    #[serde(serialize_with = "serialize_group")]
+    pub u: G1,
+    /// y^sigma
+    pub w: Gt,
+}
+
+/// The ABE part of a cipher text with a partially hidden policy. The leaves of the access tree
+/// only name the category of their attribute, `arr_test` lets key holders find their values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeHiddenHeader {
+    /// Format version, see `kdf::FORMAT_VERSION`
+    pub version: u32,
+    pub system_id: Fingerprint,
+    /// Identifier of the data encapsulation mechanism used for the payload
+    pub algorithm: String,
+    /// Policy of the cipher text with categories instead of attributes, with indices but without
+    /// the shares of the secret
+    pub access_tree: Box<AccessTree>,
+    #[serde(serialize_with = "serialize_group")]
+    pub c_0: G1,
+    pub c_1: Gt,
+    #[serde(serialize_with = "serialize_group_pairs")]
+    pub arr_c: Vec<(AbeIdentifier, G1)>,
+    pub arr_test: Vec<(AbeIdentifier, AbeMatchTest)>,
+}
+
+pub type AbeHiddenCipherText = CipherText<AbeHiddenHeader>;
+
+impl CanonicalEncode for AbeMatchTest {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.v.encode_canonical(out);
+        self.u.encode_canonical(out);
+        self.w.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeHiddenHeader {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.version.encode_canonical(out);
+        self.system_id.encode_canonical(out);
+        self.algorithm.encode_canonical(out);
+        self.access_tree.encode_canonical(out);
+        self.c_0.encode_canonical(out);
+        self.c_1.encode_canonical(out);
+        self.arr_c.encode_canonical(out);
+        self.arr_test.encode_canonical(out);
+    }
+}
+
+impl Fingerprinted for AbeHiddenHeader {
+    const FINGERPRINT_LABEL: &'static str = "abe-hidden-header";
+}
+
+impl CipherTextHeader for AbeHiddenHeader {
+    const CIPHER_TEXT_LABEL: &'static str = "abe-hidden-cipher-text";
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    /// Checks that every leaf of the access tree has a unique index with matching entries in
+    /// `arr_c` and `arr_test` and no group element is the identity
+    fn validate_components(&self) -> Result<(), AbeError> {
+        if self.c_0.is_zero() || self.c_1 == Gt::one() {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::IdentityElement,
+                "c_0 or c_1 is the identity element",
+            ));
+        }
+
+        let leaves = self.access_tree.get_attributes();
+        check_leaf_components(&leaves, &self.arr_c, "arr_c")?;
+        check_leaf_identifiers(&leaves, &self.arr_test, "arr_test")?;
+        if self
+            .arr_test
+            .iter()
+            .any(|(_, test)| test.v.is_zero() || test.u.is_zero() || test.w == Gt::one())
+        {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::IdentityElement,
+                "A component of arr_test is the identity element",
+            ));
+        }
+
+        Ok(())
+    }
+}
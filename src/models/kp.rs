@@ -0,0 +1,104 @@
+// Models of the key-policy scheme, see `kp_abe`. It shares its public and master keys with
+// `crypto`.
+
+use std::collections::BTreeMap;
+
+use rabe_bn::{Group, Gt, G1, G2};
+use serde::{Deserialize, Serialize};
+
+use super::{check_leaf_components, CipherText, CipherTextHeader};
+use crate::abe_attribute::AbeIdentifier;
+use crate::access_tree::{AccessTree, GetAttributes};
+use crate::canonical::{
+    serialize_group_map, serialize_group_pairs, CanonicalEncode, Fingerprint, Fingerprinted,
+};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+
+/// Secret key of the key-policy scheme, see `kp_abe`. It decrypts cipher texts whose
+/// attributes satisfy its access tree.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeKpSecretKey {
+    pub system_id: Fingerprint,
+    /// Policy of the key, with indices but without the shares of the master secret
+    pub access_tree: Box<AccessTree>,
+    #[serde(serialize_with = "serialize_group_pairs")]
+    pub arr_d: Vec<(AbeIdentifier, G2)>,
+}
+
+/// The ABE part of a key-policy cipher text, labelled with the attributes it was encrypted
+/// under
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeKpHeader {
+    /// Format version, see `kdf::FORMAT_VERSION`
+    pub version: u32,
+    pub system_id: Fingerprint,
+    /// Identifier of the data encapsulation mechanism used for the payload
+    pub algorithm: String,
+    pub c_1: Gt,
+    #[serde(serialize_with = "serialize_group_map")]
+    pub arr_c: BTreeMap<String, G1>,
+}
+
+pub type AbeKpCipherText = CipherText<AbeKpHeader>;
+
+impl CanonicalEncode for AbeKpSecretKey {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.system_id.encode_canonical(out);
+        self.access_tree.encode_canonical(out);
+        self.arr_d.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeKpHeader {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.version.encode_canonical(out);
+        self.system_id.encode_canonical(out);
+        self.algorithm.encode_canonical(out);
+        self.c_1.encode_canonical(out);
+        self.arr_c.encode_canonical(out);
+    }
+}
+
+impl Fingerprinted for AbeKpSecretKey {
+    const FINGERPRINT_LABEL: &'static str = "abe-kp-secret-key";
+}
+
+impl Fingerprinted for AbeKpHeader {
+    const FINGERPRINT_LABEL: &'static str = "abe-kp-header";
+}
+
+impl AbeKpSecretKey {
+    /// Checks that every leaf of the access tree has a unique index with a matching entry in
+    /// `arr_d` and no group element is the identity
+    pub fn validate(&self) -> Result<(), AbeError> {
+        let leaves = self.access_tree.get_attributes();
+        check_leaf_components(&leaves, &self.arr_d, "arr_d")
+    }
+}
+
+impl CipherTextHeader for AbeKpHeader {
+    const CIPHER_TEXT_LABEL: &'static str = "abe-kp-cipher-text";
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    /// Checks that the header has attributes and no group element is the identity
+    fn validate_components(&self) -> Result<(), AbeError> {
+        if self.arr_c.is_empty() {
+            return Err(AbeError::new("Cipher text has no attributes"));
+        }
+        if self.c_1 == Gt::one() || self.arr_c.values().any(|c| c.is_zero()) {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::IdentityElement,
+                "c_1 or a c_j is the identity element",
+            ));
+        }
+
+        Ok(())
+    }
+}
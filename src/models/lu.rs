@@ -0,0 +1,170 @@
+// Models of the large-universe scheme, see `lu_abe`
+
+use std::collections::BTreeMap;
+
+use rabe_bn::{Fr, Group, Gt, G1, G2};
+use serde::{Deserialize, Serialize};
+
+use super::{check_leaf_components, CipherText, CipherTextHeader};
+use crate::abe_attribute::AbeIdentifier;
+use crate::access_tree::{AccessTree, GetAttributes};
+use crate::canonical::{
+    serialize_group, serialize_group_map, serialize_group_pairs, CanonicalEncode, Fingerprint,
+    Fingerprinted,
+};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+
+/// Public key of the large-universe scheme, see `lu_abe`. Attributes are hashed to G1 instead
+/// of being registered, so its size is fixed and it never has to be redistributed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeLuPublicKey {
+    #[serde(serialize_with = "serialize_group")]
+    pub g1: G1,
+    #[serde(serialize_with = "serialize_group")]
+    pub g2: G2,
+    /// g2^beta
+    #[serde(serialize_with = "serialize_group")]
+    pub h: G2,
+    /// e(g1, g2)^alpha
+    pub y: Gt,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeLuMasterKey {
+    pub alpha: Fr,
+    pub beta: Fr,
+}
+
+/// Secret key of the large-universe scheme, with a pair of components for every attribute
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeLuSecretKey {
+    pub system_id: Fingerprint,
+    #[serde(serialize_with = "serialize_group")]
+    pub d: G1,
+    #[serde(serialize_with = "serialize_group_map")]
+    pub arr_d: BTreeMap<String, G1>,
+    #[serde(serialize_with = "serialize_group_map")]
+    pub arr_d_prime: BTreeMap<String, G2>,
+}
+
+/// The ABE part of a large-universe cipher text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeLuHeader {
+    /// Format version, see `kdf::FORMAT_VERSION`
+    pub version: u32,
+    pub system_id: Fingerprint,
+    /// Identifier of the data encapsulation mechanism used for the payload
+    pub algorithm: String,
+    /// Policy of the cipher text, with indices but without the shares of the secret
+    pub access_tree: Box<AccessTree>,
+    #[serde(serialize_with = "serialize_group")]
+    pub c_0: G2,
+    pub c_1: Gt,
+    #[serde(serialize_with = "serialize_group_pairs")]
+    pub arr_c: Vec<(AbeIdentifier, G2)>,
+    #[serde(serialize_with = "serialize_group_pairs")]
+    pub arr_c_prime: Vec<(AbeIdentifier, G1)>,
+}
+
+pub type AbeLuCipherText = CipherText<AbeLuHeader>;
+
+impl CanonicalEncode for AbeLuPublicKey {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.g1.encode_canonical(out);
+        self.g2.encode_canonical(out);
+        self.h.encode_canonical(out);
+        self.y.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeLuSecretKey {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.system_id.encode_canonical(out);
+        self.d.encode_canonical(out);
+        self.arr_d.encode_canonical(out);
+        self.arr_d_prime.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeLuHeader {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.version.encode_canonical(out);
+        self.system_id.encode_canonical(out);
+        self.algorithm.encode_canonical(out);
+        self.access_tree.encode_canonical(out);
+        self.c_0.encode_canonical(out);
+        self.c_1.encode_canonical(out);
+        self.arr_c.encode_canonical(out);
+        self.arr_c_prime.encode_canonical(out);
+    }
+}
+
+// the fingerprint of the key is its system identifier, see `system_id`
+impl Fingerprinted for AbeLuPublicKey {
+    const FINGERPRINT_LABEL: &'static str = "abe-lu-system";
+}
+
+impl Fingerprinted for AbeLuSecretKey {
+    const FINGERPRINT_LABEL: &'static str = "abe-lu-secret-key";
+}
+
+impl Fingerprinted for AbeLuHeader {
+    const FINGERPRINT_LABEL: &'static str = "abe-lu-header";
+}
+
+impl AbeLuPublicKey {
+    /// Stable identifier of the setup, derived from the canonical encoding of the key. The key
+    /// never changes, so it doubles as the system identifier.
+    pub fn system_id(&self) -> Fingerprint {
+        self.fingerprint()
+    }
+}
+
+impl AbeLuSecretKey {
+    /// Checks that every attribute has both key components and no group element is the identity
+    pub fn validate(&self) -> Result<(), AbeError> {
+        if !self.arr_d.keys().eq(self.arr_d_prime.keys()) {
+            return Err(AbeError::new(
+                "arr_d and arr_d_prime are not for the same attributes",
+            ));
+        }
+        if self.d.is_zero()
+            || self.arr_d.values().any(|d| d.is_zero())
+            || self.arr_d_prime.values().any(|d| d.is_zero())
+        {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::IdentityElement,
+                "d or a component of an attribute is the identity element",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl CipherTextHeader for AbeLuHeader {
+    const CIPHER_TEXT_LABEL: &'static str = "abe-lu-cipher-text";
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    /// Checks that every leaf of the access tree has a unique index with matching entries in
+    /// `arr_c` and `arr_c_prime` and no group element is the identity
+    fn validate_components(&self) -> Result<(), AbeError> {
+        if self.c_0.is_zero() || self.c_1 == Gt::one() {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::IdentityElement,
+                "c_0 or c_1 is the identity element",
+            ));
+        }
+
+        let leaves = self.access_tree.get_attributes();
+        check_leaf_components(&leaves, &self.arr_c, "arr_c")?;
+        check_leaf_components(&leaves, &self.arr_c_prime, "arr_c_prime")
+    }
+}
@@ -0,0 +1,180 @@
+// Models of the multi-authority scheme, see `ma_abe`
+
+use std::collections::BTreeMap;
+
+use rabe_bn::{Fr, Gt, G1, G2};
+use serde::{Deserialize, Serialize};
+
+use super::{check_leaf_components, check_leaf_identifiers, CipherText, CipherTextHeader};
+use crate::abe_attribute::AbeIdentifier;
+use crate::access_tree::{AccessTree, GetAttributes};
+use crate::canonical::{
+    serialize_group, serialize_group_map, serialize_group_pairs, CanonicalEncode, Fingerprint,
+    Fingerprinted,
+};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+
+/// Parameters shared by all authorities of the multi-authority scheme, see `ma_abe`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeMaGlobalParameters {
+    #[serde(serialize_with = "serialize_group")]
+    pub g1: G1,
+    #[serde(serialize_with = "serialize_group")]
+    pub g2: G2,
+}
+
+/// Public part of an attribute of a multi-authority system
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeMaAttributePublicKey {
+    /// e(g1, g2)^alpha_i
+    pub e_alpha: Gt,
+    /// g2^y_i
+    #[serde(serialize_with = "serialize_group")]
+    pub g2_y: G2,
+}
+
+/// Public key of one authority of a multi-authority system, with its attributes by their
+/// qualified names `<authority>:<attribute>`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeMaPublicKey {
+    pub system_id: Fingerprint,
+    pub authority: String,
+    pub attributes: BTreeMap<String, AbeMaAttributePublicKey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeMaAttributeMasterKey {
+    pub alpha: Fr,
+    pub y: Fr,
+}
+
+/// Master key of one authority of a multi-authority system
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeMaMasterKey {
+    pub system_id: Fingerprint,
+    pub authority: String,
+    pub attributes: BTreeMap<String, AbeMaAttributeMasterKey>,
+}
+
+/// Secret key of a user of a multi-authority system. Keys issued by different authorities for
+/// the same global identifier are combined with `ma_abe::combine`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeMaSecretKey {
+    pub system_id: Fingerprint,
+    /// Global identifier of the user, all components are bound to it
+    pub gid: String,
+    /// g1^alpha_i * H(gid)^y_i for every attribute i
+    #[serde(serialize_with = "serialize_group_map")]
+    pub arr_k: BTreeMap<String, G1>,
+}
+
+/// The ABE part of a multi-authority cipher text, with three components for every row of the
+/// LSSS matrix of the access tree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeMaHeader {
+    /// Format version, see `kdf::FORMAT_VERSION`
+    pub version: u32,
+    pub system_id: Fingerprint,
+    /// Identifier of the data encapsulation mechanism used for the payload
+    pub algorithm: String,
+    /// Policy of the cipher text, with indices but without the shares of the secret
+    pub access_tree: Box<AccessTree>,
+    pub c_0: Gt,
+    pub arr_c1: Vec<(AbeIdentifier, Gt)>,
+    #[serde(serialize_with = "serialize_group_pairs")]
+    pub arr_c2: Vec<(AbeIdentifier, G2)>,
+    #[serde(serialize_with = "serialize_group_pairs")]
+    pub arr_c3: Vec<(AbeIdentifier, G2)>,
+}
+
+pub type AbeMaCipherText = CipherText<AbeMaHeader>;
+
+impl CanonicalEncode for AbeMaGlobalParameters {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.g1.encode_canonical(out);
+        self.g2.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeMaAttributePublicKey {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.e_alpha.encode_canonical(out);
+        self.g2_y.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeMaPublicKey {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.system_id.encode_canonical(out);
+        self.authority.encode_canonical(out);
+        self.attributes.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeMaSecretKey {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.system_id.encode_canonical(out);
+        self.gid.encode_canonical(out);
+        self.arr_k.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeMaHeader {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.version.encode_canonical(out);
+        self.system_id.encode_canonical(out);
+        self.algorithm.encode_canonical(out);
+        self.access_tree.encode_canonical(out);
+        self.c_0.encode_canonical(out);
+        self.arr_c1.encode_canonical(out);
+        self.arr_c2.encode_canonical(out);
+        self.arr_c3.encode_canonical(out);
+    }
+}
+
+impl Fingerprinted for AbeMaPublicKey {
+    const FINGERPRINT_LABEL: &'static str = "abe-ma-public-key";
+}
+
+impl Fingerprinted for AbeMaSecretKey {
+    const FINGERPRINT_LABEL: &'static str = "abe-ma-secret-key";
+}
+
+impl Fingerprinted for AbeMaHeader {
+    const FINGERPRINT_LABEL: &'static str = "abe-ma-header";
+}
+
+impl AbeMaGlobalParameters {
+    /// Stable identifier of the system, derived from the canonical encoding of the parameters
+    pub fn system_id(&self) -> Fingerprint {
+        Fingerprint::of("abe-ma-system", self)
+    }
+}
+
+impl CipherTextHeader for AbeMaHeader {
+    const CIPHER_TEXT_LABEL: &'static str = "abe-ma-cipher-text";
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    /// Checks that every leaf of the access tree has a unique index with matching entries in
+    /// `arr_c1`, `arr_c2` and `arr_c3` and no group element is the identity
+    fn validate_components(&self) -> Result<(), AbeError> {
+        if self.c_0 == Gt::one() {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::IdentityElement,
+                "c_0 is the identity element",
+            ));
+        }
+
+        let leaves = self.access_tree.get_attributes();
+        check_leaf_identifiers(&leaves, &self.arr_c1, "arr_c1")?;
+        check_leaf_components(&leaves, &self.arr_c2, "arr_c2")?;
+        check_leaf_components(&leaves, &self.arr_c3, "arr_c3")
+    }
+}
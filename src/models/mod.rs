@@ -0,0 +1,317 @@
+use std::collections::BTreeSet;
+use std::io::Read;
+
+use base64::engine::general_purpose::STANDARD;
+use base64_serde::base64_serde_type;
+use rabe_bn::{Group, Gt};
+use serde::{Deserialize, Serialize};
+
+use crate::abe_attribute::{AbeAttribute, AbeIdentifier};
+use crate::canonical::{CanonicalEncode, Fingerprint, Fingerprinted};
+use crate::compression;
+use crate::dem;
+use crate::dem::{DataEncapsulation, DemResolver};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+use crate::kdf;
+use crate::padding::Padding;
+use crate::stream;
+
+mod cp;
+mod envelope;
+mod hidden;
+mod kp;
+mod lu;
+mod ma;
+mod online;
+mod waters;
+
+pub use cp::*;
+pub use envelope::*;
+pub use hidden::*;
+pub use kp::*;
+pub use lu::*;
+pub use ma::*;
+pub use online::*;
+pub use waters::*;
+
+base64_serde_type!(Base64Standard, STANDARD);
+
+/// The part of a cipher text in front of its symmetric payload, which lets key holders recover
+/// the key of the payload. Every scheme has its own.
+pub trait CipherTextHeader: CanonicalEncode {
+    /// Domain label of the fingerprint of a cipher text with this header
+    const CIPHER_TEXT_LABEL: &'static str;
+
+    /// Format version, see `kdf::FORMAT_VERSION`
+    fn version(&self) -> u32;
+
+    /// Identifier of the data encapsulation mechanism used for the payload
+    fn algorithm(&self) -> &str;
+
+    /// Plaintext bytes per segment if the payload is segmented, see `stream`
+    fn segment_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Algorithm the message was compressed with before encryption, see `compression`
+    fn compression(&self) -> Option<&str> {
+        None
+    }
+
+    /// Padding appended to the (compressed) message before encryption
+    fn padding(&self) -> Option<&Padding> {
+        None
+    }
+
+    /// Checks the components specific to the scheme, see `validate`
+    fn validate_components(&self) -> Result<(), AbeError>;
+
+    /// Checks that the header is structurally sound: its format version is supported, the
+    /// payload options are valid and so are the components of the scheme
+    fn validate(&self) -> Result<(), AbeError> {
+        if self.version() > kdf::FORMAT_VERSION {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::UnsupportedVersion,
+                format!("Unsupported format version {}", self.version()).as_str(),
+            ));
+        }
+        if self.segment_size() == Some(0) {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::InvalidSegmentSize,
+                "Segment size must be positive",
+            ));
+        }
+        if let Some(algorithm) = self.compression() {
+            if !compression::is_supported(algorithm) {
+                return Err(AbeError::with_kind(
+                    AbeErrorKind::UnknownAlgorithm,
+                    format!("Unknown compression algorithm {}", algorithm).as_str(),
+                ));
+            }
+        }
+        if let Some(padding) = self.padding() {
+            padding.validate()?;
+        }
+
+        self.validate_components()
+    }
+
+    /// Looks up the data encapsulation mechanism of the payload in `dems`
+    fn resolve<'d>(
+        &self,
+        dems: &'d dyn DemResolver,
+    ) -> Result<&'d dyn DataEncapsulation, AbeError> {
+        dems.resolve(self.algorithm()).ok_or(AbeError::with_kind(
+            AbeErrorKind::UnknownAlgorithm,
+            format!("Unknown symmetric algorithm {}", self.algorithm()).as_str(),
+        ))
+    }
+}
+
+/// A header of one of the schemes followed by the symmetric payload it protects
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CipherText<H> {
+    #[serde(flatten)]
+    pub header: H,
+    #[serde(with = "Base64Standard")]
+    pub message: Vec<u8>,
+}
+
+/// A header stored apart from its payload, linked to it by the hash of the payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Detached<H> {
+    #[serde(flatten)]
+    pub header: H,
+    pub payload_hash: Fingerprint,
+}
+
+pub struct AbeDecrypted {
+    pub message: Vec<u8>,
+    pub secret: Gt,
+}
+
+impl<H: CanonicalEncode> CanonicalEncode for CipherText<H> {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.header.encode_canonical(out);
+        self.message.encode_canonical(out);
+    }
+}
+
+impl<H: CanonicalEncode> CanonicalEncode for Detached<H> {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.header.encode_canonical(out);
+        self.payload_hash.encode_canonical(out);
+    }
+}
+
+impl<H: CipherTextHeader> Fingerprinted for CipherText<H> {
+    const FINGERPRINT_LABEL: &'static str = H::CIPHER_TEXT_LABEL;
+}
+
+impl<H: CipherTextHeader> CipherText<H> {
+    /// Checks that the header is structurally sound and the payload is long enough for the
+    /// recorded symmetric algorithm, which `dems` has to resolve, returning the algorithm
+    pub fn validate<'d>(
+        &self,
+        dems: &'d dyn DemResolver,
+    ) -> Result<&'d dyn DataEncapsulation, AbeError> {
+        let dem = self.header.resolve(dems)?;
+        self.validate_with(dem)?;
+
+        Ok(dem)
+    }
+
+    /// Like `validate`, for a payload encrypted with the given symmetric algorithm
+    pub fn validate_with(&self, dem: &dyn DataEncapsulation) -> Result<(), AbeError> {
+        self.header.validate()?;
+        check_payload_length(dem, self.header.segment_size(), &self.message)
+    }
+
+    /// Splits the cipher text into its header and the symmetric payload
+    pub fn detach(self) -> (Detached<H>, Vec<u8>) {
+        let header = Detached {
+            header: self.header,
+            payload_hash: payload_hash(&self.message),
+        };
+        (header, self.message)
+    }
+
+    /// Joins a detached header with its payload, checking that the payload belongs to it
+    pub fn attach(header: Detached<H>, payload: Vec<u8>) -> Result<Self, AbeError> {
+        if header.payload_hash != payload_hash(&payload) {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::PayloadMismatch,
+                "Payload does not match the hash recorded in the header",
+            ));
+        }
+
+        // the payload is checked against its algorithm once it is decrypted
+        header.header.validate()?;
+        Ok(CipherText {
+            header: header.header,
+            message: payload,
+        })
+    }
+}
+
+impl<H> Detached<H> {
+    /// Creates the header for a payload of `length` bytes read from `payload`, e.g. a file too
+    /// large to be held in memory
+    pub fn for_payload<R: Read>(header: H, length: u64, payload: R) -> Result<Self, AbeError> {
+        Ok(Detached {
+            header,
+            payload_hash: Fingerprint::of_reader(PAYLOAD_LABEL, length, payload)
+                .map_err(|e| AbeError::new(format!("Could not hash payload: {}", e).as_str()))?,
+        })
+    }
+
+    /// Checks that a payload of `length` bytes read from `payload` belongs to the header,
+    /// without holding it in memory
+    pub fn verify_payload<R: Read>(&self, length: u64, payload: R) -> Result<(), AbeError> {
+        let hash = Fingerprint::of_reader(PAYLOAD_LABEL, length, payload)
+            .map_err(|e| AbeError::new(format!("Could not hash payload: {}", e).as_str()))?;
+        if self.payload_hash != hash {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::PayloadMismatch,
+                "Payload does not match the hash recorded in the header",
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Checks that the components are for the leaves in order, each with a unique index, and none
+// is the identity
+fn check_leaf_components<G: Group>(
+    leaves: &[AbeAttribute],
+    components: &[(AbeIdentifier, G)],
+    name: &str,
+) -> Result<(), AbeError> {
+    check_leaf_identifiers(leaves, components, name)?;
+    for (leaf, (_, c)) in leaves.iter().zip(components.iter()) {
+        if c.is_zero() {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::IdentityElement,
+                format!(
+                    "{} entry of leaf {} is the identity element",
+                    name, leaf.name
+                )
+                .as_str(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Checks that the components are for the leaves in order, each with a unique index
+fn check_leaf_identifiers<T>(
+    leaves: &[AbeAttribute],
+    components: &[(AbeIdentifier, T)],
+    name: &str,
+) -> Result<(), AbeError> {
+    if leaves.len() != components.len() {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::LeafMismatch,
+            format!(
+                "Access tree has {} leaves but {} has {} entries",
+                leaves.len(),
+                name,
+                components.len()
+            )
+            .as_str(),
+        ));
+    }
+
+    let mut indices = BTreeSet::new();
+    for (leaf, (identifier, _)) in leaves.iter().zip(components.iter()) {
+        if leaf != identifier || leaf.index.is_none() {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::LeafMismatch,
+                format!(
+                    "Leaf {} does not match {} entry {}",
+                    leaf.name, name, identifier.name
+                )
+                .as_str(),
+            ));
+        }
+        if !indices.insert(leaf.index) {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::DuplicateIndex,
+                format!("Index {:?} is used by more than one leaf", leaf.index).as_str(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// the payload has to hold at least the nonce and tag of a single (segment of) cipher text
+fn check_payload_length(
+    dem: &dyn DataEncapsulation,
+    segment_size: Option<usize>,
+    payload: &[u8],
+) -> Result<(), AbeError> {
+    let minimum_length = match segment_size {
+        None => dem.overhead(),
+        Some(_) => stream::nonce_prefix_size(dem) + dem.tag_size(),
+    };
+    if payload.len() < minimum_length {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::PayloadTooShort,
+            format!("Payload of {} bytes is too short", payload.len()).as_str(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn default_algorithm() -> String {
+    dem::DEFAULT_ALGORITHM.to_string()
+}
+
+const PAYLOAD_LABEL: &str = "abe-payload";
+
+fn payload_hash(payload: &[u8]) -> Fingerprint {
+    Fingerprint::of(PAYLOAD_LABEL, payload)
+}
@@ -0,0 +1,159 @@
+// Models of online/offline encryption, see `online`. Keys are the ones of `crypto`.
+
+use std::collections::BTreeMap;
+
+use rabe_bn::{Fr, Group, Gt, G1, G2};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    check_leaf_components, check_leaf_identifiers, system_id_of, CipherText, CipherTextHeader,
+};
+use crate::abe_attribute::AbeIdentifier;
+use crate::access_tree::{AccessTree, GetAttributes};
+use crate::armor;
+use crate::canonical::{
+    serialize_group, serialize_group_pairs, CanonicalEncode, Fingerprint, Fingerprinted,
+};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+use crate::errors::armor_error::ArmorError;
+
+/// Piece of an intermediate cipher text for one leaf, `c = Tj^lambda`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbePiece {
+    pub lambda: Fr,
+    #[serde(serialize_with = "serialize_group")]
+    pub c: G1,
+}
+
+/// Cipher text components precomputed before the policy is known, see `online`. Holds the
+/// secret exponent `s`, so it has to be kept secret and used for one encryption only. It can
+/// neither be cloned nor serialized, storing it takes an explicit `export`.
+#[derive(Debug, PartialEq)]
+pub struct AbeIntermediate {
+    pub system_id: Fingerprint,
+    pub s: Fr,
+    /// g1^s
+    pub c_0: G1,
+    /// y^s
+    pub y_s: Gt,
+    /// Unused pieces per attribute of the system
+    pub pieces: BTreeMap<String, Vec<AbePiece>>,
+}
+
+// serde form of `AbeIntermediate`, used by its `export` and `import` only
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "AbeIntermediate")]
+struct AbeIntermediateExport {
+    system_id: Fingerprint,
+    s: Fr,
+    #[serde(serialize_with = "serialize_group")]
+    c_0: G1,
+    y_s: Gt,
+    pieces: BTreeMap<String, Vec<AbePiece>>,
+}
+
+/// The ABE part of a cipher text encrypted from an intermediate cipher text. The components of
+/// the leaves were precomputed for random shares, `arr_delta` holds the difference to the shares
+/// of the policy. Decryption needs the parameters `g1`, `g2` and `y` of the system, which have to
+/// match its system identifier.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeOnlineHeader {
+    /// Format version, see `kdf::FORMAT_VERSION`
+    pub version: u32,
+    pub system_id: Fingerprint,
+    /// Identifier of the data encapsulation mechanism used for the payload
+    pub algorithm: String,
+    /// Policy of the cipher text, with indices but without the shares of the secret
+    pub access_tree: Box<AccessTree>,
+    #[serde(serialize_with = "serialize_group")]
+    pub c_0: G1,
+    pub c_1: Gt,
+    #[serde(serialize_with = "serialize_group")]
+    pub g1: G1,
+    #[serde(serialize_with = "serialize_group")]
+    pub g2: G2,
+    pub y: Gt,
+    #[serde(serialize_with = "serialize_group_pairs")]
+    pub arr_c: Vec<(AbeIdentifier, G1)>,
+    pub arr_delta: Vec<(AbeIdentifier, Fr)>,
+}
+
+pub type AbeOnlineCipherText = CipherText<AbeOnlineHeader>;
+
+impl CanonicalEncode for AbeOnlineHeader {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.version.encode_canonical(out);
+        self.system_id.encode_canonical(out);
+        self.algorithm.encode_canonical(out);
+        self.access_tree.encode_canonical(out);
+        self.c_0.encode_canonical(out);
+        self.c_1.encode_canonical(out);
+        self.g1.encode_canonical(out);
+        self.g2.encode_canonical(out);
+        self.y.encode_canonical(out);
+        self.arr_c.encode_canonical(out);
+        self.arr_delta.encode_canonical(out);
+    }
+}
+
+impl Fingerprinted for AbeOnlineHeader {
+    const FINGERPRINT_LABEL: &'static str = "abe-online-header";
+}
+
+impl AbeIntermediate {
+    /// Label of the ASCII armor of an export
+    pub const LABEL: &'static str = "ABE INTERMEDIATE CIPHERTEXT";
+
+    /// Exports the intermediate cipher text as ASCII armored text, e.g. to store a pool on the
+    /// device that encrypts with it. The export contains `s`: anyone who reads it can decrypt
+    /// the cipher text encrypted with it, and importing it twice reuses `s` for two encryptions.
+    pub fn export(&self) -> Result<String, ArmorError> {
+        let mut json = Vec::new();
+        AbeIntermediateExport::serialize(self, &mut serde_json::Serializer::new(&mut json))
+            .map_err(|e| ArmorError::new(format!("Could not serialize: {}", e).as_str()))?;
+        Ok(armor::armor(Self::LABEL, &json))
+    }
+
+    /// Imports an intermediate cipher text written by `export`. The export should be deleted
+    /// once it is imported, so that it is used for one encryption only.
+    pub fn import(text: &str) -> Result<Self, ArmorError> {
+        let json = armor::dearmor(Self::LABEL, text)?;
+        AbeIntermediateExport::deserialize(&mut serde_json::Deserializer::from_slice(&json))
+            .map_err(|e| ArmorError::new(format!("Could not deserialize: {}", e).as_str()))
+    }
+}
+
+impl CipherTextHeader for AbeOnlineHeader {
+    const CIPHER_TEXT_LABEL: &'static str = "abe-online-cipher-text";
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    /// Checks that `g1`, `g2` and `y` are the parameters of the system of the cipher text, every
+    /// leaf of the access tree has a unique index with matching entries in `arr_c` and
+    /// `arr_delta` and no group element is the identity
+    fn validate_components(&self) -> Result<(), AbeError> {
+        if self.c_0.is_zero() || self.c_1 == Gt::one() {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::IdentityElement,
+                "c_0 or c_1 is the identity element",
+            ));
+        }
+        // decryption relies on them, so they cannot be left to whoever wrote the cipher text
+        if system_id_of(&self.g1, &self.g2, &self.y) != self.system_id {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::SystemMismatch,
+                "g1, g2 and y are not the parameters of the system of the cipher text",
+            ));
+        }
+
+        let leaves = self.access_tree.get_attributes();
+        check_leaf_components(&leaves, &self.arr_c, "arr_c")?;
+        check_leaf_identifiers(&leaves, &self.arr_delta, "arr_delta")
+    }
+}
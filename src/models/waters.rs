@@ -0,0 +1,167 @@
+// Models of the Waters (2011) scheme, see `waters`
+
+use std::collections::BTreeMap;
+
+use rabe_bn::{Fr, Group, Gt, G1, G2};
+use serde::{Deserialize, Serialize};
+
+use super::{check_leaf_components, CipherText, CipherTextHeader};
+use crate::abe_attribute::AbeIdentifier;
+use crate::access_tree::{AccessTree, GetAttributes};
+use crate::canonical::{
+    serialize_group, serialize_group_map, serialize_group_pairs, CanonicalEncode, Fingerprint,
+    Fingerprinted,
+};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+
+/// Public key of the Waters (2011) scheme, see `waters`. Like the large-universe scheme it
+/// hashes attributes instead of registering them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeWatersPublicKey {
+    #[serde(serialize_with = "serialize_group")]
+    pub g1: G1,
+    #[serde(serialize_with = "serialize_group")]
+    pub g2: G2,
+    /// g1^a
+    #[serde(serialize_with = "serialize_group")]
+    pub g1_a: G1,
+    /// e(g1, g2)^alpha
+    pub y: Gt,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeWatersMasterKey {
+    pub alpha: Fr,
+    pub a: Fr,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeWatersSecretKey {
+    pub system_id: Fingerprint,
+    /// g2^(alpha + a * t)
+    #[serde(serialize_with = "serialize_group")]
+    pub k: G2,
+    /// g2^t
+    #[serde(serialize_with = "serialize_group")]
+    pub l: G2,
+    /// H(x)^t for every attribute x
+    #[serde(serialize_with = "serialize_group_map")]
+    pub arr_k: BTreeMap<String, G1>,
+}
+
+/// The ABE part of a Waters cipher text, with a pair of components for every row of the LSSS
+/// matrix of its access tree, i.e. for every leaf
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbeWatersHeader {
+    /// Format version, see `kdf::FORMAT_VERSION`
+    pub version: u32,
+    pub system_id: Fingerprint,
+    /// Identifier of the data encapsulation mechanism used for the payload
+    pub algorithm: String,
+    /// Policy of the cipher text, with indices but without the shares of the secret
+    pub access_tree: Box<AccessTree>,
+    #[serde(serialize_with = "serialize_group")]
+    pub c_0: G1,
+    pub c_1: Gt,
+    #[serde(serialize_with = "serialize_group_pairs")]
+    pub arr_c: Vec<(AbeIdentifier, G1)>,
+    #[serde(serialize_with = "serialize_group_pairs")]
+    pub arr_d: Vec<(AbeIdentifier, G2)>,
+}
+
+pub type AbeWatersCipherText = CipherText<AbeWatersHeader>;
+
+impl CanonicalEncode for AbeWatersPublicKey {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.g1.encode_canonical(out);
+        self.g2.encode_canonical(out);
+        self.g1_a.encode_canonical(out);
+        self.y.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeWatersSecretKey {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.system_id.encode_canonical(out);
+        self.k.encode_canonical(out);
+        self.l.encode_canonical(out);
+        self.arr_k.encode_canonical(out);
+    }
+}
+
+impl CanonicalEncode for AbeWatersHeader {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        self.version.encode_canonical(out);
+        self.system_id.encode_canonical(out);
+        self.algorithm.encode_canonical(out);
+        self.access_tree.encode_canonical(out);
+        self.c_0.encode_canonical(out);
+        self.c_1.encode_canonical(out);
+        self.arr_c.encode_canonical(out);
+        self.arr_d.encode_canonical(out);
+    }
+}
+
+// the fingerprint of the key is its system identifier, see `system_id`
+impl Fingerprinted for AbeWatersPublicKey {
+    const FINGERPRINT_LABEL: &'static str = "abe-waters-system";
+}
+
+impl Fingerprinted for AbeWatersSecretKey {
+    const FINGERPRINT_LABEL: &'static str = "abe-waters-secret-key";
+}
+
+impl Fingerprinted for AbeWatersHeader {
+    const FINGERPRINT_LABEL: &'static str = "abe-waters-header";
+}
+
+impl AbeWatersPublicKey {
+    /// Stable identifier of the setup, derived from the canonical encoding of the key
+    pub fn system_id(&self) -> Fingerprint {
+        self.fingerprint()
+    }
+}
+
+impl AbeWatersSecretKey {
+    /// Checks that the key has attributes and no group element is the identity
+    pub fn validate(&self) -> Result<(), AbeError> {
+        if self.arr_k.is_empty() {
+            return Err(AbeError::new("Secret key has no attributes"));
+        }
+        if self.k.is_zero() || self.l.is_zero() || self.arr_k.values().any(|k| k.is_zero()) {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::IdentityElement,
+                "k, l or a k_x is the identity element",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl CipherTextHeader for AbeWatersHeader {
+    const CIPHER_TEXT_LABEL: &'static str = "abe-waters-cipher-text";
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    /// Checks that every leaf of the access tree has a unique index with matching entries in
+    /// `arr_c` and `arr_d` and no group element is the identity
+    fn validate_components(&self) -> Result<(), AbeError> {
+        if self.c_0.is_zero() || self.c_1 == Gt::one() {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::IdentityElement,
+                "c_0 or c_1 is the identity element",
+            ));
+        }
+
+        let leaves = self.access_tree.get_attributes();
+        check_leaf_components(&leaves, &self.arr_c, "arr_c")?;
+        check_leaf_components(&leaves, &self.arr_d, "arr_d")
+    }
+}
@@ -0,0 +1,242 @@
+// Online/offline encryption for the ciphertext-policy scheme of `crypto`, after Hohenberger and
+// Waters, "Online/Offline Attribute-Based Encryption" (PKC 2014). The group operations of an
+// encryption do not depend on the policy, so they are done ahead of time.
+//
+// The offline phase picks `s` and computes `c_0 = g1^s` and `y^s`, and for every attribute j of
+// the system a number of pieces `c = Tj^rho` for random `rho`. The online phase shares `s` over
+// the access tree, which only takes field operations, and gives leaf i with attribute j an unused
+// piece of j together with the correction `delta_i = lambda_i - rho`. A key holds
+// `d_0 = g2^(alpha - r)` and `d_j = g2^(r / tj)`, so for a satisfying set of leaves
+// `prod e(c_i, d_j) = e(g1, g2)^(r * sum rho)` and the corrections add
+// `(y / e(g1, d_0))^(sum delta) = e(g1, g2)^(r * sum delta)`. As the shares of the leaves add up
+// to `s`, `e(c_0, d_0)` times both is `e(g1, g2)^(alpha * s)`.
+
+use std::collections::BTreeMap;
+
+use rabe_bn::{pairing, Fr, Gt};
+use rand::Rng;
+
+use crate::abe_attribute::{AbeAttribute, AbeIdentifier};
+use crate::access_tree::{AccessTree, AssignValues, GetAttributes};
+use crate::dem::{Aes256Gcm, BuiltinDems, DataEncapsulation, DemResolver};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+use crate::kdf;
+use crate::kdf::KeySchedule;
+use crate::models::{
+    AbeDecrypted, AbeIntermediate, AbeOnlineCipherText, AbeOnlineHeader, AbePiece, AbePublicKey,
+    AbeSecretKey,
+};
+
+/// Offline phase, precomputes the components of one encryption. Every attribute of the system
+/// gets `leaves_per_attribute` pieces, the number of leaves it may have in the policy.
+///
+/// The cost grows with the system, not with the policy: for the `|U|` attributes of the public
+/// key this takes `|U| * leaves_per_attribute` exponentiations in G1 and the result holds as
+/// many group elements, whether or not the policy uses the attributes.
+pub fn precompute<R: Rng + ?Sized>(
+    public_key: &AbePublicKey,
+    leaves_per_attribute: usize,
+    rng: &mut R,
+) -> AbeIntermediate {
+    // s = random field element
+    let s: Fr = rng.gen();
+
+    // c = Tj^rho
+    let pieces = public_key
+        .big_t
+        .iter()
+        .map(|(attribute, big_t)| {
+            let pieces = (0..leaves_per_attribute)
+                .map(|_| {
+                    let lambda: Fr = rng.gen();
+                    AbePiece {
+                        lambda,
+                        c: *big_t * lambda,
+                    }
+                })
+                .collect::<Vec<AbePiece>>();
+            (attribute.clone(), pieces)
+        })
+        .collect::<BTreeMap<String, Vec<AbePiece>>>();
+
+    AbeIntermediate {
+        system_id: public_key.system_id(),
+        s,
+        c_0: public_key.g1 * s,
+        y_s: public_key.y.pow(s),
+        pieces,
+    }
+}
+
+/// Offline phase for `size` encryptions, `size` times the cost of `precompute`
+pub fn precompute_pool<R: Rng + ?Sized>(
+    public_key: &AbePublicKey,
+    size: usize,
+    leaves_per_attribute: usize,
+    rng: &mut R,
+) -> Vec<AbeIntermediate> {
+    (0..size)
+        .map(|_| precompute(public_key, leaves_per_attribute, rng))
+        .collect()
+}
+
+/// Online phase, binds the intermediate cipher text to the access tree and encrypts the message.
+/// The intermediate cipher text is used up, it has to be precomputed for the public key.
+pub fn encrypt<R: Rng + ?Sized>(
+    intermediate: AbeIntermediate,
+    secret: &Gt,
+    message: &[u8],
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeOnlineCipherText, AbeError> {
+    encrypt_with(
+        &Aes256Gcm,
+        intermediate,
+        secret,
+        message,
+        public_key,
+        access_tree,
+        rng,
+    )
+}
+
+/// Like `encrypt`, protecting the payload with the given data encapsulation mechanism
+pub fn encrypt_with<R: Rng + ?Sized>(
+    dem: &dyn DataEncapsulation,
+    intermediate: AbeIntermediate,
+    secret: &Gt,
+    message: &[u8],
+    public_key: &AbePublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeOnlineCipherText, AbeError> {
+    let AbeIntermediate {
+        system_id,
+        s,
+        c_0,
+        y_s,
+        mut pieces,
+    } = intermediate;
+    if system_id != public_key.system_id() {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::SystemMismatch,
+            "Intermediate cipher text was precomputed for a different system than the public key",
+        ));
+    }
+
+    // lambda_i = shares of s
+    let mut access_tree = access_tree.assign_values(s, Some(s), rng);
+    access_tree.assign_indices();
+
+    // ci = precomputed piece, delta_i = lambda_i - rho
+    let mut arr_c = Vec::new();
+    let mut arr_delta = Vec::new();
+    for leaf in access_tree.get_attributes() {
+        let piece = pieces
+            .get_mut(&leaf.name)
+            .and_then(Vec::pop)
+            .ok_or(AbeError::new(
+                format!(
+                    "Intermediate cipher text has no piece left for attribute {}",
+                    leaf.name
+                )
+                .as_str(),
+            ))?;
+        let lambda = leaf.value.ok_or(AbeError::new(
+            format!("Expected value for {} but got None", leaf.name).as_str(),
+        ))?;
+        arr_c.push((AbeIdentifier::from(leaf.clone()), piece.c));
+        arr_delta.push((AbeIdentifier::from(leaf), lambda - piece.lambda));
+    }
+
+    // c1 = m * y^s
+    let c_1 = *secret * y_s;
+
+    // the shares are only needed to compute the corrections
+    access_tree.clear_values();
+    let header = AbeOnlineHeader {
+        version: kdf::FORMAT_VERSION,
+        system_id,
+        algorithm: dem.algorithm().to_string(),
+        access_tree: Box::new(access_tree),
+        c_0,
+        c_1,
+        g1: public_key.g1,
+        g2: public_key.g2,
+        y: public_key.y,
+        arr_c,
+        arr_delta,
+    };
+    let message = dem.encrypt(&payload_key(secret, &header), message)?;
+
+    Ok(AbeOnlineCipherText { header, message })
+}
+
+/// Decrypts the cipher text if the attributes of the secret key satisfy its access tree
+pub fn decrypt(
+    cipher_text: &AbeOnlineCipherText,
+    secret_key: &AbeSecretKey,
+) -> Result<AbeDecrypted, AbeError> {
+    decrypt_with(&BuiltinDems, cipher_text, secret_key)
+}
+
+/// Like `decrypt`, for a payload protected with a data encapsulation mechanism `dems` resolves
+pub fn decrypt_with(
+    dems: &dyn DemResolver,
+    cipher_text: &AbeOnlineCipherText,
+    secret_key: &AbeSecretKey,
+) -> Result<AbeDecrypted, AbeError> {
+    let dem = cipher_text.validate(dems)?;
+    let header = &cipher_text.header;
+    if header.system_id != secret_key.system_id {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::SystemMismatch,
+            "Secret key belongs to a different system than the cipher text",
+        ));
+    }
+
+    let attributes = secret_key
+        .arr_d
+        .keys()
+        .map(|name| AbeAttribute::new(name))
+        .collect::<Vec<AbeAttribute>>();
+    let leaves = header
+        .access_tree
+        .find_satisfying_leaves(&attributes)
+        .ok_or(AbeError::new(
+            "Secret key attributes do not satisfy the access tree of the cipher text",
+        ))?;
+
+    // prod e(ci, dj) = e(g1, g2)^(r * sum rho), validation made sure the components line up
+    // with the leaves
+    let mut product = Gt::one();
+    let mut delta = Fr::zero();
+    for leaf in &leaves {
+        let position = header
+            .arr_c
+            .iter()
+            .position(|(identifier, _)| leaf == identifier)
+            .ok_or(AbeError::with_kind(
+                AbeErrorKind::LeafMismatch,
+                format!("No cipher text component for leaf {}", leaf.name).as_str(),
+            ))?;
+        product = product * pairing(header.arr_c[position].1, secret_key.arr_d[&leaf.name]);
+        delta = delta + header.arr_delta[position].1;
+    }
+
+    // e(g1, g2)^r = y / e(g1, d0), validation made sure g1 and y are the ones of the system
+    let r_pairing = header.y * pairing(header.g1, secret_key.d_0).inverse();
+
+    // e(c0, d0) * e(g1, g2)^(r * sum rho) * e(g1, g2)^(r * sum delta) = e(g1, g2)^(alpha * s)
+    let blinding = pairing(header.c_0, secret_key.d_0) * product * r_pairing.pow(delta);
+    let secret = header.c_1 * blinding.inverse();
+    let message = dem.decrypt(&payload_key(&secret, header), &cipher_text.message)?;
+
+    Ok(AbeDecrypted { message, secret })
+}
+
+// Derives the key of the payload from the secret, bound to the header
+fn payload_key(secret: &Gt, header: &AbeOnlineHeader) -> [u8; kdf::KEY_SIZE] {
+    KeySchedule::for_header(secret, header).derive(kdf::PAYLOAD_KEY)
+}
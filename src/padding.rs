@@ -0,0 +1,403 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::canonical::CanonicalEncode;
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+
+// Padding is appended as in ISO/IEC 7816-4: a single 0x80 byte followed by zeros, so it is
+// stripped without knowing its length and the plaintext may end with anything
+const MARKER: u8 = 0x80;
+
+/// Largest block size and bound of random padding, the padding is held in memory when the
+/// message is
+pub const MAX_PADDING: u64 = 1 << 30;
+
+/// How far the length of the message is rounded up before encryption, which hides the exact
+/// length from anyone without the key. The padding always adds at least one byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Padding {
+    /// Up to the next power of two, which leaks only the magnitude of the length at the cost of
+    /// up to doubling it
+    PowerOfTwo,
+    /// Up to the next multiple of the block size
+    Block(u64),
+    /// A random number of bytes up to the given bound, drawn anew for every message
+    Random(u64),
+}
+
+impl Padding {
+    /// Checks that the parameters of the scheme are usable
+    pub fn validate(&self) -> Result<(), AbeError> {
+        if *self == Padding::Block(0) {
+            return Err(AbeError::with_kind(
+                AbeErrorKind::InvalidPadding,
+                "Padding block size must be positive",
+            ));
+        }
+        match self {
+            Padding::Block(bound) | Padding::Random(bound) if *bound > MAX_PADDING => {
+                Err(AbeError::with_kind(
+                    AbeErrorKind::InvalidPadding,
+                    format!(
+                        "Padding of {} exceeds the maximum of {}",
+                        bound, MAX_PADDING
+                    )
+                    .as_str(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Length of the padded message, `extra` is the random part of the padding
+    fn padded_length(&self, length: u64, extra: u64) -> u64 {
+        let minimum = length.saturating_add(1);
+        match self {
+            Padding::PowerOfTwo => minimum.checked_next_power_of_two().unwrap_or(minimum),
+            Padding::Block(size) => minimum
+                .div_ceil(*size)
+                .checked_mul(*size)
+                .unwrap_or(minimum),
+            Padding::Random(_) => minimum.saturating_add(extra),
+        }
+    }
+
+    fn draw_extra<R: Rng + ?Sized>(&self, rng: &mut R) -> u64 {
+        match self {
+            Padding::Random(bound) => rng.gen_range(0..=*bound),
+            _ => 0,
+        }
+    }
+}
+
+impl Display for Padding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Padding::PowerOfTwo => write!(f, "pow2"),
+            Padding::Block(size) => write!(f, "block:{}", size),
+            Padding::Random(bound) => write!(f, "random:{}", bound),
+        }
+    }
+}
+
+impl FromStr for Padding {
+    type Err = AbeError;
+
+    /// Parses "pow2", "block:<size>" or "random:<bound>"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            AbeError::with_kind(
+                AbeErrorKind::InvalidPadding,
+                format!(
+                    "Unknown padding {:?}, expected pow2, block:<size> or random:<bound>",
+                    s
+                )
+                .as_str(),
+            )
+        };
+        let padding = match s.split_once(':') {
+            None if s == "pow2" => Padding::PowerOfTwo,
+            Some(("block", size)) => Padding::Block(size.parse().map_err(|_| error())?),
+            Some(("random", bound)) => Padding::Random(bound.parse().map_err(|_| error())?),
+            _ => return Err(error()),
+        };
+        padding.validate()?;
+        Ok(padding)
+    }
+}
+
+impl CanonicalEncode for Padding {
+    fn encode_canonical(&self, out: &mut Vec<u8>) {
+        match self {
+            Padding::PowerOfTwo => 0u32.encode_canonical(out),
+            Padding::Block(size) => {
+                1u32.encode_canonical(out);
+                size.encode_canonical(out);
+            }
+            Padding::Random(bound) => {
+                2u32.encode_canonical(out);
+                bound.encode_canonical(out);
+            }
+        }
+    }
+}
+
+/// Pads a message held in memory
+pub fn pad<R: Rng + ?Sized>(
+    padding: &Padding,
+    buffer: &mut Vec<u8>,
+    rng: &mut R,
+) -> Result<(), AbeError> {
+    let length = padding.padded_length(buffer.len() as u64, padding.draw_extra(rng));
+    let length = usize::try_from(length).map_err(|_| {
+        AbeError::with_kind(
+            AbeErrorKind::InvalidPadding,
+            "Padded message does not fit into memory",
+        )
+    })?;
+    buffer.push(MARKER);
+    buffer.resize(length, 0);
+    Ok(())
+}
+
+/// Strips the padding from a message held in memory
+pub fn unpad(buffer: &mut Vec<u8>) -> Result<(), AbeError> {
+    match buffer.iter().rposition(|b| *b != 0) {
+        Some(position) if buffer[position] == MARKER => {
+            buffer.truncate(position);
+            Ok(())
+        }
+        _ => Err(padding_error()),
+    }
+}
+
+fn padding_error() -> AbeError {
+    AbeError::with_kind(AbeErrorKind::InvalidPadding, "Message is not padded")
+}
+
+fn to_io_error(error: AbeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.message)
+}
+
+/// Writes everything written to it to the inner writer, followed by the padding once `finish`
+/// is called
+pub struct Padder<W: Write> {
+    padding: Padding,
+    extra: u64,
+    length: u64,
+    inner: W,
+}
+
+impl<W: Write> Padder<W> {
+    pub fn new<R: Rng + ?Sized>(padding: Padding, inner: W, rng: &mut R) -> Padder<W> {
+        Padder {
+            padding,
+            extra: padding.draw_extra(rng),
+            length: 0,
+            inner,
+        }
+    }
+
+    /// Writes the padding and returns the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.write_all(&[MARKER])?;
+        let zeros = self.padding.padded_length(self.length, self.extra) - self.length - 1;
+        io::copy(&mut io::repeat(0).take(zeros), &mut self.inner)?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for Padder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let length = self.inner.write(buf)?;
+        self.length += length as u64;
+        Ok(length)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Strips the padding from the data read from the inner reader.
+///
+/// A trailing marker and the zeros after it are held back until it is clear whether they are
+/// the padding. Only their count is kept, so memory use does not grow with the padding.
+pub struct Unpadder<R: Read> {
+    inner: R,
+    buffer: Vec<u8>,
+    start: usize,
+    end: usize,
+    // a marker followed by `zeros` zeros was read that may be the padding
+    pending: bool,
+    zeros: u64,
+    // a held back marker and zeros that turned out to be data
+    release_marker: bool,
+    release_zeros: u64,
+    finished: bool,
+}
+
+impl<R: Read> Unpadder<R> {
+    pub fn new(inner: R) -> Unpadder<R> {
+        Unpadder {
+            inner,
+            buffer: vec![0u8; 8 * 1024],
+            start: 0,
+            end: 0,
+            pending: false,
+            zeros: 0,
+            release_marker: false,
+            release_zeros: 0,
+            finished: false,
+        }
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        let length = self.inner.read(&mut self.buffer)?;
+        if length == 0 {
+            if !self.pending {
+                return Err(to_io_error(padding_error()));
+            }
+            self.finished = true;
+            return Ok(());
+        }
+
+        let chunk = &self.buffer[..length];
+        self.start = 0;
+        match chunk.iter().rposition(|b| *b != 0) {
+            // zeros only extend a pending padding, otherwise they are data
+            None if self.pending => {
+                self.zeros += length as u64;
+                self.end = 0;
+            }
+            None => self.end = length,
+            Some(position) => {
+                self.release_marker = self.pending;
+                self.release_zeros = if self.pending { self.zeros } else { 0 };
+                self.pending = chunk[position] == MARKER;
+                if self.pending {
+                    self.zeros = (length - position - 1) as u64;
+                    self.end = position;
+                } else {
+                    self.zeros = 0;
+                    self.end = length;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Unpadder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if self.release_marker {
+                self.release_marker = false;
+                buf[0] = MARKER;
+                return Ok(1);
+            }
+            if self.release_zeros > 0 {
+                let length = buf.len().min(self.release_zeros as usize);
+                buf[..length].fill(0);
+                self.release_zeros -= length as u64;
+                return Ok(length);
+            }
+            if self.start < self.end {
+                let length = buf.len().min(self.end - self.start);
+                buf[..length].copy_from_slice(&self.buffer[self.start..self.start + length]);
+                self.start += length;
+                return Ok(length);
+            }
+            if self.finished {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use crate::errors::abe_error::AbeErrorKind;
+    use crate::padding::{pad, unpad, Padder, Padding, Unpadder, MAX_PADDING};
+
+    #[test]
+    fn test_padded_lengths() {
+        let rng = &mut rand::thread_rng();
+        for (padding, length, padded) in [
+            (Padding::PowerOfTwo, 0, 1),
+            (Padding::PowerOfTwo, 100, 128),
+            (Padding::PowerOfTwo, 127, 128),
+            (Padding::PowerOfTwo, 128, 256),
+            (Padding::Block(16), 0, 16),
+            (Padding::Block(16), 15, 16),
+            (Padding::Block(16), 16, 32),
+            (Padding::Random(0), 10, 11),
+        ] {
+            let mut buffer = vec![1u8; length];
+            pad(&padding, &mut buffer, rng).unwrap();
+            assert_eq!(buffer.len(), padded);
+        }
+
+        let mut buffer = vec![1u8; 10];
+        pad(&Padding::Random(5), &mut buffer, rng).unwrap();
+        assert!((11..=16).contains(&buffer.len()));
+    }
+
+    #[test]
+    fn test_unpad() {
+        let rng = &mut rand::thread_rng();
+        // messages ending in what looks like padding survive
+        for message in [vec![], vec![0u8; 20], vec![0x80, 0, 0], vec![1, 0x80]] {
+            let mut buffer = message.clone();
+            pad(&Padding::Block(8), &mut buffer, rng).unwrap();
+            unpad(&mut buffer).unwrap();
+            assert_eq!(buffer, message);
+        }
+
+        assert_eq!(
+            unpad(&mut vec![1, 0, 0]).err().unwrap().kind,
+            AbeErrorKind::InvalidPadding
+        );
+        assert!(unpad(&mut vec![]).is_err());
+    }
+
+    #[test]
+    fn test_stream_matches_in_memory() {
+        let rng = &mut rand::thread_rng();
+        let mut message = vec![0x80u8; 3000];
+        message.extend_from_slice(&[0u8; 20000]);
+        message.push(0x80);
+        message.extend_from_slice(&[0u8; 5]);
+
+        let mut padder = Padder::new(Padding::PowerOfTwo, Vec::new(), rng);
+        padder.write_all(&message).unwrap();
+        let padded = padder.finish().unwrap();
+        let mut expected = message.clone();
+        pad(&Padding::PowerOfTwo, &mut expected, rng).unwrap();
+        assert_eq!(padded, expected);
+
+        let mut unpadded = Vec::new();
+        Unpadder::new(padded.as_slice())
+            .read_to_end(&mut unpadded)
+            .unwrap();
+        assert_eq!(unpadded, message);
+
+        assert!(Unpadder::new([1u8, 0, 0].as_slice())
+            .read_to_end(&mut Vec::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        for padding in [
+            Padding::PowerOfTwo,
+            Padding::Block(4096),
+            Padding::Random(100),
+        ] {
+            assert_eq!(padding.to_string().parse::<Padding>().unwrap(), padding);
+        }
+        assert!("block:0".parse::<Padding>().is_err());
+        assert!(format!("random:{}", MAX_PADDING).parse::<Padding>().is_ok());
+        assert!(format!("random:{}", MAX_PADDING + 1)
+            .parse::<Padding>()
+            .is_err());
+        assert!("random:18446744073709551615".parse::<Padding>().is_err());
+        assert!(format!("block:{}", MAX_PADDING + 1)
+            .parse::<Padding>()
+            .is_err());
+        assert!("block".parse::<Padding>().is_err());
+        assert!("zeros".parse::<Padding>().is_err());
+    }
+}
@@ -2,9 +2,9 @@ use crate::abe_attribute::AbeAttribute;
 use crate::access_tree;
 use crate::errors::parse_error::ParseError;
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+#[derive(Debug, Clone, PartialEq)]
 enum Token {
-    Variable(char),
+    Variable(String),
     And,
     Or,
     OpenParen,
@@ -13,7 +13,7 @@ enum Token {
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum AstNode {
-    Variable(char),
+    Variable(String),
     BinaryOp(char, Box<AstNode>, Box<AstNode>),
 }
 
@@ -25,11 +25,18 @@ pub struct AccessTreeParser {
 
 impl AccessTreeParser {
     pub fn new(input: &str) -> AccessTreeParser {
-        // simple lexer
+        // simple lexer, attribute names are runs of letters, digits and `_ - . :`
         let mut tokens = Vec::new();
-        for c in input.chars() {
+        let mut chars = input.chars().peekable();
+        while let Some(c) = chars.next() {
             match c {
-                'a'..='z' | 'A'..='Z' => tokens.push(Token::Variable(c)),
+                c if is_name_char(c) => {
+                    let mut name = c.to_string();
+                    while let Some(next) = chars.next_if(|next| is_name_char(*next)) {
+                        name.push(next);
+                    }
+                    tokens.push(Token::Variable(name));
+                }
                 '&' => tokens.push(Token::And),
                 '|' => tokens.push(Token::Or),
                 '(' => tokens.push(Token::OpenParen),
@@ -55,10 +62,10 @@ impl AccessTreeParser {
     }
 
     fn parse_variable(&mut self) -> Result<AstNode, ParseError> {
-        match self.current_token {
-            Some(Token::Variable(c)) => {
+        match self.current_token.take() {
+            Some(Token::Variable(name)) => {
                 self.advance();
-                Ok(AstNode::Variable(c))
+                Ok(AstNode::Variable(name))
             }
             Some(token) => Err(ParseError::new(
                 format!("Invalid token {:?}", token).as_str(),
@@ -107,8 +114,8 @@ impl AccessTreeParser {
 
     fn ast_to_access_tree(&self, ast: AstNode) -> Result<access_tree::AccessTree, ParseError> {
         Ok(match ast {
-            AstNode::Variable(c) => access_tree::AccessTree::Leaf(access_tree::Leaf {
-                attribute: AbeAttribute::new(&c.to_string()),
+            AstNode::Variable(name) => access_tree::AccessTree::Leaf(access_tree::Leaf {
+                attribute: AbeAttribute::new(&name),
                 value: None,
             }),
             AstNode::BinaryOp(op, left, right) => {
@@ -151,6 +158,10 @@ impl AccessTreeParser {
     }
 }
 
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -167,10 +178,10 @@ mod tests {
                 '|',
                 Box::new(AstNode::BinaryOp(
                     '&',
-                    Box::new(AstNode::Variable('a')),
-                    Box::new(AstNode::Variable('b')),
+                    Box::new(AstNode::Variable("a".to_string())),
+                    Box::new(AstNode::Variable("b".to_string())),
                 )),
-                Box::new(AstNode::Variable('c')),
+                Box::new(AstNode::Variable("c".to_string())),
             )
         );
     }
@@ -185,11 +196,11 @@ mod tests {
             result,
             AstNode::BinaryOp(
                 '&',
-                Box::new(AstNode::Variable('a')),
+                Box::new(AstNode::Variable("a".to_string())),
                 Box::new(AstNode::BinaryOp(
                     '|',
-                    Box::new(AstNode::Variable('b')),
-                    Box::new(AstNode::Variable('c')),
+                    Box::new(AstNode::Variable("b".to_string())),
+                    Box::new(AstNode::Variable("c".to_string())),
                 ),),
             )
         );
@@ -205,8 +216,8 @@ mod tests {
             result,
             AstNode::BinaryOp(
                 '|',
-                Box::new(AstNode::Variable('a')),
-                Box::new(AstNode::Variable('b')),
+                Box::new(AstNode::Variable("a".to_string())),
+                Box::new(AstNode::Variable("b".to_string())),
             )
         );
     }
@@ -221,8 +232,8 @@ mod tests {
             result,
             AstNode::BinaryOp(
                 '&',
-                Box::new(AstNode::Variable('a')),
-                Box::new(AstNode::Variable('b')),
+                Box::new(AstNode::Variable("a".to_string())),
+                Box::new(AstNode::Variable("b".to_string())),
             )
         );
     }
@@ -233,7 +244,7 @@ mod tests {
         let mut parser = AccessTreeParser::new(input);
         let result = parser.generate_ast().unwrap();
 
-        assert_eq!(result, AstNode::Variable('a'));
+        assert_eq!(result, AstNode::Variable("a".to_string()));
     }
 
     #[test]
@@ -246,8 +257,8 @@ mod tests {
             result,
             AstNode::BinaryOp(
                 '&',
-                Box::new(AstNode::Variable('a')),
-                Box::new(AstNode::Variable('b')),
+                Box::new(AstNode::Variable("a".to_string())),
+                Box::new(AstNode::Variable("b".to_string())),
             )
         );
     }
@@ -262,8 +273,8 @@ mod tests {
             result,
             AstNode::BinaryOp(
                 '|',
-                Box::new(AstNode::Variable('a')),
-                Box::new(AstNode::Variable('b')),
+                Box::new(AstNode::Variable("a".to_string())),
+                Box::new(AstNode::Variable("b".to_string())),
             )
         );
     }
@@ -284,19 +295,45 @@ mod tests {
                         '&',
                         Box::new(AstNode::BinaryOp(
                             '|',
-                            Box::new(AstNode::Variable('A')),
-                            Box::new(AstNode::Variable('D')),
+                            Box::new(AstNode::Variable("A".to_string())),
+                            Box::new(AstNode::Variable("D".to_string())),
                         )),
                         Box::new(AstNode::BinaryOp(
                             '|',
-                            Box::new(AstNode::Variable('B')),
-                            Box::new(AstNode::Variable('E')),
+                            Box::new(AstNode::Variable("B".to_string())),
+                            Box::new(AstNode::Variable("E".to_string())),
                         )),
                     )),
-                    Box::new(AstNode::Variable('C',)),
+                    Box::new(AstNode::Variable("C".to_string())),
+                )),
+                Box::new(AstNode::Variable("A".to_string()))
+            )
+        );
+    }
+
+    #[test]
+    fn test_parser_names() {
+        let input = "(Eng_Team | project:apollo) & clearance-2";
+        let mut parser = AccessTreeParser::new(input);
+        let result = parser.generate_ast().unwrap();
+
+        assert_eq!(
+            result,
+            AstNode::BinaryOp(
+                '&',
+                Box::new(AstNode::BinaryOp(
+                    '|',
+                    Box::new(AstNode::Variable("Eng_Team".to_string())),
+                    Box::new(AstNode::Variable("project:apollo".to_string())),
                 )),
-                Box::new(AstNode::Variable('A',))
+                Box::new(AstNode::Variable("clearance-2".to_string())),
             )
         );
     }
+
+    #[test]
+    fn test_parser_rejects_adjacent_names() {
+        let mut parser = AccessTreeParser::new("Eng Ops");
+        assert!(parser.generate_ast().is_err());
+    }
 }
@@ -0,0 +1,755 @@
+use std::collections::BTreeMap;
+
+use rabe_bn::{Group, Gt, G1, G2};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::access_tree::{AccessTree, GetAttributes};
+use crate::armor::Armored;
+use crate::canonical::Fingerprinted;
+use crate::dem::{BuiltinDems, DataEncapsulation, DemResolver};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+use crate::models::{
+    AbeCipherText, AbeDecrypted, AbeEnvelope, AbeHiddenCipherText, AbeKpCipherText, AbeKpSecretKey,
+    AbeLuCipherText, AbeLuMasterKey, AbeLuPublicKey, AbeLuSecretKey, AbeMaCipherText,
+    AbeMaGlobalParameters, AbeMaMasterKey, AbeMaPublicKey, AbeMaSecretKey, AbeMasterKey,
+    AbeOnlineCipherText, AbeOutsourcedKey, AbePublicKey, AbeSecretKey, AbeWatersCipherText,
+    AbeWatersMasterKey, AbeWatersPublicKey, AbeWatersSecretKey,
+};
+use crate::parser::AccessTreeParser;
+use crate::{crypto, hidden, kp_abe, lu_abe, ma_abe, online, waters};
+
+/// Common interface of the ABE schemes, so that code handling keys and cipher texts does not
+/// depend on a particular scheme
+pub trait AbeScheme {
+    /// Identifier of the scheme, recorded in its key files, see `SchemeKey`
+    const NAME: &'static str;
+
+    /// Its fingerprint identifies the system, see `canonical::Fingerprinted`
+    type PublicKey: Armored + Fingerprinted;
+    type MasterKey: Armored;
+    type SecretKey: Armored + Fingerprinted;
+    type CipherText: Armored + Fingerprinted;
+    /// What secret keys are issued for, attributes or an access tree
+    type KeyInput: SchemeInput;
+    /// What cipher texts are encrypted under, the counterpart of `KeyInput`
+    type EncryptInput: SchemeInput;
+
+    /// Creates a new system. Schemes that hash attributes ignore the attribute set, the others
+    /// only accept attributes from it.
+    fn setup<R: Rng + ?Sized>(
+        attributes: &[String],
+        rng: &mut R,
+    ) -> Result<(Self::PublicKey, Self::MasterKey), AbeError>;
+
+    fn keygen<R: Rng + ?Sized>(
+        input: &Self::KeyInput,
+        public_key: &Self::PublicKey,
+        master_key: &Self::MasterKey,
+        rng: &mut R,
+    ) -> Result<Self::SecretKey, AbeError>;
+
+    /// Like `keygen`, for a key whose holder can derive keys for subsets of its attributes with
+    /// `delegate`. Fails with `AbeErrorKind::Unsupported` unless the scheme supports delegation.
+    fn keygen_delegable<R: Rng + ?Sized>(
+        _input: &Self::KeyInput,
+        _public_key: &Self::PublicKey,
+        _master_key: &Self::MasterKey,
+        _rng: &mut R,
+    ) -> Result<Self::SecretKey, AbeError> {
+        Err(unsupported::<Self>("Delegation"))
+    }
+
+    /// Derives a secret key for a subset of the attributes of a key from `keygen_delegable`
+    fn delegate<R: Rng + ?Sized>(
+        _input: &Self::KeyInput,
+        _public_key: &Self::PublicKey,
+        _secret_key: &Self::SecretKey,
+        _rng: &mut R,
+    ) -> Result<Self::SecretKey, AbeError> {
+        Err(unsupported::<Self>("Delegation"))
+    }
+
+    fn encrypt<R: Rng + ?Sized>(
+        dem: &dyn DataEncapsulation,
+        secret: &Gt,
+        message: &[u8],
+        public_key: &Self::PublicKey,
+        input: &Self::EncryptInput,
+        rng: &mut R,
+    ) -> Result<Self::CipherText, AbeError>;
+
+    /// Checks that the cipher text is structurally sound and its payload is protected with a
+    /// data encapsulation mechanism `dems` resolves
+    fn validate(dems: &dyn DemResolver, cipher_text: &Self::CipherText) -> Result<(), AbeError>;
+
+    fn decrypt(
+        cipher_text: &Self::CipherText,
+        secret_key: &Self::SecretKey,
+    ) -> Result<AbeDecrypted, AbeError> {
+        Self::decrypt_with(&BuiltinDems, cipher_text, secret_key)
+    }
+
+    /// Like `decrypt`, for a payload protected with a data encapsulation mechanism `dems` resolves
+    fn decrypt_with(
+        dems: &dyn DemResolver,
+        cipher_text: &Self::CipherText,
+        secret_key: &Self::SecretKey,
+    ) -> Result<AbeDecrypted, AbeError>;
+
+    /// Encrypts the key of the payload for a new policy without touching the payload, see
+    /// `crypto::rewrap`. Envelopes are for the scheme of `crypto`, whatever the scheme of the cipher
+    /// text. Fails with `AbeErrorKind::Unsupported` unless the scheme supports rewrapping.
+    fn rewrap<R: Rng + ?Sized>(
+        _dems: &dyn DemResolver,
+        _cipher_text: Self::CipherText,
+        _secret_key: &Self::SecretKey,
+        _public_key: &AbePublicKey,
+        _access_tree: &AccessTree,
+        _rng: &mut R,
+    ) -> Result<AbeEnvelope, AbeError> {
+        Err(unsupported::<Self>("Rewrapping"))
+    }
+}
+
+fn unsupported<S: AbeScheme + ?Sized>(operation: &str) -> AbeError {
+    AbeError::with_kind(
+        AbeErrorKind::Unsupported,
+        format!("{} is not supported by scheme {:?}", operation, S::NAME).as_str(),
+    )
+}
+
+/// Input of `keygen` or `encrypt`, parsed from command line arguments
+pub trait SchemeInput: Sized {
+    fn parse(values: &[String]) -> Result<Self, AbeError>;
+}
+
+/// A set of attributes, separated by whitespace or commas
+impl SchemeInput for Vec<String> {
+    fn parse(values: &[String]) -> Result<Self, AbeError> {
+        let attributes = values
+            .iter()
+            .flat_map(|value| value.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|attribute| !attribute.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<String>>();
+        if attributes.is_empty() {
+            return Err(AbeError::new("No attributes given"));
+        }
+        Ok(attributes)
+    }
+}
+
+/// An access policy, e.g. "(A & B) | C"
+impl SchemeInput for AccessTree {
+    fn parse(values: &[String]) -> Result<Self, AbeError> {
+        AccessTreeParser::new(&values.join(" "))
+            .parse()
+            .map_err(|e| {
+                AbeError::new(format!("Could not parse access tree: {:?}", e.to_string()).as_str())
+            })
+    }
+}
+
+/// A key or cipher text together with the name of its scheme, the form the CLI stores them in.
+/// Files without a name predate it and belong to `CpAbe`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemeKey<T> {
+    #[serde(default = "default_scheme")]
+    pub scheme: String,
+    #[serde(flatten)]
+    pub key: T,
+}
+
+impl<T> SchemeKey<T> {
+    /// Records the name of scheme `S` with a key or cipher text of it
+    pub fn new<S: AbeScheme>(key: T) -> Self {
+        SchemeKey {
+            scheme: S::NAME.to_string(),
+            key,
+        }
+    }
+}
+
+impl<T: Armored> Armored for SchemeKey<T> {
+    const LABEL: &'static str = T::LABEL;
+}
+
+fn default_scheme() -> String {
+    CpAbe::NAME.to_string()
+}
+
+/// The ciphertext-policy scheme of `crypto`, with attributes registered at setup
+pub struct CpAbe;
+
+/// The key-policy scheme of `kp_abe`, on the keys of `crypto`
+pub struct KpAbe;
+
+/// The large-universe scheme of `lu_abe`
+pub struct LuAbe;
+
+/// The Waters (2011) scheme of `waters`
+pub struct WatersAbe;
+
+/// The multi-authority scheme of `ma_abe` with a single authority per setup. Setups share the
+/// global parameters, so keys that separate authorities issued for a user can be combined with
+/// `ma_abe::combine`, encrypting under the policy of several authorities takes `ma_abe::encrypt`.
+pub struct MaAbe;
+
+/// The online/offline encryption of `online`, on the keys of `crypto`. Every encryption does its
+/// offline phase right before the online one, devices that precompute ahead of time use
+/// `online::precompute_pool` instead.
+pub struct OnlineAbe;
+
+/// The partially hidden policies of `hidden`, on the keys of `crypto`
+pub struct HiddenAbe;
+
+/// The scheme of `crypto` with outsourced decryption, see `crypto::transform`. Its secret keys are
+/// transformation keys with their retrieval keys, decryption does both the transformation and
+/// its completion.
+pub struct OutsourcedAbe;
+
+/// Secret key request of `MaAbe`: the global identifier of the user followed by the attributes of
+/// the authority, by their qualified names
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaKeyInput {
+    pub gid: String,
+    pub attributes: Vec<String>,
+}
+
+impl SchemeInput for MaKeyInput {
+    fn parse(values: &[String]) -> Result<Self, AbeError> {
+        let mut attributes = Vec::<String>::parse(values)?;
+        let gid = attributes.remove(0);
+        if attributes.is_empty() {
+            return Err(AbeError::new("No attributes given"));
+        }
+        Ok(MaKeyInput { gid, attributes })
+    }
+}
+
+impl AbeScheme for CpAbe {
+    const NAME: &'static str = "cp";
+
+    type PublicKey = AbePublicKey;
+    type MasterKey = AbeMasterKey;
+    type SecretKey = AbeSecretKey;
+    type CipherText = AbeCipherText;
+    type KeyInput = Vec<String>;
+    type EncryptInput = AccessTree;
+
+    fn setup<R: Rng + ?Sized>(
+        attributes: &[String],
+        rng: &mut R,
+    ) -> Result<(AbePublicKey, AbeMasterKey), AbeError> {
+        if attributes.is_empty() {
+            return Err(AbeError::new("No attributes given"));
+        }
+        Ok(crypto::setup(attributes, G1::one(), G2::one(), rng))
+    }
+
+    fn keygen<R: Rng + ?Sized>(
+        attributes: &Vec<String>,
+        public_key: &AbePublicKey,
+        master_key: &AbeMasterKey,
+        rng: &mut R,
+    ) -> Result<AbeSecretKey, AbeError> {
+        crypto::keygen(attributes, public_key, master_key, rng)
+    }
+
+    fn keygen_delegable<R: Rng + ?Sized>(
+        attributes: &Vec<String>,
+        public_key: &AbePublicKey,
+        master_key: &AbeMasterKey,
+        rng: &mut R,
+    ) -> Result<AbeSecretKey, AbeError> {
+        crypto::keygen_delegable(attributes, public_key, master_key, rng)
+    }
+
+    fn delegate<R: Rng + ?Sized>(
+        attributes: &Vec<String>,
+        public_key: &AbePublicKey,
+        secret_key: &AbeSecretKey,
+        rng: &mut R,
+    ) -> Result<AbeSecretKey, AbeError> {
+        crypto::delegate(attributes, public_key, secret_key, rng)
+    }
+
+    fn encrypt<R: Rng + ?Sized>(
+        dem: &dyn DataEncapsulation,
+        secret: &Gt,
+        message: &[u8],
+        public_key: &AbePublicKey,
+        access_tree: &AccessTree,
+        rng: &mut R,
+    ) -> Result<AbeCipherText, AbeError> {
+        crypto::encrypt_with(dem, secret, message, public_key, access_tree, rng)
+    }
+
+    fn validate(dems: &dyn DemResolver, cipher_text: &AbeCipherText) -> Result<(), AbeError> {
+        cipher_text.validate(dems).map(|_| ())
+    }
+
+    fn decrypt_with(
+        dems: &dyn DemResolver,
+        cipher_text: &AbeCipherText,
+        secret_key: &AbeSecretKey,
+    ) -> Result<AbeDecrypted, AbeError> {
+        crypto::decrypt_in_place(dems, cipher_text.clone(), secret_key)
+    }
+
+    fn rewrap<R: Rng + ?Sized>(
+        dems: &dyn DemResolver,
+        cipher_text: AbeCipherText,
+        secret_key: &AbeSecretKey,
+        public_key: &AbePublicKey,
+        access_tree: &AccessTree,
+        rng: &mut R,
+    ) -> Result<AbeEnvelope, AbeError> {
+        crypto::rewrap(dems, cipher_text, secret_key, public_key, access_tree, rng)
+    }
+}
+
+impl AbeScheme for KpAbe {
+    const NAME: &'static str = "kp";
+
+    type PublicKey = AbePublicKey;
+    type MasterKey = AbeMasterKey;
+    type SecretKey = AbeKpSecretKey;
+    type CipherText = AbeKpCipherText;
+    type KeyInput = AccessTree;
+    type EncryptInput = Vec<String>;
+
+    fn setup<R: Rng + ?Sized>(
+        attributes: &[String],
+        rng: &mut R,
+    ) -> Result<(AbePublicKey, AbeMasterKey), AbeError> {
+        CpAbe::setup(attributes, rng)
+    }
+
+    fn keygen<R: Rng + ?Sized>(
+        access_tree: &AccessTree,
+        public_key: &AbePublicKey,
+        master_key: &AbeMasterKey,
+        rng: &mut R,
+    ) -> Result<AbeKpSecretKey, AbeError> {
+        kp_abe::keygen(access_tree, public_key, master_key, rng)
+    }
+
+    fn encrypt<R: Rng + ?Sized>(
+        dem: &dyn DataEncapsulation,
+        secret: &Gt,
+        message: &[u8],
+        public_key: &AbePublicKey,
+        attributes: &Vec<String>,
+        rng: &mut R,
+    ) -> Result<AbeKpCipherText, AbeError> {
+        kp_abe::encrypt_with(dem, secret, message, public_key, attributes, rng)
+    }
+
+    fn validate(dems: &dyn DemResolver, cipher_text: &AbeKpCipherText) -> Result<(), AbeError> {
+        cipher_text.validate(dems).map(|_| ())
+    }
+
+    fn decrypt_with(
+        dems: &dyn DemResolver,
+        cipher_text: &AbeKpCipherText,
+        secret_key: &AbeKpSecretKey,
+    ) -> Result<AbeDecrypted, AbeError> {
+        kp_abe::decrypt_with(dems, cipher_text, secret_key)
+    }
+}
+
+impl AbeScheme for LuAbe {
+    const NAME: &'static str = "lu";
+
+    type PublicKey = AbeLuPublicKey;
+    type MasterKey = AbeLuMasterKey;
+    type SecretKey = AbeLuSecretKey;
+    type CipherText = AbeLuCipherText;
+    type KeyInput = Vec<String>;
+    type EncryptInput = AccessTree;
+
+    fn setup<R: Rng + ?Sized>(
+        _attributes: &[String],
+        rng: &mut R,
+    ) -> Result<(AbeLuPublicKey, AbeLuMasterKey), AbeError> {
+        Ok(lu_abe::setup(G1::one(), G2::one(), rng))
+    }
+
+    fn keygen<R: Rng + ?Sized>(
+        attributes: &Vec<String>,
+        public_key: &AbeLuPublicKey,
+        master_key: &AbeLuMasterKey,
+        rng: &mut R,
+    ) -> Result<AbeLuSecretKey, AbeError> {
+        lu_abe::keygen(attributes, public_key, master_key, rng)
+    }
+
+    fn encrypt<R: Rng + ?Sized>(
+        dem: &dyn DataEncapsulation,
+        secret: &Gt,
+        message: &[u8],
+        public_key: &AbeLuPublicKey,
+        access_tree: &AccessTree,
+        rng: &mut R,
+    ) -> Result<AbeLuCipherText, AbeError> {
+        lu_abe::encrypt_with(dem, secret, message, public_key, access_tree, rng)
+    }
+
+    fn validate(dems: &dyn DemResolver, cipher_text: &AbeLuCipherText) -> Result<(), AbeError> {
+        cipher_text.validate(dems).map(|_| ())
+    }
+
+    fn decrypt_with(
+        dems: &dyn DemResolver,
+        cipher_text: &AbeLuCipherText,
+        secret_key: &AbeLuSecretKey,
+    ) -> Result<AbeDecrypted, AbeError> {
+        lu_abe::decrypt_with(dems, cipher_text, secret_key)
+    }
+}
+
+impl AbeScheme for WatersAbe {
+    const NAME: &'static str = "waters";
+
+    type PublicKey = AbeWatersPublicKey;
+    type MasterKey = AbeWatersMasterKey;
+    type SecretKey = AbeWatersSecretKey;
+    type CipherText = AbeWatersCipherText;
+    type KeyInput = Vec<String>;
+    type EncryptInput = AccessTree;
+
+    fn setup<R: Rng + ?Sized>(
+        _attributes: &[String],
+        rng: &mut R,
+    ) -> Result<(AbeWatersPublicKey, AbeWatersMasterKey), AbeError> {
+        Ok(waters::setup(G1::one(), G2::one(), rng))
+    }
+
+    fn keygen<R: Rng + ?Sized>(
+        attributes: &Vec<String>,
+        public_key: &AbeWatersPublicKey,
+        master_key: &AbeWatersMasterKey,
+        rng: &mut R,
+    ) -> Result<AbeWatersSecretKey, AbeError> {
+        waters::keygen(attributes, public_key, master_key, rng)
+    }
+
+    fn encrypt<R: Rng + ?Sized>(
+        dem: &dyn DataEncapsulation,
+        secret: &Gt,
+        message: &[u8],
+        public_key: &AbeWatersPublicKey,
+        access_tree: &AccessTree,
+        rng: &mut R,
+    ) -> Result<AbeWatersCipherText, AbeError> {
+        waters::encrypt_with(dem, secret, message, public_key, access_tree, rng)
+    }
+
+    fn validate(dems: &dyn DemResolver, cipher_text: &AbeWatersCipherText) -> Result<(), AbeError> {
+        cipher_text.validate(dems).map(|_| ())
+    }
+
+    fn decrypt_with(
+        dems: &dyn DemResolver,
+        cipher_text: &AbeWatersCipherText,
+        secret_key: &AbeWatersSecretKey,
+    ) -> Result<AbeDecrypted, AbeError> {
+        waters::decrypt_with(dems, cipher_text, secret_key)
+    }
+}
+
+impl AbeScheme for MaAbe {
+    const NAME: &'static str = "ma";
+
+    type PublicKey = AbeMaPublicKey;
+    type MasterKey = AbeMaMasterKey;
+    type SecretKey = AbeMaSecretKey;
+    type CipherText = AbeMaCipherText;
+    type KeyInput = MaKeyInput;
+    type EncryptInput = AccessTree;
+
+    /// Sets up one authority, the attributes are qualified with its name, e.g. "hr:manager"
+    fn setup<R: Rng + ?Sized>(
+        attributes: &[String],
+        rng: &mut R,
+    ) -> Result<(AbeMaPublicKey, AbeMaMasterKey), AbeError> {
+        let mut authority = None;
+        let mut names = Vec::new();
+        for attribute in attributes {
+            let (qualifier, name) = attribute.split_once(':').ok_or(AbeError::new(
+                format!(
+                    "Attribute {:?} is not qualified with an authority",
+                    attribute
+                )
+                .as_str(),
+            ))?;
+            if *authority.get_or_insert(qualifier) != qualifier {
+                return Err(AbeError::new("Attributes of more than one authority given"));
+            }
+            names.push(name.to_string());
+        }
+        let authority = authority.ok_or(AbeError::new("No attributes given"))?;
+        ma_abe::authority_setup(&ma_parameters(), authority, &names, rng)
+    }
+
+    fn keygen<R: Rng + ?Sized>(
+        input: &MaKeyInput,
+        _public_key: &AbeMaPublicKey,
+        master_key: &AbeMaMasterKey,
+        _rng: &mut R,
+    ) -> Result<AbeMaSecretKey, AbeError> {
+        ma_abe::keygen(&input.gid, &input.attributes, &ma_parameters(), master_key)
+    }
+
+    fn encrypt<R: Rng + ?Sized>(
+        dem: &dyn DataEncapsulation,
+        secret: &Gt,
+        message: &[u8],
+        public_key: &AbeMaPublicKey,
+        access_tree: &AccessTree,
+        rng: &mut R,
+    ) -> Result<AbeMaCipherText, AbeError> {
+        ma_abe::encrypt_with(
+            dem,
+            secret,
+            message,
+            &ma_parameters(),
+            std::slice::from_ref(public_key),
+            access_tree,
+            rng,
+        )
+    }
+
+    fn validate(dems: &dyn DemResolver, cipher_text: &AbeMaCipherText) -> Result<(), AbeError> {
+        cipher_text.validate(dems).map(|_| ())
+    }
+
+    fn decrypt_with(
+        dems: &dyn DemResolver,
+        cipher_text: &AbeMaCipherText,
+        secret_key: &AbeMaSecretKey,
+    ) -> Result<AbeDecrypted, AbeError> {
+        ma_abe::decrypt_with(dems, cipher_text, secret_key)
+    }
+}
+
+// the global parameters of every setup of `MaAbe`
+fn ma_parameters() -> AbeMaGlobalParameters {
+    ma_abe::global_setup(G1::one(), G2::one())
+}
+
+impl AbeScheme for OnlineAbe {
+    const NAME: &'static str = "online";
+
+    type PublicKey = AbePublicKey;
+    type MasterKey = AbeMasterKey;
+    type SecretKey = AbeSecretKey;
+    type CipherText = AbeOnlineCipherText;
+    type KeyInput = Vec<String>;
+    type EncryptInput = AccessTree;
+
+    fn setup<R: Rng + ?Sized>(
+        attributes: &[String],
+        rng: &mut R,
+    ) -> Result<(AbePublicKey, AbeMasterKey), AbeError> {
+        CpAbe::setup(attributes, rng)
+    }
+
+    fn keygen<R: Rng + ?Sized>(
+        attributes: &Vec<String>,
+        public_key: &AbePublicKey,
+        master_key: &AbeMasterKey,
+        rng: &mut R,
+    ) -> Result<AbeSecretKey, AbeError> {
+        CpAbe::keygen(attributes, public_key, master_key, rng)
+    }
+
+    fn keygen_delegable<R: Rng + ?Sized>(
+        attributes: &Vec<String>,
+        public_key: &AbePublicKey,
+        master_key: &AbeMasterKey,
+        rng: &mut R,
+    ) -> Result<AbeSecretKey, AbeError> {
+        CpAbe::keygen_delegable(attributes, public_key, master_key, rng)
+    }
+
+    fn delegate<R: Rng + ?Sized>(
+        attributes: &Vec<String>,
+        public_key: &AbePublicKey,
+        secret_key: &AbeSecretKey,
+        rng: &mut R,
+    ) -> Result<AbeSecretKey, AbeError> {
+        CpAbe::delegate(attributes, public_key, secret_key, rng)
+    }
+
+    fn encrypt<R: Rng + ?Sized>(
+        dem: &dyn DataEncapsulation,
+        secret: &Gt,
+        message: &[u8],
+        public_key: &AbePublicKey,
+        access_tree: &AccessTree,
+        rng: &mut R,
+    ) -> Result<AbeOnlineCipherText, AbeError> {
+        // pieces for as many leaves per attribute as the policy has
+        let mut leaves = BTreeMap::new();
+        for leaf in access_tree.get_attributes() {
+            *leaves.entry(leaf.name).or_insert(0) += 1;
+        }
+        let leaves_per_attribute = leaves.into_values().max().unwrap_or(1);
+
+        let intermediate = online::precompute(public_key, leaves_per_attribute, rng);
+        online::encrypt_with(
+            dem,
+            intermediate,
+            secret,
+            message,
+            public_key,
+            access_tree,
+            rng,
+        )
+    }
+
+    fn validate(dems: &dyn DemResolver, cipher_text: &AbeOnlineCipherText) -> Result<(), AbeError> {
+        cipher_text.validate(dems).map(|_| ())
+    }
+
+    fn decrypt_with(
+        dems: &dyn DemResolver,
+        cipher_text: &AbeOnlineCipherText,
+        secret_key: &AbeSecretKey,
+    ) -> Result<AbeDecrypted, AbeError> {
+        online::decrypt_with(dems, cipher_text, secret_key)
+    }
+}
+
+impl AbeScheme for HiddenAbe {
+    const NAME: &'static str = "hidden";
+
+    type PublicKey = AbePublicKey;
+    type MasterKey = AbeMasterKey;
+    type SecretKey = AbeSecretKey;
+    type CipherText = AbeHiddenCipherText;
+    type KeyInput = Vec<String>;
+    type EncryptInput = AccessTree;
+
+    fn setup<R: Rng + ?Sized>(
+        attributes: &[String],
+        rng: &mut R,
+    ) -> Result<(AbePublicKey, AbeMasterKey), AbeError> {
+        CpAbe::setup(attributes, rng)
+    }
+
+    fn keygen<R: Rng + ?Sized>(
+        attributes: &Vec<String>,
+        public_key: &AbePublicKey,
+        master_key: &AbeMasterKey,
+        rng: &mut R,
+    ) -> Result<AbeSecretKey, AbeError> {
+        CpAbe::keygen(attributes, public_key, master_key, rng)
+    }
+
+    fn keygen_delegable<R: Rng + ?Sized>(
+        attributes: &Vec<String>,
+        public_key: &AbePublicKey,
+        master_key: &AbeMasterKey,
+        rng: &mut R,
+    ) -> Result<AbeSecretKey, AbeError> {
+        CpAbe::keygen_delegable(attributes, public_key, master_key, rng)
+    }
+
+    fn delegate<R: Rng + ?Sized>(
+        attributes: &Vec<String>,
+        public_key: &AbePublicKey,
+        secret_key: &AbeSecretKey,
+        rng: &mut R,
+    ) -> Result<AbeSecretKey, AbeError> {
+        CpAbe::delegate(attributes, public_key, secret_key, rng)
+    }
+
+    fn encrypt<R: Rng + ?Sized>(
+        dem: &dyn DataEncapsulation,
+        secret: &Gt,
+        message: &[u8],
+        public_key: &AbePublicKey,
+        access_tree: &AccessTree,
+        rng: &mut R,
+    ) -> Result<AbeHiddenCipherText, AbeError> {
+        hidden::encrypt_with(dem, secret, message, public_key, access_tree, rng)
+    }
+
+    fn validate(dems: &dyn DemResolver, cipher_text: &AbeHiddenCipherText) -> Result<(), AbeError> {
+        cipher_text.validate(dems).map(|_| ())
+    }
+
+    fn decrypt_with(
+        dems: &dyn DemResolver,
+        cipher_text: &AbeHiddenCipherText,
+        secret_key: &AbeSecretKey,
+    ) -> Result<AbeDecrypted, AbeError> {
+        hidden::decrypt_with(dems, cipher_text, secret_key)
+    }
+}
+
+impl AbeScheme for OutsourcedAbe {
+    const NAME: &'static str = "outsourced";
+
+    type PublicKey = AbePublicKey;
+    type MasterKey = AbeMasterKey;
+    type SecretKey = AbeOutsourcedKey;
+    type CipherText = AbeCipherText;
+    type KeyInput = Vec<String>;
+    type EncryptInput = AccessTree;
+
+    fn setup<R: Rng + ?Sized>(
+        attributes: &[String],
+        rng: &mut R,
+    ) -> Result<(AbePublicKey, AbeMasterKey), AbeError> {
+        CpAbe::setup(attributes, rng)
+    }
+
+    fn keygen<R: Rng + ?Sized>(
+        attributes: &Vec<String>,
+        public_key: &AbePublicKey,
+        master_key: &AbeMasterKey,
+        rng: &mut R,
+    ) -> Result<AbeOutsourcedKey, AbeError> {
+        let secret_key = CpAbe::keygen(attributes, public_key, master_key, rng)?;
+        let (transformation_key, retrieval_key) = crypto::transformation_key(&secret_key, rng)?;
+        Ok(AbeOutsourcedKey {
+            transformation_key,
+            retrieval_key,
+        })
+    }
+
+    fn encrypt<R: Rng + ?Sized>(
+        dem: &dyn DataEncapsulation,
+        secret: &Gt,
+        message: &[u8],
+        public_key: &AbePublicKey,
+        access_tree: &AccessTree,
+        rng: &mut R,
+    ) -> Result<AbeCipherText, AbeError> {
+        CpAbe::encrypt(dem, secret, message, public_key, access_tree, rng)
+    }
+
+    fn validate(dems: &dyn DemResolver, cipher_text: &AbeCipherText) -> Result<(), AbeError> {
+        cipher_text.validate(dems).map(|_| ())
+    }
+
+    fn decrypt_with(
+        dems: &dyn DemResolver,
+        cipher_text: &AbeCipherText,
+        secret_key: &AbeOutsourcedKey,
+    ) -> Result<AbeDecrypted, AbeError> {
+        // the transformation is what a server would do with the transformation key
+        let transformed = crypto::transform(&cipher_text.header, &secret_key.transformation_key)?;
+        crypto::decrypt_transformed(
+            dems,
+            cipher_text.clone(),
+            &transformed,
+            &secret_key.retrieval_key,
+        )
+    }
+}
@@ -0,0 +1,667 @@
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use rand::Rng;
+
+use crate::dem::DataEncapsulation;
+use crate::errors::symmetric_encryption_error::SymmetricEncryptionError;
+
+/// Plaintext bytes per segment used when none is requested
+pub const DEFAULT_SEGMENT_SIZE: usize = 64 * 1024;
+
+// STREAM construction (Hoang, Reyhanitabar, Rogaway, Vizár 2015): the payload is
+// `[nonce prefix|segment 0|segment 1|...]` where every segment holds `segment_size` plaintext bytes
+// except the last, which holds fewer (possibly none). Each segment is sealed under the nonce
+// `[prefix|counter (u32 big endian)|last flag]`, so segments cannot be reordered, dropped or
+// appended and the stream cannot be truncated at a segment boundary.
+pub(crate) fn segment_nonce(prefix: &[u8], counter: u32, last: bool) -> Vec<u8> {
+    let mut nonce = Vec::with_capacity(prefix.len() + SEGMENT_SUFFIX_SIZE);
+    nonce.extend_from_slice(prefix);
+    nonce.extend_from_slice(&counter.to_be_bytes());
+    nonce.push(last as u8);
+    nonce
+}
+
+// counter and last flag at the end of every segment nonce
+const SEGMENT_SUFFIX_SIZE: usize = 5;
+
+/// Size of the random nonce prefix written at the start of every stream, the nonce of the
+/// algorithm without the counter and last flag of a segment
+pub fn nonce_prefix_size(dem: &dyn DataEncapsulation) -> usize {
+    dem.nonce_size().saturating_sub(SEGMENT_SUFFIX_SIZE)
+}
+
+/// Number of bytes a stream of `plaintext_length` bytes occupies once encrypted
+pub fn encrypted_length(
+    dem: &dyn DataEncapsulation,
+    segment_size: usize,
+    plaintext_length: u64,
+) -> u64 {
+    let segments = plaintext_length / segment_size as u64 + 1;
+    nonce_prefix_size(dem) as u64 + plaintext_length + segments * dem.tag_size() as u64
+}
+
+fn to_io_error(error: SymmetricEncryptionError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+/// Encrypts everything written to it in segments and writes the result to the inner writer.
+///
+/// `finish` has to be called to write the last segment, without it the stream is truncated
+/// and will not decrypt.
+pub struct StreamEncryptor<'a, W: Write> {
+    dem: &'a dyn DataEncapsulation,
+    key: Vec<u8>,
+    nonce_prefix: Vec<u8>,
+    counter: u32,
+    segment_size: usize,
+    buffer: Vec<u8>,
+    inner: W,
+}
+
+impl<'a, W: Write> StreamEncryptor<'a, W> {
+    pub fn new<R: Rng + ?Sized>(
+        dem: &'a dyn DataEncapsulation,
+        key: &[u8],
+        segment_size: usize,
+        mut inner: W,
+        rng: &mut R,
+    ) -> io::Result<StreamEncryptor<'a, W>> {
+        check_segment_size(dem, segment_size).map_err(to_input_error)?;
+
+        let mut nonce_prefix = vec![0u8; nonce_prefix_size(dem)];
+        rng.fill(nonce_prefix.as_mut_slice());
+        inner.write_all(&nonce_prefix)?;
+
+        Ok(StreamEncryptor {
+            dem,
+            key: key.to_vec(),
+            nonce_prefix,
+            counter: 0,
+            segment_size,
+            buffer: Vec::with_capacity(segment_size + dem.tag_size()),
+            inner,
+        })
+    }
+
+    fn write_segment(&mut self, last: bool) -> io::Result<()> {
+        let nonce = segment_nonce(&self.nonce_prefix, self.counter, last);
+        self.dem
+            .seal_in_place(&self.key, &nonce, &mut self.buffer)
+            .map_err(to_io_error)?;
+        self.inner.write_all(&self.buffer)?;
+        self.buffer.clear();
+
+        self.counter = self.counter.checked_add(1).ok_or(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Stream has too many segments",
+        ))?;
+        Ok(())
+    }
+
+    /// Writes the last segment and returns the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_segment(true)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for StreamEncryptor<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let length = buf.len().min(self.segment_size - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..length]);
+
+        // full segments are written right away, so the last segment written by `finish` is
+        // always shorter than `segment_size` and can be told apart when decrypting
+        if self.buffer.len() == self.segment_size {
+            self.write_segment(false)?;
+        }
+        Ok(length)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts a stream written by `StreamEncryptor`, authenticating every segment before any of
+/// its plaintext is returned.
+pub struct StreamDecryptor<'a, R: Read> {
+    dem: &'a dyn DataEncapsulation,
+    key: Vec<u8>,
+    nonce_prefix: Vec<u8>,
+    counter: u32,
+    segment_size: usize,
+    plaintext: Vec<u8>,
+    position: usize,
+    finished: bool,
+    inner: R,
+}
+
+impl<'a, R: Read> StreamDecryptor<'a, R> {
+    pub fn new(
+        dem: &'a dyn DataEncapsulation,
+        key: &[u8],
+        segment_size: usize,
+        mut inner: R,
+    ) -> io::Result<StreamDecryptor<'a, R>> {
+        check_segment_size(dem, segment_size).map_err(to_input_error)?;
+
+        let mut nonce_prefix = vec![0u8; nonce_prefix_size(dem)];
+        inner.read_exact(&mut nonce_prefix)?;
+
+        Ok(StreamDecryptor {
+            dem,
+            key: key.to_vec(),
+            nonce_prefix,
+            counter: 0,
+            segment_size,
+            plaintext: Vec::new(),
+            position: 0,
+            finished: false,
+            inner,
+        })
+    }
+
+    fn read_segment(&mut self) -> io::Result<()> {
+        // the segment is decrypted in the buffer it was read to, which is emptied first so
+        // nothing of a segment that fails to authenticate can be read
+        let full_length = self.segment_size + self.dem.tag_size();
+        self.plaintext.clear();
+        self.plaintext.resize(full_length, 0);
+        self.position = 0;
+
+        let mut length = 0;
+        while length < full_length {
+            match self.inner.read(&mut self.plaintext[length..]) {
+                Ok(0) => break,
+                Ok(n) => length += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.plaintext.clear();
+                    return Err(e);
+                }
+            }
+        }
+        self.plaintext.truncate(length);
+
+        // only the last segment is shorter than a full one
+        let last = length < full_length;
+        if length == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Stream is truncated",
+            ));
+        }
+
+        let nonce = segment_nonce(&self.nonce_prefix, self.counter, last);
+        if let Err(e) = self
+            .dem
+            .open_in_place(&self.key, &nonce, &mut self.plaintext)
+        {
+            self.plaintext.clear();
+            return Err(to_io_error(e));
+        }
+        self.finished = last;
+        self.counter = self.counter.checked_add(1).ok_or(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Stream has too many segments",
+        ))?;
+        Ok(())
+    }
+
+    /// Returns the inner reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for StreamDecryptor<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.plaintext.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.read_segment()?;
+        }
+
+        let length = buf.len().min(self.plaintext.len() - self.position);
+        buf[..length].copy_from_slice(&self.plaintext[self.position..self.position + length]);
+        self.position += length;
+        Ok(length)
+    }
+}
+
+/// Decrypts a stream written by `StreamEncryptor` at arbitrary positions. Only the segments
+/// holding the requested bytes are read and authenticated, plus the last segment once when the
+/// decryptor is created, which authenticates the length of the stream.
+///
+/// The inner reader has to be positioned at the start of the stream, which extends to its end.
+pub struct SeekableDecryptor<'a, R: Read + Seek> {
+    dem: &'a dyn DataEncapsulation,
+    key: Vec<u8>,
+    nonce_prefix: Vec<u8>,
+    segment_size: usize,
+    start: u64,
+    last_segment: u32,
+    length: u64,
+    position: u64,
+    segment: Option<u32>,
+    plaintext: Vec<u8>,
+    inner: R,
+}
+
+impl<'a, R: Read + Seek> SeekableDecryptor<'a, R> {
+    pub fn new(
+        dem: &'a dyn DataEncapsulation,
+        key: &[u8],
+        segment_size: usize,
+        mut inner: R,
+    ) -> io::Result<SeekableDecryptor<'a, R>> {
+        check_segment_size(dem, segment_size).map_err(to_input_error)?;
+
+        let start = inner.stream_position()?;
+        let mut nonce_prefix = vec![0u8; nonce_prefix_size(dem)];
+        inner.read_exact(&mut nonce_prefix)?;
+
+        // every segment but the last is full, the last one holds at least its tag
+        let encrypted = inner.seek(SeekFrom::End(0))? - start - nonce_prefix.len() as u64;
+        let full_length = (segment_size + dem.tag_size()) as u64;
+        let last_length = encrypted % full_length;
+        if last_length < dem.tag_size() as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Stream is truncated",
+            ));
+        }
+        let last_segment = u32::try_from(encrypted / full_length).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, "Stream has too many segments")
+        })?;
+
+        let mut decryptor = SeekableDecryptor {
+            dem,
+            key: key.to_vec(),
+            nonce_prefix,
+            segment_size,
+            start,
+            last_segment,
+            length: last_segment as u64 * segment_size as u64 + last_length - dem.tag_size() as u64,
+            position: 0,
+            segment: None,
+            plaintext: Vec::new(),
+            inner,
+        };
+        decryptor.load_segment(last_segment)?;
+        Ok(decryptor)
+    }
+
+    /// Number of plaintext bytes in the stream
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    /// Whether the stream holds no plaintext
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Returns the inner reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn load_segment(&mut self, index: u32) -> io::Result<()> {
+        if self.segment == Some(index) {
+            return Ok(());
+        }
+
+        // the buffer is emptied first, so nothing of a segment that fails to authenticate can
+        // be read
+        let full_length = self.segment_size + self.dem.tag_size();
+        let last = index == self.last_segment;
+        let length = if last {
+            (self.length - index as u64 * self.segment_size as u64) as usize + self.dem.tag_size()
+        } else {
+            full_length
+        };
+        self.segment = None;
+        self.plaintext.clear();
+        self.plaintext.resize(length, 0);
+
+        self.inner.seek(SeekFrom::Start(
+            self.start + self.nonce_prefix.len() as u64 + index as u64 * full_length as u64,
+        ))?;
+        if let Err(e) = self.inner.read_exact(&mut self.plaintext) {
+            self.plaintext.clear();
+            return Err(e);
+        }
+
+        let nonce = segment_nonce(&self.nonce_prefix, index, last);
+        if let Err(e) = self
+            .dem
+            .open_in_place(&self.key, &nonce, &mut self.plaintext)
+        {
+            self.plaintext.clear();
+            return Err(to_io_error(e));
+        }
+        self.segment = Some(index);
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Read for SeekableDecryptor<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.length || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let index = (self.position / self.segment_size as u64) as u32;
+        self.load_segment(index)?;
+
+        let offset = (self.position - index as u64 * self.segment_size as u64) as usize;
+        let length = buf.len().min(self.plaintext.len() - offset);
+        buf[..length].copy_from_slice(&self.plaintext[offset..offset + length]);
+        self.position += length as u64;
+        Ok(length)
+    }
+}
+
+impl<R: Read + Seek> Seek for SeekableDecryptor<'_, R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        }
+        .ok_or(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid seek to a negative or overflowing position",
+        ))?;
+
+        // positions past the end are allowed, reading there returns nothing
+        self.position = position;
+        Ok(position)
+    }
+}
+
+/// Encrypts a plaintext held in memory into the segmented format
+pub fn encrypt_segmented<R: Rng + ?Sized>(
+    dem: &dyn DataEncapsulation,
+    key: &[u8],
+    segment_size: usize,
+    plaintext: &[u8],
+    rng: &mut R,
+) -> Result<Vec<u8>, SymmetricEncryptionError> {
+    let mut buffer = plaintext.to_vec();
+    encrypt_segmented_in_place(dem, key, segment_size, &mut buffer, rng)?;
+    Ok(buffer)
+}
+
+/// Decrypts a segmented cipher text held in memory
+pub fn decrypt_segmented(
+    dem: &dyn DataEncapsulation,
+    key: &[u8],
+    segment_size: usize,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, SymmetricEncryptionError> {
+    let mut buffer = ciphertext.to_vec();
+    decrypt_segmented_in_place(dem, key, segment_size, &mut buffer)?;
+    Ok(buffer)
+}
+
+/// Like `encrypt_segmented`, replacing the plaintext in `buffer` with the cipher text
+pub fn encrypt_segmented_in_place<R: Rng + ?Sized>(
+    dem: &dyn DataEncapsulation,
+    key: &[u8],
+    segment_size: usize,
+    buffer: &mut Vec<u8>,
+    rng: &mut R,
+) -> Result<(), SymmetricEncryptionError> {
+    check_segment_size(dem, segment_size)?;
+    let prefix_size = nonce_prefix_size(dem);
+    let length = buffer.len();
+    let tag_size = dem.tag_size();
+    let segments = length / segment_size + 1;
+    let segment_count = u32::try_from(segments)
+        .map_err(|_| SymmetricEncryptionError::new("Stream has too many segments"))?;
+
+    // move the segments to their final position back to front, so none is overwritten before
+    // it was moved
+    buffer.resize(
+        encrypted_length(dem, segment_size, length as u64) as usize,
+        0,
+    );
+    for i in (0..segments).rev() {
+        let start = i * segment_size;
+        let end = length.min(start + segment_size);
+        buffer.copy_within(start..end, prefix_size + i * (segment_size + tag_size));
+    }
+
+    let (nonce_prefix, mut rest) = buffer.split_at_mut(prefix_size);
+    rng.fill(&mut *nonce_prefix);
+    for counter in 0..segment_count {
+        let last = counter == segment_count - 1;
+        let data_length = if last {
+            length % segment_size
+        } else {
+            segment_size
+        };
+        let (segment, next) = rest.split_at_mut(data_length + tag_size);
+        let (data, tag) = segment.split_at_mut(data_length);
+        let nonce = segment_nonce(nonce_prefix, counter, last);
+        dem.seal_detached(key, &nonce, data, tag)?;
+        rest = next;
+    }
+    Ok(())
+}
+
+/// Like `decrypt_segmented`, replacing the cipher text in `buffer` with the plaintext
+pub fn decrypt_segmented_in_place(
+    dem: &dyn DataEncapsulation,
+    key: &[u8],
+    segment_size: usize,
+    buffer: &mut Vec<u8>,
+) -> Result<(), SymmetricEncryptionError> {
+    check_segment_size(dem, segment_size)?;
+    let prefix_size = nonce_prefix_size(dem);
+    if buffer.len() < prefix_size {
+        return Err(SymmetricEncryptionError::new("Stream is truncated"));
+    }
+
+    let full_length = segment_size + dem.tag_size();
+    let nonce_prefix = buffer[..prefix_size].to_vec();
+
+    // every segment is decrypted where it is and then moved behind the previous plaintext
+    let mut read = prefix_size;
+    let mut written = 0;
+    let mut counter: u32 = 0;
+    loop {
+        let length = full_length.min(buffer.len() - read);
+        if length == 0 {
+            return Err(SymmetricEncryptionError::new("Stream is truncated"));
+        }
+        let data_length = length
+            .checked_sub(dem.tag_size())
+            .ok_or(SymmetricEncryptionError::new("Stream is truncated"))?;
+
+        // only the last segment is shorter than a full one
+        let last = length < full_length;
+        let nonce = segment_nonce(&nonce_prefix, counter, last);
+        let (data, tag) = buffer[read..read + length].split_at_mut(data_length);
+        dem.open_detached(key, &nonce, data, tag)?;
+        buffer.copy_within(read..read + data_length, written);
+
+        read += length;
+        written += data_length;
+        if last {
+            break;
+        }
+        counter = counter.checked_add(1).ok_or(SymmetricEncryptionError::new(
+            "Stream has too many segments",
+        ))?;
+    }
+
+    buffer.truncate(written);
+    Ok(())
+}
+
+fn to_input_error(error: SymmetricEncryptionError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, error.to_string())
+}
+
+fn check_segment_size(
+    dem: &dyn DataEncapsulation,
+    segment_size: usize,
+) -> Result<(), SymmetricEncryptionError> {
+    if segment_size == 0 {
+        return Err(SymmetricEncryptionError::new(
+            "Segment size must be positive",
+        ));
+    }
+    // the counter and last flag need room in the nonce
+    if dem.nonce_size() < SEGMENT_SUFFIX_SIZE {
+        return Err(SymmetricEncryptionError::new(
+            format!(
+                "Nonces of {} are too short for segmented payloads",
+                dem.algorithm()
+            )
+            .as_str(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dem::{Aes256Gcm, DataEncapsulation};
+    use std::io::{Cursor, Read, Seek, SeekFrom};
+
+    use crate::stream::{
+        decrypt_segmented, decrypt_segmented_in_place, encrypt_segmented,
+        encrypt_segmented_in_place, encrypted_length, SeekableDecryptor, StreamDecryptor,
+    };
+
+    const KEY: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn test_roundtrip_lengths() {
+        let rng = &mut rand::thread_rng();
+        for length in [0, 1, 15, 16, 17, 32, 100] {
+            let plaintext = (0..length).map(|i| i as u8).collect::<Vec<u8>>();
+            let ciphertext = encrypt_segmented(&Aes256Gcm, &KEY, 16, &plaintext, rng).unwrap();
+
+            assert_eq!(
+                ciphertext.len() as u64,
+                encrypted_length(&Aes256Gcm, 16, length as u64)
+            );
+            assert_eq!(
+                decrypt_segmented(&Aes256Gcm, &KEY, 16, &ciphertext).unwrap(),
+                plaintext
+            );
+        }
+    }
+
+    #[test]
+    fn test_in_place_matches_stream() {
+        let rng = &mut rand::thread_rng();
+        for length in [0, 15, 16, 40] {
+            let plaintext = (0..length).map(|i| i as u8).collect::<Vec<u8>>();
+
+            let mut buffer = plaintext.clone();
+            encrypt_segmented_in_place(&Aes256Gcm, &KEY, 16, &mut buffer, rng).unwrap();
+            let mut decryptor =
+                StreamDecryptor::new(&Aes256Gcm, &KEY, 16, buffer.as_slice()).unwrap();
+            let mut decrypted = Vec::new();
+            decryptor.read_to_end(&mut decrypted).unwrap();
+            assert_eq!(decrypted, plaintext);
+
+            let mut buffer = encrypt_segmented(&Aes256Gcm, &KEY, 16, &plaintext, rng).unwrap();
+            decrypt_segmented_in_place(&Aes256Gcm, &KEY, 16, &mut buffer).unwrap();
+            assert_eq!(buffer, plaintext);
+        }
+    }
+
+    #[test]
+    fn test_truncation_is_detected() {
+        let rng = &mut rand::thread_rng();
+        let plaintext = vec![1u8; 64];
+        let ciphertext = encrypt_segmented(&Aes256Gcm, &KEY, 16, &plaintext, rng).unwrap();
+        let segment = 16 + Aes256Gcm.tag_size();
+
+        // dropping the last segment leaves only full segments
+        let truncated = &ciphertext[..ciphertext.len() - Aes256Gcm.tag_size()];
+        assert!(decrypt_segmented(&Aes256Gcm, &KEY, 16, truncated).is_err());
+
+        // cutting a full segment short makes it look like the last one
+        let truncated = &ciphertext[..ciphertext.len() - segment];
+        assert!(decrypt_segmented(&Aes256Gcm, &KEY, 16, truncated).is_err());
+    }
+
+    #[test]
+    fn test_reordering_is_detected() {
+        let rng = &mut rand::thread_rng();
+        let plaintext = (0..64).map(|i| i as u8).collect::<Vec<u8>>();
+        let mut ciphertext = encrypt_segmented(&Aes256Gcm, &KEY, 16, &plaintext, rng).unwrap();
+        let segment = 16 + Aes256Gcm.tag_size();
+        let start = super::nonce_prefix_size(&Aes256Gcm);
+
+        let first = ciphertext[start..start + segment].to_vec();
+        let second = ciphertext[start + segment..start + 2 * segment].to_vec();
+        ciphertext[start..start + segment].copy_from_slice(&second);
+        ciphertext[start + segment..start + 2 * segment].copy_from_slice(&first);
+
+        assert!(decrypt_segmented(&Aes256Gcm, &KEY, 16, &ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_seek() {
+        let rng = &mut rand::thread_rng();
+        for length in [0, 15, 16, 17, 100] {
+            let plaintext = (0..length).map(|i| i as u8).collect::<Vec<u8>>();
+            let ciphertext = encrypt_segmented(&Aes256Gcm, &KEY, 16, &plaintext, rng).unwrap();
+            let mut decryptor =
+                SeekableDecryptor::new(&Aes256Gcm, &KEY, 16, Cursor::new(&ciphertext)).unwrap();
+            assert_eq!(decryptor.len(), length as u64);
+
+            for (start, end) in [(0, length), (length / 3, length / 2), (length, length)] {
+                decryptor.seek(SeekFrom::Start(start as u64)).unwrap();
+                let mut decrypted = vec![0u8; end - start];
+                decryptor.read_exact(&mut decrypted).unwrap();
+                assert_eq!(decrypted, plaintext[start..end]);
+            }
+
+            let mut decrypted = Vec::new();
+            decryptor.seek(SeekFrom::End(-(length as i64))).unwrap();
+            decryptor.read_to_end(&mut decrypted).unwrap();
+            assert_eq!(decrypted, plaintext);
+            assert!(decryptor
+                .seek(SeekFrom::Current(-(length as i64) - 1))
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_seek_authenticates_touched_segments() {
+        let rng = &mut rand::thread_rng();
+        let plaintext = (0..64).map(|i| i as u8).collect::<Vec<u8>>();
+        let mut ciphertext = encrypt_segmented(&Aes256Gcm, &KEY, 16, &plaintext, rng).unwrap();
+        let segment = 16 + Aes256Gcm.tag_size();
+        ciphertext[super::nonce_prefix_size(&Aes256Gcm) + segment] ^= 1;
+
+        let mut decryptor =
+            SeekableDecryptor::new(&Aes256Gcm, &KEY, 16, Cursor::new(&ciphertext)).unwrap();
+        let mut decrypted = [0u8; 16];
+        decryptor.seek(SeekFrom::Start(32)).unwrap();
+        decryptor.read_exact(&mut decrypted).unwrap();
+        assert_eq!(decrypted, plaintext[32..48]);
+
+        // the tampered second segment only fails once it is read
+        decryptor.seek(SeekFrom::Start(20)).unwrap();
+        assert!(decryptor.read(&mut decrypted).is_err());
+
+        // a truncated stream fails right away
+        let truncated = &ciphertext[..ciphertext.len() - segment];
+        assert!(SeekableDecryptor::new(&Aes256Gcm, &KEY, 16, Cursor::new(truncated)).is_err());
+    }
+}
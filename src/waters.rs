@@ -0,0 +1,205 @@
+// Ciphertext-policy ABE of Waters, "Ciphertext-Policy Attribute-Based Encryption: An
+// Expressive, Efficient, and Provably Secure Realization" (PKC 2011), construction 1 adapted to
+// the asymmetric pairing of rabe-bn. The public parameters h_x of the attributes are replaced by
+// `hash_to_g1`, the random oracle variant noted in the paper, so no attribute is registered.
+//
+// The access tree is converted to an LSSS matrix `M` with `lsss::LsssMatrix`, row i belonging to
+// leaf i. The secret `s` is shared as `lambda = M * (s, y_2, ..., y_n)` and the cipher text holds
+// `c_0 = g1^s` and per row `c_i = g1^(a * lambda_i) * H(rho(i))^-r_i` and `d_i = g2^r_i`. A key
+// for S holds `k = g2^(alpha + a * t)`, `l = g2^t` and `k_x = H(x)^t` for every x in S. With the
+// reconstruction coefficients w_i of the rows of S,
+// `e(c_0, k) / prod (e(c_i, l) * e(k_rho(i), d_i))^w_i = e(g1, g2)^(alpha * s)`.
+
+use std::collections::BTreeMap;
+
+use rabe_bn::{pairing, Fr, Gt, G1, G2};
+use rand::Rng;
+
+use crate::abe_attribute::{AbeAttribute, AbeIdentifier};
+use crate::access_tree::{AccessTree, GetAttributes};
+use crate::dem::{Aes256Gcm, BuiltinDems, DataEncapsulation, DemResolver};
+use crate::errors::abe_error::{AbeError, AbeErrorKind};
+use crate::hash_to_curve::hash_to_g1;
+use crate::kdf;
+use crate::kdf::KeySchedule;
+use crate::lsss::LsssMatrix;
+use crate::models::{
+    AbeDecrypted, AbeWatersCipherText, AbeWatersHeader, AbeWatersMasterKey, AbeWatersPublicKey,
+    AbeWatersSecretKey,
+};
+
+const ATTRIBUTE_LABEL: &str = "abe-waters-attribute";
+
+pub fn setup<R: Rng + ?Sized>(
+    g1: G1,
+    g2: G2,
+    rng: &mut R,
+) -> (AbeWatersPublicKey, AbeWatersMasterKey) {
+    let alpha: Fr = rng.gen();
+    let a: Fr = rng.gen();
+
+    (
+        AbeWatersPublicKey {
+            g1,
+            g2,
+            g1_a: g1 * a,
+            y: pairing(g1, g2).pow(alpha),
+        },
+        AbeWatersMasterKey { alpha, a },
+    )
+}
+
+pub fn keygen<R: Rng + ?Sized>(
+    attributes: &[String],
+    public_key: &AbeWatersPublicKey,
+    master_key: &AbeWatersMasterKey,
+    rng: &mut R,
+) -> Result<AbeWatersSecretKey, AbeError> {
+    if attributes.is_empty() {
+        return Err(AbeError::new("No attributes given"));
+    }
+
+    // t = random field element, binds the components of a key together
+    let t: Fr = rng.gen();
+
+    // k = g2^(alpha + a * t), l = g2^t
+    let k = public_key.g2 * (master_key.alpha + master_key.a * t);
+    let l = public_key.g2 * t;
+
+    // kx = H(x)^t
+    let arr_k = attributes
+        .iter()
+        .map(|attribute| (attribute.clone(), hash_attribute(attribute) * t))
+        .collect::<BTreeMap<String, G1>>();
+
+    Ok(AbeWatersSecretKey {
+        system_id: public_key.system_id(),
+        k,
+        l,
+        arr_k,
+    })
+}
+
+pub fn encrypt<R: Rng + ?Sized>(
+    secret: &Gt,
+    message: &[u8],
+    public_key: &AbeWatersPublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeWatersCipherText, AbeError> {
+    encrypt_with(&Aes256Gcm, secret, message, public_key, access_tree, rng)
+}
+
+/// Like `encrypt`, protecting the payload with the given data encapsulation mechanism
+pub fn encrypt_with<R: Rng + ?Sized>(
+    dem: &dyn DataEncapsulation,
+    secret: &Gt,
+    message: &[u8],
+    public_key: &AbeWatersPublicKey,
+    access_tree: &AccessTree,
+    rng: &mut R,
+) -> Result<AbeWatersCipherText, AbeError> {
+    let mut access_tree = access_tree.clone();
+    access_tree.clear_values();
+    access_tree.assign_indices();
+
+    // s = random field element, lambda_i = shares of s
+    let s: Fr = rng.gen();
+    let matrix = LsssMatrix::from_access_tree(&access_tree);
+    let shares = matrix.share(s, rng);
+
+    // c0 = g1^s
+    let c_0 = public_key.g1 * s;
+
+    // c1 = m * y^s
+    let c_1 = *secret * public_key.y.pow(s);
+
+    // ci = g1^(a * lambda_i) * H(rho(i))^-ri, di = g2^ri
+    let mut arr_c = Vec::new();
+    let mut arr_d = Vec::new();
+    for (leaf, share) in access_tree.get_attributes().into_iter().zip(shares) {
+        let r: Fr = rng.gen();
+        arr_c.push((
+            AbeIdentifier::from(leaf.clone()),
+            public_key.g1_a * share - hash_attribute(&leaf.name) * r,
+        ));
+        arr_d.push((AbeIdentifier::from(leaf), public_key.g2 * r));
+    }
+
+    let header = AbeWatersHeader {
+        version: kdf::FORMAT_VERSION,
+        system_id: public_key.system_id(),
+        algorithm: dem.algorithm().to_string(),
+        access_tree: Box::new(access_tree),
+        c_0,
+        c_1,
+        arr_c,
+        arr_d,
+    };
+    let message = dem.encrypt(&payload_key(secret, &header), message)?;
+
+    Ok(AbeWatersCipherText { header, message })
+}
+
+/// Decrypts the cipher text if the attributes of the secret key satisfy its access tree
+pub fn decrypt(
+    cipher_text: &AbeWatersCipherText,
+    secret_key: &AbeWatersSecretKey,
+) -> Result<AbeDecrypted, AbeError> {
+    decrypt_with(&BuiltinDems, cipher_text, secret_key)
+}
+
+/// Like `decrypt`, for a payload protected with a data encapsulation mechanism `dems` resolves
+pub fn decrypt_with(
+    dems: &dyn DemResolver,
+    cipher_text: &AbeWatersCipherText,
+    secret_key: &AbeWatersSecretKey,
+) -> Result<AbeDecrypted, AbeError> {
+    let dem = cipher_text.validate(dems)?;
+    secret_key.validate()?;
+    let header = &cipher_text.header;
+    if header.system_id != secret_key.system_id {
+        return Err(AbeError::with_kind(
+            AbeErrorKind::SystemMismatch,
+            "Secret key belongs to a different system than the cipher text",
+        ));
+    }
+
+    let attributes = secret_key
+        .arr_k
+        .keys()
+        .map(|name| AbeAttribute::new(name))
+        .collect::<Vec<AbeAttribute>>();
+    let matrix = LsssMatrix::from_access_tree(&header.access_tree);
+    let coefficients = matrix
+        .reconstruction_coefficients(&attributes)
+        .ok_or(AbeError::new(
+            "Secret key attributes do not satisfy the access tree of the cipher text",
+        ))?;
+
+    // prod (e(ci, l) * e(k_rho(i), di))^wi = e(g1, g2)^(a * s * t), validation made sure the
+    // rows of the matrix line up with the components
+    let mut product = Gt::one();
+    for (row, coefficient) in coefficients {
+        let c = header.arr_c[row].1;
+        let d = header.arr_d[row].1;
+        let k = secret_key.arr_k[&matrix.rho[row]];
+        product = product * (pairing(c, secret_key.l) * pairing(k, d)).pow(coefficient);
+    }
+
+    // e(c0, k) = e(g1, g2)^(alpha * s) * e(g1, g2)^(a * s * t)
+    let blinding = pairing(header.c_0, secret_key.k) * product.inverse();
+    let secret = header.c_1 * blinding.inverse();
+    let message = dem.decrypt(&payload_key(&secret, header), &cipher_text.message)?;
+
+    Ok(AbeDecrypted { message, secret })
+}
+
+fn hash_attribute(attribute: &str) -> G1 {
+    hash_to_g1(ATTRIBUTE_LABEL, attribute.as_bytes())
+}
+
+// Derives the key of the payload from the secret, bound to the header
+fn payload_key(secret: &Gt, header: &AbeWatersHeader) -> [u8; kdf::KEY_SIZE] {
+    KeySchedule::for_header(secret, header).derive(kdf::PAYLOAD_KEY)
+}
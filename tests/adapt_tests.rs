@@ -9,8 +9,7 @@ fn test_adapt_eq() {
     let rng = &mut rand::thread_rng();
     let attributes = vec!["A".to_string(), "B".to_string()];
     let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
-    let (adapted_public, adapted_master) =
-        adapt(&public_key, &master_key, &vec!["C".to_string()], rng);
+    let (adapted_public, adapted_master) = adapt(&public_key, &master_key, &["C".to_string()], rng);
 
     // PUBLIC
     // Basic eq
@@ -25,8 +24,8 @@ fn test_adapt_eq() {
     }
 
     // Check that C is not in the original big_t
-    assert_eq!(public_key.big_t.contains_key("C"), false);
-    assert_eq!(adapted_public.big_t.contains_key("C"), true);
+    assert!(!public_key.big_t.contains_key("C"));
+    assert!(adapted_public.big_t.contains_key("C"));
 
     // MASTER
     assert_eq!(adapted_master.alpha, master_key.alpha);
@@ -52,14 +51,14 @@ fn test_original_secret_decrypts_adapted_pk() {
     let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
 
     let secret_key = keygen(
-        &vec!["A".to_string(), "B".to_string()],
+        &["A".to_string(), "B".to_string()],
         &public_key,
         &master_key,
         rng,
     )
     .unwrap();
 
-    let (adapted_public, _) = adapt(&public_key, &master_key, &vec!["C".to_string()], rng);
+    let (adapted_public, _) = adapt(&public_key, &master_key, &["C".to_string()], rng);
 
     let cipher_text = encrypt(&secret, &message_bytes, &adapted_public, &tree, rng).unwrap();
     let decrypted = decrypt(&cipher_text, &secret_key).unwrap();
@@ -82,11 +81,10 @@ fn test_new_key_decrypts_old_data() {
 
     let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
 
-    let (adapted_public, adapted_master) =
-        adapt(&public_key, &master_key, &vec!["C".to_string()], rng);
+    let (adapted_public, adapted_master) = adapt(&public_key, &master_key, &["C".to_string()], rng);
 
     let new_secret = keygen(
-        &vec!["A".to_string(), "B".to_string(), "C".to_string()],
+        &["A".to_string(), "B".to_string(), "C".to_string()],
         &adapted_public,
         &adapted_master,
         rng,
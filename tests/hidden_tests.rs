@@ -0,0 +1,195 @@
+use rabe_bn::{Group, Gt, G1, G2};
+use rand::Rng;
+
+use abe::access_tree::GetAttributes;
+use abe::armor::Armored;
+use abe::crypto::{keygen, setup};
+use abe::dem::Aes256GcmSiv;
+use abe::errors::abe_error::AbeErrorKind;
+use abe::hidden::{category, decrypt, encrypt, encrypt_with};
+use abe::models::AbeHiddenCipherText;
+use abe::parser::AccessTreeParser;
+
+fn attributes(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+fn system_attributes() -> Vec<String> {
+    attributes(&[
+        "clinic:hiv",
+        "clinic:oncology",
+        "clinic:cardiology",
+        "role:doctor",
+        "role:nurse",
+        "ward:a",
+        "ward:b",
+    ])
+}
+
+#[test]
+fn test_encrypt_decrypt() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = setup(&system_attributes(), G1::one(), G2::one(), rng);
+
+    let secret: Gt = rng.gen();
+    let message_bytes = String::from("Hello World!").into_bytes();
+    for (policy, labels, satisfied) in [
+        ("clinic:hiv", vec!["clinic:hiv"], true),
+        ("clinic:hiv", vec!["clinic:oncology"], false),
+        (
+            "clinic:hiv&role:doctor",
+            vec!["clinic:oncology", "clinic:hiv", "role:doctor"],
+            true,
+        ),
+        (
+            "clinic:hiv&role:doctor",
+            vec!["clinic:hiv", "role:nurse"],
+            false,
+        ),
+        (
+            "(clinic:hiv&role:doctor)|(clinic:oncology&role:nurse)",
+            vec!["clinic:oncology", "role:nurse"],
+            true,
+        ),
+        (
+            "(clinic:hiv|clinic:oncology)&(ward:a|ward:b)",
+            vec!["clinic:oncology", "ward:b"],
+            true,
+        ),
+        (
+            "(clinic:hiv|clinic:oncology)&(ward:a|ward:b)",
+            vec!["clinic:cardiology", "ward:a"],
+            false,
+        ),
+    ] {
+        let access_tree = AccessTreeParser::new(policy).parse().unwrap();
+        let secret_key = keygen(&attributes(&labels), &public_key, &master_key, rng).unwrap();
+        let cipher_text = encrypt(&secret, &message_bytes, &public_key, &access_tree, rng).unwrap();
+
+        let decrypted = decrypt(&cipher_text, &secret_key);
+        assert_eq!(decrypted.is_ok(), satisfied, "{} with {:?}", policy, labels);
+        if satisfied {
+            let decrypted = decrypted.unwrap();
+            assert_eq!(decrypted.message, message_bytes);
+            assert_eq!(decrypted.secret, secret);
+        }
+    }
+}
+
+#[test]
+fn test_values_are_hidden() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, _) = setup(&system_attributes(), G1::one(), G2::one(), rng);
+    let access_tree = AccessTreeParser::new("clinic:hiv&(role:doctor|role:nurse)")
+        .parse()
+        .unwrap();
+    let secret: Gt = rng.gen();
+
+    let cipher_text = encrypt(&secret, b"Hello World!", &public_key, &access_tree, rng).unwrap();
+    let leaves = cipher_text.header.access_tree.get_attributes();
+    assert_eq!(
+        leaves
+            .iter()
+            .map(|leaf| leaf.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["clinic", "role", "role"]
+    );
+    assert!(leaves.iter().all(|leaf| leaf.value.is_none()));
+
+    let json = serde_json::to_string(&cipher_text.header.access_tree).unwrap();
+    for value in ["hiv", "doctor", "nurse"] {
+        assert!(!json.contains(value));
+    }
+}
+
+#[test]
+fn test_mismatches() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = setup(&system_attributes(), G1::one(), G2::one(), rng);
+    let (other_public_key, _) = setup(&system_attributes(), G1::one(), G2::one(), rng);
+    let secret_key = keygen(
+        &attributes(&["clinic:hiv", "role:doctor"]),
+        &public_key,
+        &master_key,
+        rng,
+    )
+    .unwrap();
+    let access_tree = AccessTreeParser::new("clinic:hiv&role:doctor")
+        .parse()
+        .unwrap();
+    let secret: Gt = rng.gen();
+
+    let cipher_text = encrypt(
+        &secret,
+        b"Hello World!",
+        &other_public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+        AbeErrorKind::SystemMismatch
+    );
+
+    let mut cipher_text =
+        encrypt(&secret, b"Hello World!", &public_key, &access_tree, rng).unwrap();
+    cipher_text.header.arr_test.pop();
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+        AbeErrorKind::LeafMismatch
+    );
+
+    // a swapped match test makes the leaf fail its test
+    let mut cipher_text =
+        encrypt(&secret, b"Hello World!", &public_key, &access_tree, rng).unwrap();
+    let other = encrypt(&secret, b"Hello World!", &public_key, &access_tree, rng).unwrap();
+    cipher_text.header.arr_test[0].1 = other.header.arr_test[0].1.clone();
+    assert!(decrypt(&cipher_text, &secret_key).is_err());
+
+    // attributes need a category and have to be registered
+    for policy in ["hiv&role:doctor", "clinic:dental&role:doctor"] {
+        let access_tree = AccessTreeParser::new(policy).parse().unwrap();
+        assert!(encrypt(&secret, b"Hello World!", &public_key, &access_tree, rng).is_err());
+    }
+
+    assert_eq!(category("clinic:hiv"), Some("clinic"));
+    assert_eq!(category("clinic"), None);
+    assert_eq!(category(":hiv"), None);
+    assert_eq!(category("clinic:"), None);
+}
+
+#[test]
+fn test_serialization() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = setup(&system_attributes(), G1::one(), G2::one(), rng);
+    let secret_key = keygen(
+        &attributes(&["clinic:hiv", "ward:a"]),
+        &public_key,
+        &master_key,
+        rng,
+    )
+    .unwrap();
+
+    let access_tree = AccessTreeParser::new("clinic:hiv&(ward:a|ward:b)")
+        .parse()
+        .unwrap();
+    let secret: Gt = rng.gen();
+    let cipher_text = encrypt_with(
+        &Aes256GcmSiv,
+        &secret,
+        b"Hello World!",
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    assert_eq!(cipher_text.header.algorithm, Aes256GcmSiv::ALGORITHM);
+
+    let cipher_text =
+        AbeHiddenCipherText::from_armored(&cipher_text.to_armored().unwrap()).unwrap();
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).unwrap().message,
+        b"Hello World!"
+    );
+}
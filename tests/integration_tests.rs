@@ -1,20 +1,37 @@
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
 use itertools::Itertools;
 use rabe_bn::{Fr, Group, Gt, G1, G2};
 use rand::Rng;
+use sha3::{Digest, Sha3_256};
 
 use abe::abe_attribute::AbeAttribute;
 use abe::access_tree::TreeOperator::{And, Or};
 use abe::access_tree::{AccessTree, GetAttributes, Leaf, Operator};
-use abe::crypto::{decrypt, encrypt, keygen, setup};
+use abe::canonical::Fingerprint;
+use abe::compression::{DEFAULT_SIZE_LIMIT, DEFLATE};
+use abe::crypto::{
+    decapsulate, decrypt, decrypt_envelope, decrypt_envelope_seekable, decrypt_in_place,
+    decrypt_seekable, decrypt_stream, decrypt_transformed, delegate, encapsulate, encrypt,
+    encrypt_envelope, encrypt_in_place, encrypt_stream, encrypt_with, encrypt_with_options, keygen,
+    keygen_delegable, rewrap, rewrap_envelope_header, rewrap_header, setup, transform,
+    transformation_key, PayloadOptions,
+};
+use abe::dem::{Aes256Gcm, Aes256GcmSiv, BuiltinDems, DataEncapsulation};
+use abe::errors::abe_error::AbeErrorKind;
+use abe::errors::symmetric_encryption_error::SymmetricEncryptionError;
+use abe::kdf::FORMAT_VERSION;
+use abe::models::{AbeCipherText, AbeDetachedEnvelope, AbeEnvelope};
+use abe::padding::Padding;
 use abe::parser::AccessTreeParser;
 
-fn encrypt_decrypt(tree: &AccessTree, key_attributes: &Vec<AbeAttribute>) {
+fn encrypt_decrypt(tree: &AccessTree, key_attributes: &[AbeAttribute]) {
     let rng = &mut rand::thread_rng();
 
     let secret: Gt = rng.gen();
     let message_bytes = String::from("Hello World!").into_bytes();
 
-    let attributes = tree
+    let attributes: Vec<String> = tree
         .get_attributes()
         .iter()
         .map(|a| a.name.clone())
@@ -24,13 +41,16 @@ fn encrypt_decrypt(tree: &AccessTree, key_attributes: &Vec<AbeAttribute>) {
     let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
 
     let secret_key = keygen(
-        &key_attributes.iter().map(|a| a.name.clone()).collect(),
+        &key_attributes
+            .iter()
+            .map(|a| a.name.clone())
+            .collect::<Vec<String>>(),
         &public_key,
         &master_key,
         rng,
     )
     .unwrap();
-    let cipher_text = encrypt(&secret, &message_bytes, &public_key, &tree, rng).unwrap();
+    let cipher_text = encrypt(&secret, &message_bytes, &public_key, tree, rng).unwrap();
 
     let decrypted = decrypt(&cipher_text, &secret_key).unwrap();
 
@@ -71,7 +91,7 @@ fn correctness_test1() {
 
     encrypt_decrypt(
         &access_tree,
-        &vec![AbeAttribute::new("A"), AbeAttribute::new("B")],
+        &[AbeAttribute::new("A"), AbeAttribute::new("B")],
     );
 }
 
@@ -80,7 +100,7 @@ fn correctness_test2() {
     let mut parser = AccessTreeParser::new("A&A");
     let access_tree = parser.parse().unwrap();
 
-    encrypt_decrypt(&access_tree, &vec![AbeAttribute::new("A")]);
+    encrypt_decrypt(&access_tree, &[AbeAttribute::new("A")]);
 }
 
 #[test]
@@ -88,7 +108,7 @@ fn correctness_test3() {
     let mut parser = AccessTreeParser::new("(A&A)&A");
     let access_tree = parser.parse().unwrap();
 
-    encrypt_decrypt(&access_tree, &vec![AbeAttribute::new("A")]);
+    encrypt_decrypt(&access_tree, &[AbeAttribute::new("A")]);
 }
 
 #[test]
@@ -98,7 +118,7 @@ fn correctness_test4() {
 
     encrypt_decrypt(
         &access_tree,
-        &vec![AbeAttribute::new("A"), AbeAttribute::new("B")],
+        &[AbeAttribute::new("A"), AbeAttribute::new("B")],
     );
 }
 
@@ -109,7 +129,7 @@ fn correctness_test_complex() {
 
     encrypt_decrypt(
         &access_tree,
-        &vec![
+        &[
             AbeAttribute::new("A"),
             AbeAttribute::new("B"),
             AbeAttribute::new("E"),
@@ -125,7 +145,7 @@ fn correctness_test_very_complex() {
 
     encrypt_decrypt(
         &access_tree,
-        &vec![
+        &[
             AbeAttribute::new("A"),
             AbeAttribute::new("B"),
             AbeAttribute::new("E"),
@@ -144,7 +164,7 @@ fn correctness_test_unbalanced_tree() {
 
     encrypt_decrypt(
         &access_tree,
-        &vec![
+        &[
             AbeAttribute::new("A"),
             AbeAttribute::new("B"),
             AbeAttribute::new("E"),
@@ -184,3 +204,1149 @@ fn correctness_test_long_chain() {
         ],
     );
 }
+
+#[test]
+fn test_decrypt_with_key_from_other_system() {
+    let rng = &mut rand::thread_rng();
+    let access_tree = AccessTreeParser::new("A&B").parse().unwrap();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+
+    let (public_key, _) = setup(&attributes, G1::one(), G2::one(), rng);
+    let (other_public_key, other_master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let other_secret_key = keygen(&attributes, &other_public_key, &other_master_key, rng).unwrap();
+
+    let secret: Gt = rng.gen();
+    let message_bytes = String::from("Hello World!").into_bytes();
+    let cipher_text = encrypt(&secret, &message_bytes, &public_key, &access_tree, rng).unwrap();
+
+    let error = decrypt(&cipher_text, &other_secret_key).err().unwrap();
+    assert_eq!(error.kind, AbeErrorKind::SystemMismatch);
+}
+
+#[test]
+fn test_encrypt_with_other_algorithm() {
+    let rng = &mut rand::thread_rng();
+    let access_tree = AccessTreeParser::new("A|B").parse().unwrap();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+
+    let secret: Gt = rng.gen();
+    let message_bytes = String::from("Hello World!").into_bytes();
+    let mut cipher_text = encrypt_with(
+        &Aes256GcmSiv,
+        &secret,
+        &message_bytes,
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    assert_eq!(cipher_text.header.algorithm, Aes256GcmSiv::ALGORITHM);
+
+    // the algorithm is picked from the cipher text
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).unwrap().message,
+        message_bytes
+    );
+    assert_eq!(
+        decrypt_in_place(&Aes256Gcm, cipher_text.clone(), &secret_key)
+            .err()
+            .unwrap()
+            .kind,
+        AbeErrorKind::UnknownAlgorithm
+    );
+
+    cipher_text.header.algorithm = String::from("ROT13");
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+        AbeErrorKind::UnknownAlgorithm
+    );
+}
+
+// Toy data encapsulation mechanism defined outside of the crate, with a longer nonce than the
+// built in ones. The key stream and tag are hashes of the key and nonce, do not use for real data.
+struct XorSha3;
+
+impl XorSha3 {
+    const ALGORITHM: &'static str = "TEST-XOR-SHA3";
+
+    fn key_stream(key: &[u8], nonce: &[u8], length: usize) -> Vec<u8> {
+        (0..length.div_ceil(32) as u64)
+            .flat_map(|block| {
+                Sha3_256::new()
+                    .chain(key)
+                    .chain(nonce)
+                    .chain(block.to_be_bytes())
+                    .finalize()
+            })
+            .take(length)
+            .collect()
+    }
+
+    fn tag(key: &[u8], nonce: &[u8], data: &[u8]) -> Vec<u8> {
+        Sha3_256::new()
+            .chain(b"tag")
+            .chain(key)
+            .chain(nonce)
+            .chain(data)
+            .finalize()
+            .to_vec()
+    }
+}
+
+impl DataEncapsulation for XorSha3 {
+    fn algorithm(&self) -> &str {
+        Self::ALGORITHM
+    }
+
+    fn nonce_size(&self) -> usize {
+        24
+    }
+
+    fn tag_size(&self) -> usize {
+        32
+    }
+
+    fn seal_detached(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        buffer: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<(), SymmetricEncryptionError> {
+        assert_eq!(nonce.len(), self.nonce_size());
+        let key_stream = Self::key_stream(key, nonce, buffer.len());
+        for (byte, key) in buffer.iter_mut().zip(key_stream) {
+            *byte ^= key;
+        }
+        tag.copy_from_slice(&Self::tag(key, nonce, buffer));
+        Ok(())
+    }
+
+    fn open_detached(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), SymmetricEncryptionError> {
+        assert_eq!(nonce.len(), self.nonce_size());
+        if Self::tag(key, nonce, buffer) != tag {
+            return Err(SymmetricEncryptionError::new("Tag mismatch"));
+        }
+        let key_stream = Self::key_stream(key, nonce, buffer.len());
+        for (byte, key) in buffer.iter_mut().zip(key_stream) {
+            *byte ^= key;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_external_algorithm() {
+    let rng = &mut rand::thread_rng();
+    let access_tree = AccessTreeParser::new("A|B").parse().unwrap();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+    let dems: &[&dyn DataEncapsulation] = &[&Aes256Gcm, &XorSha3];
+
+    let secret: Gt = rng.gen();
+    let message_bytes = (0..1000).map(|i| i as u8).collect::<Vec<u8>>();
+    for segment_size in [None, Some(100)] {
+        let options = PayloadOptions {
+            segment_size,
+            ..Default::default()
+        };
+        let cipher_text = encrypt_with_options(
+            &XorSha3,
+            &options,
+            &secret,
+            &message_bytes,
+            &public_key,
+            &access_tree,
+            rng,
+        )
+        .unwrap();
+        assert_eq!(cipher_text.header.algorithm, XorSha3::ALGORITHM);
+
+        // the built in algorithms do not know it, a resolver with it decrypts on every path
+        assert_eq!(
+            decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+            AbeErrorKind::UnknownAlgorithm
+        );
+        assert_eq!(
+            decrypt_in_place(&dems, cipher_text.clone(), &secret_key)
+                .unwrap()
+                .message,
+            message_bytes
+        );
+        let mut output = Vec::new();
+        decrypt_stream(
+            &XorSha3,
+            &cipher_text.header,
+            &secret_key,
+            cipher_text.message.as_slice(),
+            &mut output,
+            DEFAULT_SIZE_LIMIT,
+        )
+        .unwrap();
+        assert_eq!(output, message_bytes);
+
+        let envelope = rewrap(
+            &dems,
+            cipher_text.clone(),
+            &secret_key,
+            &public_key,
+            &access_tree,
+            rng,
+        )
+        .unwrap();
+        assert_eq!(
+            decrypt_envelope(&XorSha3, envelope.clone(), &secret_key).unwrap(),
+            message_bytes
+        );
+        assert_eq!(
+            decrypt_in_place(&XorSha3, cipher_text, &secret_key)
+                .unwrap()
+                .message,
+            message_bytes
+        );
+    }
+}
+
+#[test]
+fn test_encrypt_stream() {
+    let rng = &mut rand::thread_rng();
+    let access_tree = AccessTreeParser::new("A&B").parse().unwrap();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+
+    let secret: Gt = rng.gen();
+    let message_bytes = (0..1000).map(|i| i as u8).collect::<Vec<u8>>();
+    let options = PayloadOptions {
+        segment_size: Some(64),
+        ..Default::default()
+    };
+    let (header, mut encryptor) = encrypt_stream(
+        &Aes256Gcm,
+        &secret,
+        &options,
+        Vec::new(),
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    encryptor.write_all(&message_bytes).unwrap();
+    let payload = encryptor.finish().unwrap();
+    assert_eq!(header.segment_size, Some(64));
+
+    let mut decrypted = Vec::new();
+    decrypt_stream(
+        &BuiltinDems,
+        &header,
+        &secret_key,
+        payload.as_slice(),
+        &mut decrypted,
+        DEFAULT_SIZE_LIMIT,
+    )
+    .unwrap();
+    assert_eq!(decrypted, message_bytes);
+
+    // segmented payloads also decrypt in memory
+    let cipher_text = AbeCipherText {
+        header,
+        message: payload,
+    };
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).unwrap().message,
+        message_bytes
+    );
+
+    let mut tampered = cipher_text.message.clone();
+    tampered[100] ^= 1;
+    assert!(decrypt_stream(
+        &BuiltinDems,
+        &cipher_text.header,
+        &secret_key,
+        tampered.as_slice(),
+        &mut Vec::new(),
+        DEFAULT_SIZE_LIMIT
+    )
+    .is_err());
+}
+
+#[test]
+fn test_payload_key_is_bound_to_header() {
+    let rng = &mut rand::thread_rng();
+    let access_tree = AccessTreeParser::new("A|B").parse().unwrap();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+
+    let secret: Gt = rng.gen();
+    let message_bytes = String::from("Hello World!").into_bytes();
+    let cipher_text = encrypt(&secret, &message_bytes, &public_key, &access_tree, rng).unwrap();
+    assert_eq!(cipher_text.header.version, FORMAT_VERSION);
+
+    // switching the algorithm to one with the same layout changes the key
+    let mut tampered = cipher_text.clone();
+    tampered.header.algorithm = String::from(Aes256GcmSiv::ALGORITHM);
+    assert!(decrypt(&tampered, &secret_key).is_err());
+
+    let mut tampered = cipher_text.clone();
+    tampered.header.version = FORMAT_VERSION + 1;
+    assert_eq!(
+        decrypt(&tampered, &secret_key).err().unwrap().kind,
+        AbeErrorKind::UnsupportedVersion
+    );
+}
+
+#[test]
+fn test_decrypt_header_without_version() {
+    let rng = &mut rand::thread_rng();
+    let access_tree = AccessTreeParser::new("A|B").parse().unwrap();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+
+    // headers from before the key schedule use the hash of the secret as key
+    let secret: Gt = rng.gen();
+    let message_bytes = String::from("Hello World!").into_bytes();
+    let mut cipher_text = encrypt(&secret, &[], &public_key, &access_tree, rng).unwrap();
+    cipher_text.header.version = 0;
+    cipher_text.message = Aes256Gcm
+        .encrypt(&Sha3_256::digest(&secret.into_bytes()), &message_bytes)
+        .unwrap();
+
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).unwrap().message,
+        message_bytes
+    );
+}
+
+#[test]
+fn test_encapsulate_decapsulate() {
+    let rng = &mut rand::thread_rng();
+    let access_tree = AccessTreeParser::new("A&B").parse().unwrap();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+    let other_key = keygen(&["A".to_string()], &public_key, &master_key, rng).unwrap();
+
+    let (header, key) = encapsulate(&public_key, &access_tree, rng).unwrap();
+    assert_eq!(decapsulate(&header, &secret_key).unwrap(), key);
+    assert!(decapsulate(&header, &other_key).is_err());
+
+    // every encapsulation yields a new key
+    let (_, other) = encapsulate(&public_key, &access_tree, rng).unwrap();
+    assert_ne!(key, other);
+
+    // headers of encrypted payloads are not accepted and vice versa
+    let secret: Gt = rng.gen();
+    let cipher_text = encrypt(&secret, b"Hello World!", &public_key, &access_tree, rng).unwrap();
+    assert_eq!(
+        decapsulate(&cipher_text.header, &secret_key)
+            .err()
+            .unwrap()
+            .kind,
+        AbeErrorKind::UnknownAlgorithm
+    );
+    let cipher_text = AbeCipherText {
+        header,
+        message: key.to_vec(),
+    };
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+        AbeErrorKind::UnknownAlgorithm
+    );
+}
+
+// Panics if a node of the tree holds a value, i.e. a share of the secret
+fn assert_no_shares(tree: &AccessTree) {
+    match tree {
+        AccessTree::Operator(Operator {
+            left, right, value, ..
+        }) => {
+            assert!(value.is_none());
+            assert_no_shares(left);
+            assert_no_shares(right);
+        }
+        AccessTree::Leaf(Leaf { attribute, value }) => {
+            assert!(value.is_none() && attribute.value.is_none());
+        }
+    }
+}
+
+#[test]
+fn test_headers_hold_no_shares() {
+    let rng = &mut rand::thread_rng();
+    let attributes = vec!["A".to_string(), "B".to_string(), "C".to_string()];
+    let (public_key, _) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret: Gt = rng.gen();
+
+    for policy in ["A", "A&B", "(A&B)|(A&C)", "(A|B)&(A|C)"] {
+        let access_tree = AccessTreeParser::new(policy).parse().unwrap();
+        let cipher_text =
+            encrypt(&secret, b"Hello World!", &public_key, &access_tree, rng).unwrap();
+        assert_no_shares(&cipher_text.header.access_tree);
+        assert!(cipher_text
+            .header
+            .access_tree
+            .get_attributes()
+            .iter()
+            .all(|leaf| leaf.value.is_none()));
+
+        let (header, _) = encapsulate(&public_key, &access_tree, rng).unwrap();
+        assert_no_shares(&header.access_tree);
+    }
+}
+
+#[test]
+fn test_single_attribute_policy() {
+    let rng = &mut rand::thread_rng();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&["A".to_string()], &public_key, &master_key, rng).unwrap();
+    let other_key = keygen(&["B".to_string()], &public_key, &master_key, rng).unwrap();
+    let access_tree = AccessTreeParser::new("A").parse().unwrap();
+
+    let secret: Gt = rng.gen();
+    let cipher_text = encrypt(&secret, b"Hello World!", &public_key, &access_tree, rng).unwrap();
+    let decrypted = decrypt(&cipher_text, &secret_key).unwrap();
+    assert_eq!(decrypted.secret, secret);
+    assert_eq!(decrypted.message, b"Hello World!");
+    assert!(decrypt(&cipher_text, &other_key).is_err());
+
+    let (header, key) = encapsulate(&public_key, &access_tree, rng).unwrap();
+    assert_eq!(decapsulate(&header, &secret_key).unwrap(), key);
+}
+
+#[test]
+fn test_unknown_attribute() {
+    let rng = &mut rand::thread_rng();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    assert!(keygen(&["Z".to_string()], &public_key, &master_key, rng).is_err());
+
+    let access_tree = AccessTreeParser::new("A & Z").parse().unwrap();
+    let secret: Gt = rng.gen();
+    assert!(encrypt(&secret, b"Hello World!", &public_key, &access_tree, rng).is_err());
+    assert!(encapsulate(&public_key, &access_tree, rng).is_err());
+    assert!(encrypt_envelope(
+        &Aes256Gcm,
+        b"Hello World!",
+        &[(&public_key, &access_tree)],
+        rng
+    )
+    .is_err());
+}
+
+#[test]
+fn test_encrypt_decrypt_in_place() {
+    let rng = &mut rand::thread_rng();
+    let access_tree = AccessTreeParser::new("A|B").parse().unwrap();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+
+    let secret: Gt = rng.gen();
+    let message_bytes = String::from("Hello World!").into_bytes();
+    let mut buffer = Vec::with_capacity(message_bytes.len() + Aes256Gcm.overhead());
+    buffer.extend_from_slice(&message_bytes);
+    let capacity = buffer.capacity();
+
+    let header = encrypt_in_place(
+        &Aes256Gcm,
+        &secret,
+        &mut buffer,
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    assert_eq!(buffer.capacity(), capacity);
+
+    let cipher_text = AbeCipherText {
+        header,
+        message: buffer,
+    };
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).unwrap().message,
+        message_bytes
+    );
+
+    let decrypted = decrypt_in_place(&BuiltinDems, cipher_text, &secret_key).unwrap();
+    assert_eq!(decrypted.message, message_bytes);
+    assert_eq!(decrypted.message.capacity(), capacity);
+}
+
+#[test]
+fn test_envelope_with_several_systems() {
+    let rng = &mut rand::thread_rng();
+    // (Legal & EU) in one system, (Security & Incident) in another
+    let legal = AccessTreeParser::new("L&E").parse().unwrap();
+    let security = AccessTreeParser::new("S&I").parse().unwrap();
+    let (legal_public, legal_master) = setup(
+        &["L".to_string(), "E".to_string()],
+        G1::one(),
+        G2::one(),
+        rng,
+    );
+    let (security_public, security_master) = setup(
+        &["S".to_string(), "I".to_string()],
+        G1::one(),
+        G2::one(),
+        rng,
+    );
+
+    let message_bytes = String::from("Hello World!").into_bytes();
+    let envelope = encrypt_envelope(
+        &Aes256Gcm,
+        &message_bytes,
+        &[(&legal_public, &legal), (&security_public, &security)],
+        rng,
+    )
+    .unwrap();
+    assert_eq!(envelope.header.recipients.len(), 2);
+
+    let legal_key = keygen(
+        &["L".to_string(), "E".to_string()],
+        &legal_public,
+        &legal_master,
+        rng,
+    )
+    .unwrap();
+    let security_key = keygen(
+        &["S".to_string(), "I".to_string()],
+        &security_public,
+        &security_master,
+        rng,
+    )
+    .unwrap();
+    assert_eq!(
+        decrypt_envelope(&BuiltinDems, envelope.clone(), &legal_key).unwrap(),
+        message_bytes
+    );
+    assert_eq!(
+        decrypt_envelope(&BuiltinDems, envelope.clone(), &security_key).unwrap(),
+        message_bytes
+    );
+
+    let unauthorized_key = keygen(&["L".to_string()], &legal_public, &legal_master, rng).unwrap();
+    assert_eq!(
+        decrypt_envelope(&BuiltinDems, envelope.clone(), &unauthorized_key)
+            .err()
+            .unwrap()
+            .kind,
+        AbeErrorKind::NoRecipient
+    );
+    assert_eq!(
+        encrypt_envelope(&Aes256Gcm, &message_bytes, &[], rng)
+            .err()
+            .unwrap()
+            .kind,
+        AbeErrorKind::NoRecipient
+    );
+}
+
+#[test]
+fn test_envelope_without_satisfied_recipient() {
+    let rng = &mut rand::thread_rng();
+    let attributes = vec!["A".to_string(), "B".to_string(), "C".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let first = AccessTreeParser::new("A&B").parse().unwrap();
+    let second = AccessTreeParser::new("(A|B)&C").parse().unwrap();
+
+    let message_bytes = String::from("Hello World!").into_bytes();
+    let envelope = encrypt_envelope(
+        &Aes256Gcm,
+        &message_bytes,
+        &[(&public_key, &first), (&public_key, &second)],
+        rng,
+    )
+    .unwrap();
+
+    // the recipients publish no shares a key could be recovered from
+    for recipient in &envelope.header.recipients {
+        assert_no_shares(&recipient.header.access_tree);
+    }
+
+    // A alone satisfies neither policy, not even when every recipient is tried directly
+    let secret_key = keygen(&["A".to_string()], &public_key, &master_key, rng).unwrap();
+    assert_eq!(
+        decrypt_envelope(&BuiltinDems, envelope.clone(), &secret_key)
+            .err()
+            .unwrap()
+            .kind,
+        AbeErrorKind::NoRecipient
+    );
+    for recipient in &envelope.header.recipients {
+        assert!(decapsulate(&recipient.header, &secret_key).is_err());
+    }
+}
+
+#[test]
+fn test_rewrap() {
+    let rng = &mut rand::thread_rng();
+    let attributes = vec![
+        "A".to_string(),
+        "B".to_string(),
+        "C".to_string(),
+        "D".to_string(),
+    ];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let old_key = keygen(
+        &["A".to_string(), "B".to_string()],
+        &public_key,
+        &master_key,
+        rng,
+    )
+    .unwrap();
+    let new_key = keygen(
+        &["C".to_string(), "D".to_string()],
+        &public_key,
+        &master_key,
+        rng,
+    )
+    .unwrap();
+    let old_tree = AccessTreeParser::new("A&B").parse().unwrap();
+    let new_tree = AccessTreeParser::new("C&D").parse().unwrap();
+
+    let secret: Gt = rng.gen();
+    let message_bytes = String::from("Hello World!").into_bytes();
+    let cipher_text = encrypt(&secret, &message_bytes, &public_key, &old_tree, rng).unwrap();
+    let payload = cipher_text.message.clone();
+
+    // only holders of a key for the current policy can rewrap
+    assert!(rewrap(
+        &BuiltinDems,
+        cipher_text.clone(),
+        &new_key,
+        &public_key,
+        &new_tree,
+        rng
+    )
+    .is_err());
+
+    let envelope = rewrap(
+        &BuiltinDems,
+        cipher_text,
+        &old_key,
+        &public_key,
+        &new_tree,
+        rng,
+    )
+    .unwrap();
+    assert_eq!(envelope.message, payload);
+    assert_eq!(
+        decrypt_envelope(&BuiltinDems, envelope.clone(), &new_key).unwrap(),
+        message_bytes
+    );
+    assert_eq!(
+        decrypt_envelope(&BuiltinDems, envelope.clone(), &old_key)
+            .err()
+            .unwrap()
+            .kind,
+        AbeErrorKind::NoRecipient
+    );
+
+    // a detached envelope keeps the hash of the untouched payload
+    let (detached, payload) = envelope.detach();
+    let header = rewrap_envelope_header(
+        &BuiltinDems,
+        &detached.header,
+        &new_key,
+        &public_key,
+        &old_tree,
+        rng,
+    )
+    .unwrap();
+    let envelope = AbeEnvelope::attach(
+        AbeDetachedEnvelope {
+            header,
+            payload_hash: detached.payload_hash,
+        },
+        payload,
+    )
+    .unwrap();
+    assert_eq!(
+        decrypt_envelope(&BuiltinDems, envelope.clone(), &old_key).unwrap(),
+        message_bytes
+    );
+}
+
+#[test]
+fn test_rewrap_excludes_previous_keys() {
+    let rng = &mut rand::thread_rng();
+    let attributes = vec!["A".to_string(), "B".to_string(), "C".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let old_key = keygen(
+        &["A".to_string(), "B".to_string()],
+        &public_key,
+        &master_key,
+        rng,
+    )
+    .unwrap();
+    let new_key = keygen(
+        &["A".to_string(), "C".to_string()],
+        &public_key,
+        &master_key,
+        rng,
+    )
+    .unwrap();
+    let old_tree = AccessTreeParser::new("A&B").parse().unwrap();
+    let new_tree = AccessTreeParser::new("A&C").parse().unwrap();
+
+    let secret: Gt = rng.gen();
+    let message_bytes = String::from("Hello World!").into_bytes();
+    let cipher_text = encrypt(&secret, &message_bytes, &public_key, &old_tree, rng).unwrap();
+
+    // rewrapping the header alone gives the same result as rewrapping the cipher text
+    let header = rewrap_header(
+        &BuiltinDems,
+        &cipher_text.header,
+        &old_key,
+        &public_key,
+        &new_tree,
+        rng,
+    )
+    .unwrap();
+    let envelope = rewrap(
+        &BuiltinDems,
+        cipher_text,
+        &old_key,
+        &public_key,
+        &new_tree,
+        rng,
+    )
+    .unwrap();
+    for header in [&header, &envelope.header] {
+        let recipient = &header.recipients[0];
+        assert_no_shares(&recipient.header.access_tree);
+        // the key for the previous policy does not satisfy the new one, even when tried directly
+        assert!(decapsulate(&recipient.header, &old_key).is_err());
+        assert!(decapsulate(&recipient.header, &new_key).is_ok());
+    }
+    assert_eq!(
+        decrypt_envelope(&BuiltinDems, envelope.clone(), &old_key)
+            .err()
+            .unwrap()
+            .kind,
+        AbeErrorKind::NoRecipient
+    );
+    assert_eq!(
+        decrypt_envelope(&BuiltinDems, envelope.clone(), &new_key).unwrap(),
+        message_bytes
+    );
+}
+
+#[test]
+fn test_compressed_payload() {
+    let rng = &mut rand::thread_rng();
+    let access_tree = AccessTreeParser::new("A|B").parse().unwrap();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+
+    let secret: Gt = rng.gen();
+    let message_bytes = vec![0u8; 100_000];
+    for segment_size in [None, Some(1000)] {
+        let options = PayloadOptions {
+            segment_size,
+            compression: Some(DEFLATE.to_string()),
+            ..Default::default()
+        };
+        let cipher_text = encrypt_with_options(
+            &Aes256Gcm,
+            &options,
+            &secret,
+            &message_bytes,
+            &public_key,
+            &access_tree,
+            rng,
+        )
+        .unwrap();
+        assert_eq!(cipher_text.header.compression, options.compression);
+        assert!(cipher_text.message.len() < message_bytes.len() / 10);
+        assert_eq!(
+            decrypt(&cipher_text, &secret_key).unwrap().message,
+            message_bytes
+        );
+
+        // a limit below the size of the message stops decompression
+        let mut decrypted = Vec::new();
+        assert_eq!(
+            decrypt_stream(
+                &BuiltinDems,
+                &cipher_text.header,
+                &secret_key,
+                cipher_text.message.as_slice(),
+                &mut decrypted,
+                1000,
+            )
+            .err()
+            .unwrap()
+            .kind,
+            AbeErrorKind::SizeLimitExceeded
+        );
+        assert!(decrypted.len() <= 1000);
+    }
+
+    // compression is streamed as well
+    let (header, mut encryptor) = encrypt_stream(
+        &Aes256Gcm,
+        &secret,
+        &PayloadOptions {
+            segment_size: None,
+            compression: Some(DEFLATE.to_string()),
+            ..Default::default()
+        },
+        Vec::new(),
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    encryptor.write_all(&message_bytes).unwrap();
+    let payload = encryptor.finish().unwrap();
+
+    let mut decrypted = Vec::new();
+    decrypt_stream(
+        &BuiltinDems,
+        &header,
+        &secret_key,
+        payload.as_slice(),
+        &mut decrypted,
+        DEFAULT_SIZE_LIMIT,
+    )
+    .unwrap();
+    assert_eq!(decrypted, message_bytes);
+
+    // the compression algorithm is authenticated with the header
+    let mut tampered = AbeCipherText {
+        header,
+        message: payload,
+    };
+    tampered.header.compression = None;
+    assert!(decrypt(&tampered, &secret_key).is_err());
+    tampered.header.compression = Some(String::from("ZIP"));
+    assert_eq!(
+        decrypt(&tampered, &secret_key).err().unwrap().kind,
+        AbeErrorKind::UnknownAlgorithm
+    );
+}
+
+#[test]
+fn test_decrypt_seekable() {
+    let rng = &mut rand::thread_rng();
+    let access_tree = AccessTreeParser::new("A|B").parse().unwrap();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+
+    let secret: Gt = rng.gen();
+    let message_bytes = (0..10_000).map(|i| i as u8).collect::<Vec<u8>>();
+    let options = PayloadOptions {
+        segment_size: Some(1000),
+        ..Default::default()
+    };
+    let cipher_text = encrypt_with_options(
+        &Aes256Gcm,
+        &options,
+        &secret,
+        &message_bytes,
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+
+    let mut reader = decrypt_seekable(
+        &BuiltinDems,
+        &cipher_text.header,
+        &secret_key,
+        Cursor::new(&cipher_text.message),
+    )
+    .unwrap();
+    assert_eq!(reader.len(), message_bytes.len() as u64);
+    let mut range = vec![0u8; 2500];
+    reader.seek(SeekFrom::Start(4321)).unwrap();
+    reader.read_exact(&mut range).unwrap();
+    assert_eq!(range, message_bytes[4321..6821]);
+
+    // envelopes are read the same way
+    let envelope = rewrap(
+        &BuiltinDems,
+        cipher_text,
+        &secret_key,
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    let mut reader = decrypt_envelope_seekable(
+        &BuiltinDems,
+        &envelope.header,
+        &secret_key,
+        Cursor::new(&envelope.message),
+    )
+    .unwrap();
+    let mut last = Vec::new();
+    reader.seek(SeekFrom::End(-10)).unwrap();
+    reader.read_to_end(&mut last).unwrap();
+    assert_eq!(last, message_bytes[9990..]);
+
+    // payloads sealed at once have no segments to seek to
+    let cipher_text = encrypt(&secret, &message_bytes, &public_key, &access_tree, rng).unwrap();
+    assert!(decrypt_seekable(
+        &BuiltinDems,
+        &cipher_text.header,
+        &secret_key,
+        Cursor::new(&cipher_text.message)
+    )
+    .is_err());
+}
+
+#[test]
+fn test_padded_payload() {
+    let rng = &mut rand::thread_rng();
+    let access_tree = AccessTreeParser::new("A|B").parse().unwrap();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+    let secret: Gt = rng.gen();
+
+    // messages of different lengths in the same bucket give payloads of the same length
+    let encrypt_padded = |message: &[u8], options: &PayloadOptions, rng: &mut _| {
+        encrypt_with_options(
+            &Aes256Gcm,
+            options,
+            &secret,
+            message,
+            &public_key,
+            &access_tree,
+            rng,
+        )
+        .unwrap()
+    };
+    for padding in [Padding::PowerOfTwo, Padding::Block(1024)] {
+        for segment_size in [None, Some(100)] {
+            let options = PayloadOptions {
+                segment_size,
+                padding: Some(padding),
+                ..Default::default()
+            };
+            let short = encrypt_padded(&[1u8; 600], &options, rng);
+            let long = encrypt_padded(&[1u8; 1000], &options, rng);
+            assert_eq!(short.header.padding, Some(padding));
+            assert_eq!(short.message.len(), long.message.len());
+            assert_eq!(
+                decrypt(&short, &secret_key).unwrap().message,
+                vec![1u8; 600]
+            );
+
+            let mut decrypted = Vec::new();
+            decrypt_stream(
+                &BuiltinDems,
+                &long.header,
+                &secret_key,
+                long.message.as_slice(),
+                &mut decrypted,
+                DEFAULT_SIZE_LIMIT,
+            )
+            .unwrap();
+            assert_eq!(decrypted, vec![1u8; 1000]);
+        }
+    }
+
+    // padding is applied after compression and stripped before decompression
+    let (header, mut encryptor) = encrypt_stream(
+        &Aes256Gcm,
+        &secret,
+        &PayloadOptions {
+            segment_size: Some(100),
+            compression: Some(DEFLATE.to_string()),
+            padding: Some(Padding::Random(500)),
+        },
+        Vec::new(),
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    let message_bytes = String::from("Hello World!").repeat(100).into_bytes();
+    encryptor.write_all(&message_bytes).unwrap();
+    let cipher_text = AbeCipherText {
+        header,
+        message: encryptor.finish().unwrap(),
+    };
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).unwrap().message,
+        message_bytes
+    );
+
+    // removing the padding from the header is detected
+    let mut tampered = cipher_text.clone();
+    tampered.header.padding = None;
+    assert!(decrypt(&tampered, &secret_key).is_err());
+
+    let options = PayloadOptions {
+        padding: Some(Padding::Block(0)),
+        ..Default::default()
+    };
+    assert_eq!(
+        encrypt_with_options(
+            &Aes256Gcm,
+            &options,
+            &secret,
+            &message_bytes,
+            &public_key,
+            &access_tree,
+            rng
+        )
+        .err()
+        .unwrap()
+        .kind,
+        AbeErrorKind::InvalidPadding
+    );
+}
+
+#[test]
+fn test_delegate() {
+    let rng = &mut rand::thread_rng();
+    let attributes = ["Eng", "Senior", "OnCall"]
+        .iter()
+        .map(|a| a.to_string())
+        .collect::<Vec<String>>();
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen_delegable(&attributes, &public_key, &master_key, rng).unwrap();
+
+    let eng = vec!["Eng".to_string()];
+    let delegated = delegate(&eng, &public_key, &secret_key, rng).unwrap();
+    assert_eq!(delegated.arr_d.keys().collect_vec(), vec!["Eng"]);
+    assert_eq!(delegated.system_id, secret_key.system_id);
+    // r is re-randomized, no component is shared with the original key
+    assert_ne!(delegated.d_0, secret_key.d_0);
+    assert_ne!(delegated.arr_d["Eng"], secret_key.arr_d["Eng"]);
+
+    let secret: Gt = rng.gen();
+    let message_bytes = String::from("Hello World!").into_bytes();
+    for (policy, satisfied) in [
+        ("Eng|OnCall", true),
+        ("Senior|Eng", true),
+        ("Eng&Senior", false),
+    ] {
+        let access_tree = AccessTreeParser::new(policy).parse().unwrap();
+        let cipher_text = encrypt(&secret, &message_bytes, &public_key, &access_tree, rng).unwrap();
+        let decrypted = decrypt(&cipher_text, &delegated);
+        assert_eq!(decrypted.is_ok(), satisfied, "{}", policy);
+        if satisfied {
+            assert_eq!(decrypted.unwrap().message, message_bytes);
+        }
+    }
+
+    // delegated keys can be delegated further, but only to their own attributes
+    assert!(delegate(&eng, &public_key, &delegated, rng).is_ok());
+    let senior = vec!["Senior".to_string()];
+    assert!(delegate(&senior, &public_key, &delegated, rng).is_err());
+    assert!(delegate(&[], &public_key, &secret_key, rng).is_err());
+
+    // ordinary keys have no components for it and encode as before delegation was supported
+    let ordinary_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+    assert!(ordinary_key.arr_e.is_empty());
+    assert!(!serde_json::to_string(&ordinary_key)
+        .unwrap()
+        .contains("arr_e"));
+    assert!(delegate(&eng, &public_key, &ordinary_key, rng).is_err());
+    let mut old_key = secret_key.clone();
+    old_key.arr_e.clear();
+    assert!(delegate(&eng, &public_key, &old_key, rng).is_err());
+    assert!(keygen_delegable(&["Unknown".to_string()], &public_key, &master_key, rng).is_err());
+
+    let (other_public_key, _) = setup(&attributes, G1::one(), G2::one(), rng);
+    assert_eq!(
+        delegate(&eng, &other_public_key, &secret_key, rng)
+            .err()
+            .unwrap()
+            .kind,
+        AbeErrorKind::SystemMismatch
+    );
+}
+
+#[test]
+fn test_outsourced_decryption() {
+    let rng = &mut rand::thread_rng();
+    let attributes = vec!["A".to_string(), "B".to_string(), "C".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes[..2], &public_key, &master_key, rng).unwrap();
+    let (transformation_key, retrieval_key) = transformation_key(&secret_key, rng).unwrap();
+
+    let secret: Gt = rng.gen();
+    let message_bytes = String::from("Hello World!").into_bytes();
+    let access_tree = AccessTreeParser::new("A&(B|C)").parse().unwrap();
+    let cipher_text = encrypt(&secret, &message_bytes, &public_key, &access_tree, rng).unwrap();
+
+    // the server only sees the header and the transformation key
+    let transformed = transform(&cipher_text.header, &transformation_key).unwrap();
+    let decrypted = decrypt_transformed(
+        &BuiltinDems,
+        cipher_text.clone(),
+        &transformed,
+        &retrieval_key,
+    )
+    .unwrap();
+    assert_eq!(decrypted.message, message_bytes);
+    assert_eq!(decrypted.secret, secret);
+
+    // a wrong result of the server is detected by the mac of the header
+    let mut forged = transformed.clone();
+    forged.t = forged.t * rng.gen::<Gt>();
+    assert_eq!(
+        decrypt_transformed(&BuiltinDems, cipher_text.clone(), &forged, &retrieval_key)
+            .err()
+            .unwrap()
+            .kind,
+        AbeErrorKind::InvalidTransformation
+    );
+    let mut tampered = cipher_text.clone();
+    tampered.header.mac = Some(Fingerprint([0u8; 32]));
+    assert_eq!(
+        decrypt_transformed(&BuiltinDems, tampered.clone(), &transformed, &retrieval_key)
+            .err()
+            .unwrap()
+            .kind,
+        AbeErrorKind::InvalidTransformation
+    );
+    // headers without a mac cannot be verified
+    tampered.header.mac = None;
+    assert_eq!(
+        decrypt_transformed(&BuiltinDems, tampered.clone(), &transformed, &retrieval_key)
+            .err()
+            .unwrap()
+            .kind,
+        AbeErrorKind::VerificationFailed
+    );
+    let other_cipher_text =
+        encrypt(&secret, &message_bytes, &public_key, &access_tree, rng).unwrap();
+    assert_eq!(
+        decrypt_transformed(
+            &BuiltinDems,
+            other_cipher_text,
+            &transformed,
+            &retrieval_key
+        )
+        .err()
+        .unwrap()
+        .kind,
+        AbeErrorKind::VerificationFailed
+    );
+
+    // the transformation key only decrypts what the secret key decrypts
+    let access_tree = AccessTreeParser::new("A&C").parse().unwrap();
+    let cipher_text = encrypt(&secret, &message_bytes, &public_key, &access_tree, rng).unwrap();
+    assert!(transform(&cipher_text.header, &transformation_key).is_err());
+}
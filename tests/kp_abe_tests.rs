@@ -0,0 +1,143 @@
+use rabe_bn::{Group, Gt, G1, G2};
+use rand::Rng;
+
+use abe::access_tree::GetAttributes;
+use abe::armor::Armored;
+use abe::crypto::setup;
+use abe::dem::Aes256GcmSiv;
+use abe::errors::abe_error::AbeErrorKind;
+use abe::kp_abe::{decrypt, encrypt, encrypt_with, keygen};
+use abe::models::{AbeKpCipherText, AbeKpSecretKey};
+use abe::parser::AccessTreeParser;
+
+fn attributes(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn test_encrypt_decrypt() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = setup(
+        &attributes(&["A", "B", "C", "D"]),
+        G1::one(),
+        G2::one(),
+        rng,
+    );
+
+    let secret: Gt = rng.gen();
+    let message_bytes = String::from("Hello World!").into_bytes();
+    for (policy, labels, satisfied) in [
+        ("A", vec!["A"], true),
+        ("A&B", vec!["A", "B", "C"], true),
+        ("A&B", vec!["A", "C"], false),
+        ("(A&B)|(C&D)", vec!["C", "D"], true),
+        ("(A&B)|(A&C)", vec!["A", "C"], true),
+        ("(A|B)&(C|D)", vec!["B", "C"], true),
+        ("(A|B)&(C|D)", vec!["A", "B"], false),
+    ] {
+        let access_tree = AccessTreeParser::new(policy).parse().unwrap();
+        let secret_key = keygen(&access_tree, &public_key, &master_key, rng).unwrap();
+        let cipher_text = encrypt(
+            &secret,
+            &message_bytes,
+            &public_key,
+            &attributes(&labels),
+            rng,
+        )
+        .unwrap();
+
+        let decrypted = decrypt(&cipher_text, &secret_key);
+        assert_eq!(decrypted.is_ok(), satisfied, "{} with {:?}", policy, labels);
+        if satisfied {
+            let decrypted = decrypted.unwrap();
+            assert_eq!(decrypted.message, message_bytes);
+            assert_eq!(decrypted.secret, secret);
+        }
+    }
+}
+
+#[test]
+fn test_keys_do_not_collude() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = setup(&attributes(&["A", "B"]), G1::one(), G2::one(), rng);
+
+    // two keys that each hold half of "A&B" cannot be combined
+    let and_tree = AccessTreeParser::new("A&B").parse().unwrap();
+    let first = keygen(&and_tree, &public_key, &master_key, rng).unwrap();
+    let second = keygen(&and_tree, &public_key, &master_key, rng).unwrap();
+    let mut combined = first.clone();
+    combined.arr_d[1] = second.arr_d[1].clone();
+
+    let secret: Gt = rng.gen();
+    let cipher_text = encrypt(
+        &secret,
+        b"Hello World!",
+        &public_key,
+        &attributes(&["A", "B"]),
+        rng,
+    )
+    .unwrap();
+    assert!(decrypt(&cipher_text, &first).is_ok());
+    assert!(decrypt(&cipher_text, &combined).is_err());
+}
+
+#[test]
+fn test_mismatches() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = setup(&attributes(&["A", "B"]), G1::one(), G2::one(), rng);
+    let (other_public_key, _) = setup(&attributes(&["A", "B"]), G1::one(), G2::one(), rng);
+    let access_tree = AccessTreeParser::new("A|B").parse().unwrap();
+    let secret_key = keygen(&access_tree, &public_key, &master_key, rng).unwrap();
+    let secret: Gt = rng.gen();
+
+    // the shares of alpha are not part of the key
+    assert!(secret_key
+        .access_tree
+        .get_attributes()
+        .iter()
+        .all(|leaf| leaf.value.is_none()));
+
+    let cipher_text = encrypt(
+        &secret,
+        b"Hello World!",
+        &other_public_key,
+        &attributes(&["A"]),
+        rng,
+    )
+    .unwrap();
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+        AbeErrorKind::SystemMismatch
+    );
+
+    assert!(encrypt(&secret, b"", &public_key, &attributes(&["Z"]), rng).is_err());
+    assert!(encrypt(&secret, b"", &public_key, &[], rng).is_err());
+}
+
+#[test]
+fn test_serialization() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = setup(&attributes(&["A", "B"]), G1::one(), G2::one(), rng);
+    let access_tree = AccessTreeParser::new("A&B").parse().unwrap();
+    let secret_key = keygen(&access_tree, &public_key, &master_key, rng).unwrap();
+
+    let secret: Gt = rng.gen();
+    let cipher_text = encrypt_with(
+        &Aes256GcmSiv,
+        &secret,
+        b"Hello World!",
+        &public_key,
+        &attributes(&["A", "B"]),
+        rng,
+    )
+    .unwrap();
+    assert_eq!(cipher_text.header.algorithm, Aes256GcmSiv::ALGORITHM);
+
+    let secret_key = AbeKpSecretKey::from_armored(&secret_key.to_armored().unwrap()).unwrap();
+    let cipher_text: AbeKpCipherText =
+        serde_json::from_str(&serde_json::to_string(&cipher_text).unwrap()).unwrap();
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).unwrap().message,
+        b"Hello World!"
+    );
+}
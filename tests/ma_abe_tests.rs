@@ -0,0 +1,307 @@
+use rabe_bn::{Fr, Group, Gt, G1, G2};
+use rand::Rng;
+
+use abe::access_tree::GetAttributes;
+use abe::armor::Armored;
+use abe::dem::Aes256GcmSiv;
+use abe::errors::abe_error::AbeErrorKind;
+use abe::ma_abe::{authority_setup, combine, decrypt, encrypt, encrypt_with, global_setup, keygen};
+use abe::models::{AbeMaCipherText, AbeMaPublicKey, AbeMaSecretKey};
+use abe::parser::AccessTreeParser;
+
+fn attributes(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn test_encrypt_decrypt() {
+    let rng = &mut rand::thread_rng();
+    let parameters = global_setup(G1::one(), G2::one());
+    let (hr_public_key, hr_master_key) =
+        authority_setup(&parameters, "hr", &attributes(&["manager", "intern"]), rng).unwrap();
+    let (eng_public_key, eng_master_key) =
+        authority_setup(&parameters, "eng", &attributes(&["senior", "oncall"]), rng).unwrap();
+    assert!(hr_public_key.attributes.contains_key("hr:manager"));
+    let public_keys = [hr_public_key, eng_public_key];
+
+    let secret: Gt = rng.gen();
+    let message_bytes = String::from("Hello World!").into_bytes();
+    for (policy, hr_labels, eng_labels, satisfied) in [
+        ("hr:manager", vec!["hr:manager"], vec![], true),
+        ("hr:manager", vec!["hr:intern"], vec![], false),
+        (
+            "hr:manager&eng:senior",
+            vec!["hr:manager"],
+            vec!["eng:senior"],
+            true,
+        ),
+        ("hr:manager&eng:senior", vec!["hr:manager"], vec![], false),
+        (
+            "hr:manager|(hr:intern&eng:oncall)",
+            vec!["hr:intern"],
+            vec!["eng:oncall"],
+            true,
+        ),
+        (
+            "(hr:manager|eng:senior)&(hr:intern|eng:oncall)",
+            vec![],
+            vec!["eng:senior", "eng:oncall"],
+            true,
+        ),
+        (
+            "(hr:manager|eng:senior)&(hr:intern|eng:oncall)",
+            vec!["hr:manager"],
+            vec!["eng:senior"],
+            false,
+        ),
+    ] {
+        let mut keys = Vec::new();
+        if !hr_labels.is_empty() {
+            keys.push(
+                keygen(
+                    "alice",
+                    &attributes(&hr_labels),
+                    &parameters,
+                    &hr_master_key,
+                )
+                .unwrap(),
+            );
+        }
+        if !eng_labels.is_empty() {
+            keys.push(
+                keygen(
+                    "alice",
+                    &attributes(&eng_labels),
+                    &parameters,
+                    &eng_master_key,
+                )
+                .unwrap(),
+            );
+        }
+        let secret_key = combine(&keys).unwrap();
+
+        let access_tree = AccessTreeParser::new(policy).parse().unwrap();
+        let cipher_text = encrypt(
+            &secret,
+            &message_bytes,
+            &parameters,
+            &public_keys,
+            &access_tree,
+            rng,
+        )
+        .unwrap();
+
+        let decrypted = decrypt(&cipher_text, &secret_key);
+        assert_eq!(
+            decrypted.is_ok(),
+            satisfied,
+            "{} with {:?} {:?}",
+            policy,
+            hr_labels,
+            eng_labels
+        );
+        if satisfied {
+            let decrypted = decrypted.unwrap();
+            assert_eq!(decrypted.message, message_bytes);
+            assert_eq!(decrypted.secret, secret);
+        }
+    }
+}
+
+#[test]
+fn test_keys_do_not_collude() {
+    let rng = &mut rand::thread_rng();
+    let parameters = global_setup(G1::one(), G2::one());
+    let (hr_public_key, hr_master_key) =
+        authority_setup(&parameters, "hr", &attributes(&["manager"]), rng).unwrap();
+    let (eng_public_key, eng_master_key) =
+        authority_setup(&parameters, "eng", &attributes(&["senior"]), rng).unwrap();
+
+    // keys of alice and bob cannot be combined to satisfy "hr:manager&eng:senior"
+    let alice = keygen(
+        "alice",
+        &attributes(&["hr:manager"]),
+        &parameters,
+        &hr_master_key,
+    )
+    .unwrap();
+    let bob = keygen(
+        "bob",
+        &attributes(&["eng:senior"]),
+        &parameters,
+        &eng_master_key,
+    )
+    .unwrap();
+    assert_eq!(
+        combine(&[alice.clone(), bob.clone()]).err().unwrap().kind,
+        AbeErrorKind::IdentityMismatch
+    );
+
+    let mut combined = alice.clone();
+    combined
+        .arr_k
+        .insert("eng:senior".to_string(), bob.arr_k["eng:senior"]);
+
+    let secret: Gt = rng.gen();
+    let access_tree = AccessTreeParser::new("hr:manager&eng:senior")
+        .parse()
+        .unwrap();
+    let cipher_text = encrypt(
+        &secret,
+        b"Hello World!",
+        &parameters,
+        &[hr_public_key, eng_public_key],
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    assert!(decrypt(&cipher_text, &combined).is_err());
+}
+
+#[test]
+fn test_mismatches() {
+    let rng = &mut rand::thread_rng();
+    let parameters = global_setup(G1::one(), G2::one());
+    let other_parameters = global_setup(G1::one() * rng.gen::<Fr>(), G2::one());
+    let (public_key, master_key) =
+        authority_setup(&parameters, "hr", &attributes(&["manager", "intern"]), rng).unwrap();
+    let (other_public_key, _) =
+        authority_setup(&other_parameters, "hr", &attributes(&["manager"]), rng).unwrap();
+    let secret_key = keygen(
+        "alice",
+        &attributes(&["hr:manager"]),
+        &parameters,
+        &master_key,
+    )
+    .unwrap();
+    let access_tree = AccessTreeParser::new("hr:manager|hr:intern")
+        .parse()
+        .unwrap();
+    let secret: Gt = rng.gen();
+
+    // the shares of s are not part of the cipher text
+    let public_keys = [public_key.clone()];
+    let cipher_text = encrypt(
+        &secret,
+        b"Hello World!",
+        &parameters,
+        &public_keys,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    assert!(cipher_text
+        .header
+        .access_tree
+        .get_attributes()
+        .iter()
+        .all(|leaf| leaf.value.is_none()));
+
+    let mut cipher_text = cipher_text;
+    cipher_text.header.arr_c3.pop();
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+        AbeErrorKind::LeafMismatch
+    );
+
+    // public keys of other systems and attributes without authority are refused
+    let err = encrypt(
+        &secret,
+        b"Hello World!",
+        &parameters,
+        &[other_public_key],
+        &access_tree,
+        rng,
+    )
+    .err()
+    .unwrap();
+    assert_eq!(err.kind, AbeErrorKind::SystemMismatch);
+    let eng_tree = AccessTreeParser::new("hr:manager&eng:senior")
+        .parse()
+        .unwrap();
+    assert!(encrypt(
+        &secret,
+        b"Hello World!",
+        &parameters,
+        &public_keys,
+        &eng_tree,
+        rng
+    )
+    .is_err());
+
+    // every attribute may appear once in the policy only
+    let repeated_tree = AccessTreeParser::new("(hr:manager&hr:intern)|hr:manager")
+        .parse()
+        .unwrap();
+    assert!(encrypt(
+        &secret,
+        b"Hello World!",
+        &parameters,
+        &public_keys,
+        &repeated_tree,
+        rng
+    )
+    .is_err());
+
+    let mut other_secret_key = secret_key.clone();
+    other_secret_key.system_id = other_parameters.system_id();
+    assert_eq!(
+        combine(&[secret_key.clone(), other_secret_key])
+            .err()
+            .unwrap()
+            .kind,
+        AbeErrorKind::SystemMismatch
+    );
+
+    assert!(keygen("alice", &[], &parameters, &master_key).is_err());
+    assert!(keygen(
+        "alice",
+        &attributes(&["eng:senior"]),
+        &parameters,
+        &master_key
+    )
+    .is_err());
+    assert!(combine(&[]).is_err());
+    assert!(authority_setup(&parameters, "h:r", &attributes(&["manager"]), rng).is_err());
+    assert!(authority_setup(&parameters, "", &attributes(&["manager"]), rng).is_err());
+    assert!(authority_setup(&parameters, "hr", &[], rng).is_err());
+}
+
+#[test]
+fn test_serialization() {
+    let rng = &mut rand::thread_rng();
+    let parameters = global_setup(G1::one(), G2::one());
+    let (public_key, master_key) =
+        authority_setup(&parameters, "hr", &attributes(&["manager", "intern"]), rng).unwrap();
+    let secret_key = keygen(
+        "alice",
+        &attributes(&["hr:manager", "hr:intern"]),
+        &parameters,
+        &master_key,
+    )
+    .unwrap();
+
+    let access_tree = AccessTreeParser::new("hr:manager&hr:intern")
+        .parse()
+        .unwrap();
+    let secret: Gt = rng.gen();
+    let public_key = AbeMaPublicKey::from_armored(&public_key.to_armored().unwrap()).unwrap();
+    let cipher_text = encrypt_with(
+        &Aes256GcmSiv,
+        &secret,
+        b"Hello World!",
+        &parameters,
+        &[public_key],
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    assert_eq!(cipher_text.header.algorithm, Aes256GcmSiv::ALGORITHM);
+
+    let secret_key = AbeMaSecretKey::from_armored(&secret_key.to_armored().unwrap()).unwrap();
+    let cipher_text = AbeMaCipherText::from_armored(&cipher_text.to_armored().unwrap()).unwrap();
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).unwrap().message,
+        b"Hello World!"
+    );
+}
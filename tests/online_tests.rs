@@ -0,0 +1,231 @@
+use rabe_bn::{Fr, Group, Gt, G1, G2};
+use rand::Rng;
+
+use abe::access_tree::GetAttributes;
+use abe::crypto::{keygen, setup};
+use abe::dem::Aes256GcmSiv;
+use abe::errors::abe_error::AbeErrorKind;
+use abe::models::{AbeIntermediate, AbeOnlineCipherText};
+use abe::online::{decrypt, encrypt, encrypt_with, precompute, precompute_pool};
+use abe::parser::AccessTreeParser;
+
+fn attributes(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn test_encrypt_decrypt() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = setup(
+        &attributes(&["A", "B", "C", "D", "Eng", "Senior", "OnCall"]),
+        G1::one(),
+        G2::one(),
+        rng,
+    );
+    let mut pool = precompute_pool(&public_key, 8, 2, rng);
+    assert_eq!(pool.len(), 8);
+
+    let secret: Gt = rng.gen();
+    let message_bytes = String::from("Hello World!").into_bytes();
+    for (policy, labels, satisfied) in [
+        ("Eng", vec!["Eng"], true),
+        ("Eng&Senior", vec!["Eng", "Senior", "OnCall"], true),
+        ("Eng&Senior", vec!["Eng", "OnCall"], false),
+        ("(A&B)|(A&C)", vec!["A", "C"], true),
+        ("(A|B)&(C|D)", vec!["B", "C"], true),
+        ("(A|B)&(C|D)", vec!["A", "B"], false),
+        ("(A|D)&(B|C)&C&A", vec!["A", "B", "C"], true),
+    ] {
+        let access_tree = AccessTreeParser::new(policy).parse().unwrap();
+        let secret_key = keygen(&attributes(&labels), &public_key, &master_key, rng).unwrap();
+        let intermediate = pool.pop().unwrap();
+        let cipher_text = encrypt(
+            intermediate,
+            &secret,
+            &message_bytes,
+            &public_key,
+            &access_tree,
+            rng,
+        )
+        .unwrap();
+
+        let decrypted = decrypt(&cipher_text, &secret_key);
+        assert_eq!(decrypted.is_ok(), satisfied, "{} with {:?}", policy, labels);
+        if satisfied {
+            let decrypted = decrypted.unwrap();
+            assert_eq!(decrypted.message, message_bytes);
+            assert_eq!(decrypted.secret, secret);
+        }
+    }
+}
+
+#[test]
+fn test_pieces_run_out() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, _) = setup(&attributes(&["A", "B"]), G1::one(), G2::one(), rng);
+    let secret: Gt = rng.gen();
+
+    // every attribute has one piece, so it can be used by one leaf only
+    let access_tree = AccessTreeParser::new("(A&B)|A").parse().unwrap();
+    let intermediate = precompute(&public_key, 1, rng);
+    assert!(encrypt(
+        intermediate,
+        &secret,
+        b"Hello World!",
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .is_err());
+
+    let access_tree = AccessTreeParser::new("A&C").parse().unwrap();
+    let intermediate = precompute(&public_key, 1, rng);
+    assert!(encrypt(
+        intermediate,
+        &secret,
+        b"Hello World!",
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_mismatches() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = setup(&attributes(&["A", "B"]), G1::one(), G2::one(), rng);
+    let (other_public_key, _) = setup(&attributes(&["A", "B"]), G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes(&["A"]), &public_key, &master_key, rng).unwrap();
+    let access_tree = AccessTreeParser::new("A|B").parse().unwrap();
+    let secret: Gt = rng.gen();
+
+    // the shares of s are not part of the cipher text
+    let intermediate = precompute(&public_key, 1, rng);
+    let cipher_text = encrypt(
+        intermediate,
+        &secret,
+        b"Hello World!",
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    assert!(cipher_text
+        .header
+        .access_tree
+        .get_attributes()
+        .iter()
+        .all(|leaf| leaf.value.is_none()));
+
+    let intermediate = precompute(&other_public_key, 1, rng);
+    assert_eq!(
+        encrypt(
+            intermediate,
+            &secret,
+            b"Hello World!",
+            &public_key,
+            &access_tree,
+            rng,
+        )
+        .err()
+        .unwrap()
+        .kind,
+        AbeErrorKind::SystemMismatch
+    );
+
+    let intermediate = precompute(&other_public_key, 1, rng);
+    let cipher_text = encrypt(
+        intermediate,
+        &secret,
+        b"Hello World!",
+        &other_public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+        AbeErrorKind::SystemMismatch
+    );
+
+    // the system parameters in the header are checked against the system identifier
+    let intermediate = precompute(&public_key, 1, rng);
+    let mut cipher_text = encrypt(
+        intermediate,
+        &secret,
+        b"Hello World!",
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    cipher_text.header.y = cipher_text.header.y.pow(rng.gen());
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+        AbeErrorKind::SystemMismatch
+    );
+    cipher_text.header.y = public_key.y;
+    cipher_text.header.g1 = public_key.g1 * rng.gen::<Fr>();
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+        AbeErrorKind::SystemMismatch
+    );
+
+    let intermediate = precompute(&public_key, 1, rng);
+    let mut cipher_text = encrypt(
+        intermediate,
+        &secret,
+        b"Hello World!",
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    cipher_text.header.arr_delta.pop();
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+        AbeErrorKind::LeafMismatch
+    );
+}
+
+#[test]
+fn test_serialization() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) =
+        setup(&attributes(&["Eng", "Senior"]), G1::one(), G2::one(), rng);
+    let secret_key = keygen(
+        &attributes(&["Eng", "Senior"]),
+        &public_key,
+        &master_key,
+        rng,
+    )
+    .unwrap();
+
+    // a pool survives being stored on the device
+    let intermediate = precompute(&public_key, 1, rng);
+    let export = intermediate.export().unwrap();
+    assert!(export.starts_with("-----BEGIN ABE INTERMEDIATE CIPHERTEXT-----"));
+    let intermediate = AbeIntermediate::import(&export).unwrap();
+
+    let access_tree = AccessTreeParser::new("Eng&Senior").parse().unwrap();
+    let secret: Gt = rng.gen();
+    let cipher_text = encrypt_with(
+        &Aes256GcmSiv,
+        intermediate,
+        &secret,
+        b"Hello World!",
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    assert_eq!(cipher_text.header.algorithm, Aes256GcmSiv::ALGORITHM);
+
+    let cipher_text: AbeOnlineCipherText =
+        serde_json::from_str(&serde_json::to_string(&cipher_text).unwrap()).unwrap();
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).unwrap().message,
+        b"Hello World!"
+    );
+}
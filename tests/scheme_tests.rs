@@ -0,0 +1,190 @@
+use rabe_bn::Gt;
+use rand::Rng;
+
+use abe::access_tree::AccessTree;
+use abe::armor::Armored;
+use abe::canonical::Fingerprinted;
+use abe::dem::{Aes256Gcm, BuiltinDems};
+use abe::errors::abe_error::AbeErrorKind;
+use abe::models::AbePublicKey;
+use abe::scheme::{
+    AbeScheme, CpAbe, HiddenAbe, KpAbe, LuAbe, MaAbe, MaKeyInput, OnlineAbe, OutsourcedAbe,
+    SchemeInput, SchemeKey, WatersAbe,
+};
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+// runs a scheme through its interface only, with keys and cipher texts serialized in between
+fn check_scheme<S: AbeScheme>(
+    attributes: &[&str],
+    key_input: &str,
+    satisfied: &str,
+    not_satisfied: &str,
+) {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = S::setup(&strings(attributes), rng).unwrap();
+    let public_key = SchemeKey::new::<S>(public_key).to_armored().unwrap();
+    let public_key = SchemeKey::<S::PublicKey>::from_armored(&public_key).unwrap();
+    assert_eq!(public_key.scheme, S::NAME);
+
+    let input = S::KeyInput::parse(&strings(&[key_input])).unwrap();
+    let secret_key = S::keygen(&input, &public_key.key, &master_key, rng).unwrap();
+    let secret_key = serde_json::to_string(&SchemeKey::new::<S>(secret_key)).unwrap();
+    let secret_key = serde_json::from_str::<SchemeKey<S::SecretKey>>(&secret_key).unwrap();
+
+    let secret: Gt = rng.gen();
+    for (input, expected) in [(satisfied, true), (not_satisfied, false)] {
+        let input = S::EncryptInput::parse(&strings(&[input])).unwrap();
+        let cipher_text = S::encrypt(
+            &Aes256Gcm,
+            &secret,
+            b"Hello World!",
+            &public_key.key,
+            &input,
+            rng,
+        )
+        .unwrap();
+        let fingerprint = cipher_text.fingerprint();
+        let cipher_text = S::CipherText::from_armored(&cipher_text.to_armored().unwrap()).unwrap();
+        assert_eq!(cipher_text.fingerprint(), fingerprint, "{}", S::NAME);
+        S::validate(&BuiltinDems, &cipher_text).unwrap();
+
+        let decrypted = S::decrypt(&cipher_text, &secret_key.key);
+        assert_eq!(decrypted.is_ok(), expected, "{}", S::NAME);
+        if expected {
+            assert_eq!(decrypted.unwrap().message, b"Hello World!");
+        }
+    }
+}
+
+#[test]
+fn test_schemes() {
+    let attributes = ["A", "B", "C"];
+    check_scheme::<CpAbe>(&attributes, "A B", "A&B", "A&C");
+    check_scheme::<KpAbe>(&attributes, "A&B", "A, B", "A C");
+    check_scheme::<LuAbe>(&attributes, "A B", "A&B", "A&C");
+    check_scheme::<WatersAbe>(&attributes, "A B", "A&B", "A&C");
+    check_scheme::<OnlineAbe>(&attributes, "A B", "A&(B|A)", "A&C");
+    check_scheme::<OutsourcedAbe>(&attributes, "A B", "A&B", "A&C");
+
+    let attributes = ["hr:A", "hr:B", "hr:C"];
+    check_scheme::<MaAbe>(&attributes, "alice hr:A hr:B", "hr:A&hr:B", "hr:A&hr:C");
+    check_scheme::<HiddenAbe>(&attributes, "hr:A hr:B", "hr:A&hr:B", "hr:A&hr:C");
+}
+
+#[test]
+fn test_rewrap() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = CpAbe::setup(&strings(&["A", "B"]), rng).unwrap();
+    let secret_key = CpAbe::keygen(&strings(&["A"]), &public_key, &master_key, rng).unwrap();
+    let access_tree = AccessTree::parse(&strings(&["A"])).unwrap();
+    let secret: Gt = rng.gen();
+    let cipher_text = CpAbe::encrypt(
+        &Aes256Gcm,
+        &secret,
+        b"Hello World!",
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+
+    let new_tree = AccessTree::parse(&strings(&["B"])).unwrap();
+    let envelope = CpAbe::rewrap(
+        &BuiltinDems,
+        cipher_text,
+        &secret_key,
+        &public_key,
+        &new_tree,
+        rng,
+    )
+    .unwrap();
+    assert_eq!(envelope.header.recipients.len(), 1);
+
+    let (lu_public_key, lu_master_key) = LuAbe::setup(&[], rng).unwrap();
+    let lu_secret_key =
+        LuAbe::keygen(&strings(&["A"]), &lu_public_key, &lu_master_key, rng).unwrap();
+    let cipher_text = LuAbe::encrypt(
+        &Aes256Gcm,
+        &secret,
+        b"Hello World!",
+        &lu_public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    let error = LuAbe::rewrap(
+        &BuiltinDems,
+        cipher_text,
+        &lu_secret_key,
+        &public_key,
+        &new_tree,
+        rng,
+    )
+    .unwrap_err();
+    assert_eq!(error.kind, AbeErrorKind::Unsupported);
+}
+
+#[test]
+fn test_delegate() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = HiddenAbe::setup(&strings(&["hr:A", "hr:B"]), rng).unwrap();
+    let input = strings(&["hr:A", "hr:B"]);
+
+    let secret_key = HiddenAbe::keygen(&input, &public_key, &master_key, rng).unwrap();
+    let error = HiddenAbe::delegate(&strings(&["hr:A"]), &public_key, &secret_key, rng);
+    assert!(error.is_err());
+
+    let secret_key = HiddenAbe::keygen_delegable(&input, &public_key, &master_key, rng).unwrap();
+    let delegated =
+        HiddenAbe::delegate(&strings(&["hr:A"]), &public_key, &secret_key, rng).unwrap();
+    assert_eq!(delegated.arr_d.keys().collect::<Vec<_>>(), vec!["hr:A"]);
+
+    let (public_key, master_key) = KpAbe::setup(&strings(&["A"]), rng).unwrap();
+    let access_tree = AccessTree::parse(&strings(&["A"])).unwrap();
+    let error = KpAbe::keygen_delegable(&access_tree, &public_key, &master_key, rng).unwrap_err();
+    assert_eq!(error.kind, AbeErrorKind::Unsupported);
+}
+
+#[test]
+fn test_ma_setup() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, _) = MaAbe::setup(&strings(&["hr:A", "hr:B"]), rng).unwrap();
+    assert_eq!(public_key.authority, "hr");
+    assert!(MaAbe::setup(&strings(&["hr:A", "it:B"]), rng).is_err());
+    assert!(MaAbe::setup(&strings(&["A"]), rng).is_err());
+
+    assert_eq!(
+        MaKeyInput::parse(&strings(&["alice", "hr:A hr:B"])).unwrap(),
+        MaKeyInput {
+            gid: "alice".to_string(),
+            attributes: strings(&["hr:A", "hr:B"]),
+        }
+    );
+    assert!(MaKeyInput::parse(&strings(&["alice"])).is_err());
+}
+
+#[test]
+fn test_keys_without_scheme_are_cp() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, _) = CpAbe::setup(&strings(&["A"]), rng).unwrap();
+
+    let key = serde_json::from_str::<SchemeKey<AbePublicKey>>(
+        &serde_json::to_string(&public_key).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(key.scheme, CpAbe::NAME);
+    assert_eq!(key.key, public_key);
+}
+
+#[test]
+fn test_inputs() {
+    assert_eq!(
+        Vec::<String>::parse(&strings(&["A, B", "C"])).unwrap(),
+        strings(&["A", "B", "C"])
+    );
+    assert!(Vec::<String>::parse(&strings(&[" , "])).is_err());
+    assert!(CpAbe::setup(&[], &mut rand::thread_rng()).is_err());
+}
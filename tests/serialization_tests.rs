@@ -0,0 +1,145 @@
+use rabe_bn::{Group, Gt, G1, G2};
+use rand::Rng;
+
+use abe::armor::Armored;
+use abe::canonical::Fingerprinted;
+use abe::crypto::{decrypt, decrypt_envelope, encrypt, encrypt_envelope, keygen, setup};
+use abe::dem::{Aes256Gcm, BuiltinDems};
+use abe::errors::abe_error::AbeErrorKind;
+use abe::models::{
+    AbeCipherText, AbeDetachedHeader, AbeEnvelope, AbeMasterKey, AbePublicKey, AbeSecretKey,
+};
+use abe::parser::AccessTreeParser;
+
+#[test]
+fn test_serialization_is_deterministic() {
+    let rng = &mut rand::thread_rng();
+    let attributes = vec!["A".to_string(), "B".to_string(), "C".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+
+    let first = serde_json::to_string(&public_key).unwrap();
+    let second = serde_json::to_string(&public_key.clone()).unwrap();
+    assert_eq!(first, second);
+
+    // a deserialized key serializes to the same bytes
+    let roundtrip = serde_json::from_str::<AbePublicKey>(&first).unwrap();
+    assert_eq!(first, serde_json::to_string(&roundtrip).unwrap());
+
+    let first = serde_json::to_string(&secret_key).unwrap();
+    let roundtrip = serde_json::from_str::<AbeSecretKey>(&first).unwrap();
+    assert_eq!(first, serde_json::to_string(&roundtrip).unwrap());
+}
+
+#[test]
+fn test_fingerprints_survive_roundtrip() {
+    let rng = &mut rand::thread_rng();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+
+    let tree = AccessTreeParser::new("A&B").parse().unwrap();
+    let secret: Gt = rng.gen();
+    let message = String::from("Hello World!").into_bytes();
+    let cipher_text = encrypt(&secret, &message, &public_key, &tree, rng).unwrap();
+
+    let public_roundtrip =
+        serde_json::from_str::<AbePublicKey>(&serde_json::to_string(&public_key).unwrap()).unwrap();
+    let secret_roundtrip =
+        serde_json::from_str::<AbeSecretKey>(&serde_json::to_string(&secret_key).unwrap()).unwrap();
+    let cipher_roundtrip =
+        serde_json::from_str::<AbeCipherText>(&serde_json::to_string(&cipher_text).unwrap())
+            .unwrap();
+
+    assert_eq!(public_key.fingerprint(), public_roundtrip.fingerprint());
+    assert_eq!(secret_key.fingerprint(), secret_roundtrip.fingerprint());
+    assert_eq!(cipher_text.fingerprint(), cipher_roundtrip.fingerprint());
+}
+
+#[test]
+fn test_fingerprints_differ_between_keys() {
+    let rng = &mut rand::thread_rng();
+    let attributes = vec!["A".to_string()];
+    let (first, _) = setup(&attributes, G1::one(), G2::one(), rng);
+    let (second, _) = setup(&attributes, G1::one(), G2::one(), rng);
+
+    assert_ne!(first.fingerprint(), second.fingerprint());
+}
+
+#[test]
+fn test_detached_header_roundtrip() {
+    let rng = &mut rand::thread_rng();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+
+    let tree = AccessTreeParser::new("A|B").parse().unwrap();
+    let secret: Gt = rng.gen();
+    let message = String::from("Hello World!").into_bytes();
+    let cipher_text = encrypt(&secret, &message, &public_key, &tree, rng).unwrap();
+
+    let (header, payload) = cipher_text.clone().detach();
+    let header =
+        serde_json::from_str::<AbeDetachedHeader>(&serde_json::to_string(&header).unwrap())
+            .unwrap();
+
+    // a payload that was tampered with is rejected
+    let mut tampered = payload.clone();
+    tampered[0] ^= 1;
+    let error = AbeCipherText::attach(header.clone(), tampered).unwrap_err();
+    assert_eq!(error.kind, AbeErrorKind::PayloadMismatch);
+
+    let attached = AbeCipherText::attach(header, payload).unwrap();
+    assert_eq!(attached, cipher_text);
+    assert_eq!(decrypt(&attached, &secret_key).unwrap().message, message);
+}
+
+#[test]
+fn test_armored_roundtrip() {
+    let rng = &mut rand::thread_rng();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+
+    let armored = secret_key.to_armored().unwrap();
+    assert!(armored.starts_with("-----BEGIN ABE SECRET KEY-----"));
+    assert_eq!(AbeSecretKey::from_armored(&armored).unwrap(), secret_key);
+
+    // the label has to match the requested type
+    assert!(AbePublicKey::from_armored(&armored).is_err());
+
+    let armored = master_key.to_armored().unwrap();
+    assert_eq!(AbeMasterKey::from_armored(&armored).unwrap(), master_key);
+}
+
+#[test]
+fn test_envelope_roundtrip() {
+    let rng = &mut rand::thread_rng();
+    let attributes = vec!["A".to_string(), "B".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+    let access_tree = AccessTreeParser::new("A&B").parse().unwrap();
+
+    let envelope = encrypt_envelope(
+        &Aes256Gcm,
+        b"Hello World!",
+        &[(&public_key, &access_tree)],
+        rng,
+    )
+    .unwrap();
+
+    let armored = envelope.to_armored().unwrap();
+    assert!(armored.starts_with("-----BEGIN ABE ENVELOPE-----"));
+    let deserialized = AbeEnvelope::from_armored(&armored).unwrap();
+    assert_eq!(deserialized.fingerprint(), envelope.fingerprint());
+    assert_eq!(
+        decrypt_envelope(&BuiltinDems, deserialized, &secret_key).unwrap(),
+        b"Hello World!"
+    );
+
+    let json = serde_json::to_string(&envelope).unwrap();
+    assert_eq!(
+        serde_json::from_str::<AbeEnvelope>(&json).unwrap(),
+        envelope
+    );
+}
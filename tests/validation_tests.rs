@@ -0,0 +1,106 @@
+use rabe_bn::{Group, Gt, G1, G2};
+use rand::Rng;
+
+use abe::access_tree::AccessTree;
+use abe::crypto::{decrypt, encrypt, keygen, setup};
+use abe::dem::BuiltinDems;
+use abe::errors::abe_error::AbeErrorKind;
+use abe::models::{AbeCipherText, AbeSecretKey};
+use abe::parser::AccessTreeParser;
+
+fn cipher_text_and_key(policy: &str) -> (AbeCipherText, AbeSecretKey) {
+    let rng = &mut rand::thread_rng();
+    let attributes = vec!["A".to_string(), "B".to_string(), "C".to_string()];
+    let (public_key, master_key) = setup(&attributes, G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes, &public_key, &master_key, rng).unwrap();
+
+    let tree = AccessTreeParser::new(policy).parse().unwrap();
+    let secret: Gt = rng.gen();
+    let message = String::from("Hello World!").into_bytes();
+    let cipher_text = encrypt(&secret, &message, &public_key, &tree, rng).unwrap();
+
+    (cipher_text, secret_key)
+}
+
+#[test]
+fn test_valid_cipher_text() {
+    let (cipher_text, _) = cipher_text_and_key("(A&B)|C");
+
+    assert!(cipher_text.validate(&BuiltinDems).is_ok());
+}
+
+#[test]
+fn test_truncated_arr_c() {
+    let (mut cipher_text, secret_key) = cipher_text_and_key("A&B&C");
+    cipher_text.header.arr_c.pop();
+
+    assert_eq!(
+        cipher_text.validate(&BuiltinDems).unwrap_err().kind,
+        AbeErrorKind::LeafMismatch
+    );
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+        AbeErrorKind::LeafMismatch
+    );
+}
+
+#[test]
+fn test_swapped_arr_c() {
+    let (mut cipher_text, _) = cipher_text_and_key("A&B");
+    cipher_text.header.arr_c.swap(0, 1);
+
+    assert_eq!(
+        cipher_text.validate(&BuiltinDems).unwrap_err().kind,
+        AbeErrorKind::LeafMismatch
+    );
+}
+
+#[test]
+fn test_duplicate_index() {
+    let (mut cipher_text, _) = cipher_text_and_key("A&A");
+    cipher_text.header.arr_c[1].0.index = Some(0);
+    if let AccessTree::Operator(operator) = cipher_text.header.access_tree.as_mut() {
+        if let AccessTree::Leaf(leaf) = operator.right.as_mut() {
+            leaf.attribute.index = Some(0);
+        }
+    }
+
+    assert_eq!(
+        cipher_text.validate(&BuiltinDems).unwrap_err().kind,
+        AbeErrorKind::DuplicateIndex
+    );
+}
+
+#[test]
+fn test_identity_element() {
+    let (mut cipher_text, _) = cipher_text_and_key("A|B");
+    cipher_text.header.arr_c[0].1 = G1::zero();
+
+    assert_eq!(
+        cipher_text.validate(&BuiltinDems).unwrap_err().kind,
+        AbeErrorKind::IdentityElement
+    );
+
+    let (mut cipher_text, _) = cipher_text_and_key("A|B");
+    cipher_text.header.c_0 = G1::zero();
+
+    assert_eq!(
+        cipher_text.validate(&BuiltinDems).unwrap_err().kind,
+        AbeErrorKind::IdentityElement
+    );
+}
+
+#[test]
+fn test_short_payload() {
+    let (mut cipher_text, secret_key) = cipher_text_and_key("A|B");
+    cipher_text.message.truncate(8);
+
+    assert_eq!(
+        cipher_text.validate(&BuiltinDems).unwrap_err().kind,
+        AbeErrorKind::PayloadTooShort
+    );
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+        AbeErrorKind::PayloadTooShort
+    );
+}
@@ -0,0 +1,140 @@
+use rabe_bn::{Group, Gt, G1, G2};
+use rand::Rng;
+
+use abe::access_tree::GetAttributes;
+use abe::armor::Armored;
+use abe::dem::Aes256GcmSiv;
+use abe::errors::abe_error::AbeErrorKind;
+use abe::models::{AbeWatersCipherText, AbeWatersSecretKey};
+use abe::parser::AccessTreeParser;
+use abe::waters::{decrypt, encrypt, encrypt_with, keygen, setup};
+
+fn attributes(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn test_encrypt_decrypt() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = setup(G1::one(), G2::one(), rng);
+
+    let secret: Gt = rng.gen();
+    let message_bytes = String::from("Hello World!").into_bytes();
+    for (policy, labels, satisfied) in [
+        ("Eng", vec!["Eng"], true),
+        ("Eng", vec!["Ops"], false),
+        ("Eng&Senior", vec!["Eng", "Senior", "OnCall"], true),
+        ("Eng&Senior", vec!["Eng", "OnCall"], false),
+        ("(A&B)|(A&C)", vec!["A", "C"], true),
+        ("(A|B)&(C|D)", vec!["B", "C"], true),
+        ("(A|B)&(C|D)", vec!["A", "B"], false),
+        ("(A|D)&(B|E)&C&A", vec!["A", "B", "C"], true),
+    ] {
+        let access_tree = AccessTreeParser::new(policy).parse().unwrap();
+        let secret_key = keygen(&attributes(&labels), &public_key, &master_key, rng).unwrap();
+        let cipher_text = encrypt(&secret, &message_bytes, &public_key, &access_tree, rng).unwrap();
+
+        let decrypted = decrypt(&cipher_text, &secret_key);
+        assert_eq!(decrypted.is_ok(), satisfied, "{} with {:?}", policy, labels);
+        if satisfied {
+            let decrypted = decrypted.unwrap();
+            assert_eq!(decrypted.message, message_bytes);
+            assert_eq!(decrypted.secret, secret);
+        }
+    }
+}
+
+#[test]
+fn test_keys_do_not_collude() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = setup(G1::one(), G2::one(), rng);
+
+    // keys for "Eng" and "Senior" cannot be combined to satisfy "Eng&Senior"
+    let eng = keygen(&attributes(&["Eng"]), &public_key, &master_key, rng).unwrap();
+    let senior = keygen(&attributes(&["Senior"]), &public_key, &master_key, rng).unwrap();
+    let mut combined = eng.clone();
+    combined
+        .arr_k
+        .insert("Senior".to_string(), senior.arr_k["Senior"]);
+
+    let secret: Gt = rng.gen();
+    let access_tree = AccessTreeParser::new("Eng&Senior").parse().unwrap();
+    let cipher_text = encrypt(&secret, b"Hello World!", &public_key, &access_tree, rng).unwrap();
+    assert!(decrypt(&cipher_text, &combined).is_err());
+}
+
+#[test]
+fn test_mismatches() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = setup(G1::one(), G2::one(), rng);
+    let (other_public_key, _) = setup(G1::one(), G2::one(), rng);
+    let secret_key = keygen(&attributes(&["A"]), &public_key, &master_key, rng).unwrap();
+    let access_tree = AccessTreeParser::new("A|B").parse().unwrap();
+    let secret: Gt = rng.gen();
+
+    // the shares of s are not part of the cipher text
+    let cipher_text = encrypt(&secret, b"Hello World!", &public_key, &access_tree, rng).unwrap();
+    assert!(cipher_text
+        .header
+        .access_tree
+        .get_attributes()
+        .iter()
+        .all(|leaf| leaf.value.is_none()));
+
+    let cipher_text = encrypt(
+        &secret,
+        b"Hello World!",
+        &other_public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+        AbeErrorKind::SystemMismatch
+    );
+
+    let mut cipher_text =
+        encrypt(&secret, b"Hello World!", &public_key, &access_tree, rng).unwrap();
+    cipher_text.header.arr_d.pop();
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).err().unwrap().kind,
+        AbeErrorKind::LeafMismatch
+    );
+
+    assert!(keygen(&[], &public_key, &master_key, rng).is_err());
+}
+
+#[test]
+fn test_serialization() {
+    let rng = &mut rand::thread_rng();
+    let (public_key, master_key) = setup(G1::one(), G2::one(), rng);
+    let secret_key = keygen(
+        &attributes(&["Eng", "Senior"]),
+        &public_key,
+        &master_key,
+        rng,
+    )
+    .unwrap();
+
+    let access_tree = AccessTreeParser::new("Eng&Senior").parse().unwrap();
+    let secret: Gt = rng.gen();
+    let cipher_text = encrypt_with(
+        &Aes256GcmSiv,
+        &secret,
+        b"Hello World!",
+        &public_key,
+        &access_tree,
+        rng,
+    )
+    .unwrap();
+    assert_eq!(cipher_text.header.algorithm, Aes256GcmSiv::ALGORITHM);
+
+    let secret_key = AbeWatersSecretKey::from_armored(&secret_key.to_armored().unwrap()).unwrap();
+    let cipher_text: AbeWatersCipherText =
+        serde_json::from_str(&serde_json::to_string(&cipher_text).unwrap()).unwrap();
+    assert_eq!(
+        decrypt(&cipher_text, &secret_key).unwrap().message,
+        b"Hello World!"
+    );
+}